@@ -0,0 +1,28 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::ByteBuffer;
+use std::time::Instant;
+
+const ROUNDS: usize = 200_000;
+
+fn run(inline: bool) -> f64 {
+    ByteBuffer::set_inline_release(inline);
+
+    let start = Instant::now();
+    for i in 0..ROUNDS {
+        let mut buffer = ByteBuffer::slice();
+        buffer.as_writable()[0] = i as u8;
+    }
+
+    start.elapsed().as_nanos() as f64 / ROUNDS as f64
+}
+
+fn main() {
+    ByteBuffer::init(64, 1024);
+
+    // warm up the pool and the worker
+    run(false);
+
+    println!("Released by the worker: {:.1} ns/op", run(false));
+    println!("Released inline:        {:.1} ns/op", run(true));
+}
@@ -1,56 +1,266 @@
 #![allow(dead_code)]
 
 use crate::channel::{Receiver, Sender};
-use crate::lock::{lock, unlock};
+use crate::lock::{lock, unlock, LockGuard};
 use crate::utils::*;
+use std::cell::UnsafeCell;
 use std::io::ErrorKind;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use std::vec;
 
 const DEFAULT_GROWTH: usize = 4;
 const DEFAULT_CAPACITY: usize = 512;
+const RESERVE_BACKOFF: Duration = Duration::from_millis(1);
 
-static mut BUFFER: Option<BufferPool> = None;
-static mut SIZE_CAP: AtomicUsize = AtomicUsize::new(512);
+/// The element types that can be pooled by a `TypedBufferPool`. Each element type is backed by its
+/// own global pool and cleanup worker. The trait is sealed, and it's implemented for the primitive
+/// integer and floating-point types, whose default value is all zero bits.
+pub trait PoolElement: sealed::Sealed + Copy + Default + Send + Sync + 'static {}
+
+mod sealed {
+    use super::Registry;
+
+    pub trait Sealed: Sized {
+        /// The global states of the pool for the element type.
+        fn registry() -> &'static Registry<Self>;
+    }
+}
+
+macro_rules! pool_element {
+    ($($ty:ty),*) => {
+        $(
+            impl sealed::Sealed for $ty {
+                fn registry() -> &'static Registry<$ty> {
+                    static REGISTRY: Registry<$ty> = Registry::new();
+                    &REGISTRY
+                }
+            }
+
+            impl PoolElement for $ty {}
+        )*
+    };
+}
+
+pool_element!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+/// The reasons that we are unable to reserve a slice from the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceError {
+    /// All slices in the pool are in use at the moment.
+    Empty,
+
+    /// We've failed to obtain the lock of the pool in time, which is a sign of heavy contention.
+    LockTimeout,
+
+    /// The pool of the element type has not been initialized yet.
+    NotInitialized,
+
+    /// All slices in the pool are in use, and the pool can't be extended beyond its size limit.
+    CapReached,
+}
+
+/// What `slice` does when all slices are in use and the pool can't be extended beyond its size
+/// limit, see `TypedBufferPool::set_on_cap_reached`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapPolicy {
+    /// Hand out an unpooled slice allocated on the spot, which is the default policy.
+    Fallback,
+
+    /// Wait up to the timeout for a slice to be released back to the pool, i.e. apply backpressure
+    /// to the callers. `slice` falls back to an unpooled slice if none is released in time.
+    Block(Duration),
+
+    /// Fail fast, such that `slice_checked` returns `None` right away. `slice` can't fail, so it
+    /// still falls back to an unpooled slice.
+    Error,
+}
+
+/// How the content of a slice is cleared when the slice is reset, i.e. before it's reused.
+#[derive(Debug, Clone, Copy)]
+pub enum ClearPolicy<E> {
+    /// Overwrite every element with `E::default()`, which is the default policy.
+    Zero,
+
+    /// Leave the stale content as is, which saves the cost of clearing when the caller guarantees
+    /// to overwrite the whole slice before reading it. The slice is still padded or cut to the
+    /// capacity, and the written mark is still reset.
+    None,
+
+    /// Clear the content with the given handle, e.g. to scrub the sensitive data in a specific way.
+    /// The elements the slice is padded with are always `E::default()`.
+    Custom(fn(&mut [E])),
+}
+
+impl<E: PoolElement> ClearPolicy<E> {
+    #[inline]
+    pub(crate) fn apply(&self, slice: &mut [E]) {
+        match self {
+            ClearPolicy::Zero => slice.iter_mut().for_each(|val| *val = E::default()),
+            ClearPolicy::None => {}
+            ClearPolicy::Custom(handle) => handle(slice),
+        }
+    }
+}
+
+/// The global states of the buffer pool of a single element type, since statics can't be generic.
+/// The type is public only to be named by the sealed trait, it can't be reached outside the crate.
+pub struct Registry<E> {
+    /// The buffer pool, `None` if the pool has not been initialized.
+    buffer: UnsafeCell<Option<BufferPool<E>>>,
+
+    /// The spin lock guarding the operations on the buffer pool.
+    lock: AtomicBool,
+
+    size_cap: AtomicUsize,
+
+    /// What to do when the pool is exhausted at the size limit, and how many times it has happened.
+    cap_policy: Mutex<CapPolicy>,
+    cap_hits: AtomicUsize,
+
+    auto_trim: AtomicBool,
+
+    /// If the slices shall be released on the dropping thread rather than the cleanup worker.
+    inline_release: AtomicBool,
+
+    /// The signal raised when a slice has been released back to the pool, and the number of callers
+    /// waiting on the signal, such that we only raise the signal if someone is actually waiting.
+    release_lock: Mutex<()>,
+    release_signal: Condvar,
+    release_waiters: AtomicUsize,
+
+    /// The runtime of the buffer pool, `None` if the pool has not been initialized.
+    runtime: Mutex<Option<Runtime>>,
+
+    /// The slice capacity expected by the callers, 0 if no expectation has been set.
+    expected_capacity: AtomicUsize,
+
+    /// The number of slices being checked out at the moment, i.e. `(pooled, fallback)`.
+    outstanding: (AtomicUsize, AtomicUsize),
+}
+
+impl<E> Registry<E> {
+    const fn new() -> Self {
+        Registry {
+            buffer: UnsafeCell::new(None),
+            lock: AtomicBool::new(false),
+            size_cap: AtomicUsize::new(512),
+            cap_policy: Mutex::new(CapPolicy::Fallback),
+            cap_hits: AtomicUsize::new(0),
+            auto_trim: AtomicBool::new(false),
+            inline_release: AtomicBool::new(false),
+            release_lock: Mutex::new(()),
+            release_signal: Condvar::new(),
+            release_waiters: AtomicUsize::new(0),
+            runtime: Mutex::new(None),
+            expected_capacity: AtomicUsize::new(0),
+            outstanding: (AtomicUsize::new(0), AtomicUsize::new(0)),
+        }
+    }
+}
+
+/// The buffer pool is guarded by the spin lock, or only accessed in the ways that the original
+/// global `static mut` was.
+unsafe impl<E: Send> Sync for Registry<E> {}
+
+pub(crate) struct Runtime {
+    /// The handle to the cleanup worker thread, `None` if slices are cleaned up synchronously.
+    pub(crate) worker: Option<JoinHandle<()>>,
+
+    /// The `(size, capacity)` that the pool has been initialized with, later calls to `init` are
+    /// ignored until the pool is torn down. The capacity follows `set_slice_capacity` afterwards.
+    pub(crate) params: (usize, usize),
+}
 
 struct Store {
     buf: Vec<u8>,
     taken: AtomicBool,
 }
 
-pub(crate) struct BufferPool {
-    store: Vec<Vec<u8>>,
+pub(crate) struct BufferPool<E> {
+    store: Vec<Vec<E>>,
+    arena: Option<AlignedBuf<E>>,
+    pool: Vec<usize>,
     slice_capacity: usize,
-    worker_chan: Sender<WorkerOp>,
+    /// How the content of a slice is cleared when it's reset
+    clear: ClearPolicy<E>,
+    /// If the slices are allocated without being zeroed, see `TypedBufferPool::init_unzeroed`
+    unzeroed: bool,
+    /// The channel to the cleanup worker, or `None` if slices are cleaned up synchronously
+    worker_chan: Option<Sender<WorkerOp>>,
     closing: AtomicBool,
     barrier: AtomicBool,
     visitors: AtomicUsize,
 }
 
-pub(crate) trait PoolManagement {
-    fn make(store: Vec<Vec<u8>>, slice_capacity: usize, worker_chan: Sender<WorkerOp>);
+pub(crate) trait PoolManagement<E> {
+    fn make(
+        store: Vec<Vec<E>>,
+        slice_capacity: usize,
+        clear: ClearPolicy<E>,
+        unzeroed: bool,
+        worker_chan: Option<Sender<WorkerOp>>,
+    );
+    fn make_arena(
+        arena: AlignedBuf<E>,
+        slice_capacity: usize,
+        worker_chan: Option<Sender<WorkerOp>>,
+    );
+    fn clear_policy() -> ClearPolicy<E>;
+    fn is_unzeroed() -> bool;
+    fn lock_runtime() -> MutexGuard<'static, Option<Runtime>>;
+    fn expected_capacity() -> &'static AtomicUsize;
+    fn outstanding() -> &'static (AtomicUsize, AtomicUsize);
+    fn cap_hits() -> &'static AtomicUsize;
+    fn cap_policy() -> CapPolicy;
     fn default_capacity() -> usize;
+    fn alignment() -> usize;
     fn slice_stat(id: usize, query: SliceStatusQuery) -> usize;
     fn handle_work(rx: Receiver<WorkerOp>);
-    fn exec(command: BufOp) -> Option<usize>;
+    fn exec(command: BufOp<E>) -> Option<usize>;
+    fn reserve_timeout(timeout: Duration) -> Option<usize>;
+    fn try_reserve_slice() -> Result<usize, SliceError>;
+    fn reserve_slice() -> Result<usize, SliceError>;
     fn reset_and_release(id: usize, dirty: bool);
-    fn get_writable(id: usize) -> Result<&'static mut Vec<u8>, ErrorKind>;
-    fn get_readable(id: usize) -> Result<&'static Vec<u8>, ErrorKind>;
+    fn get_writable(id: usize) -> Result<&'static mut Vec<E>, ErrorKind>;
+    fn get_readable(id: usize) -> Result<&'static Vec<E>, ErrorKind>;
+    fn get_slice_mut(id: usize) -> Result<&'static mut [E], ErrorKind>;
+    fn get_slice(id: usize) -> Result<&'static [E], ErrorKind>;
+    fn for_each_slice<F: FnMut(usize, &[E])>(f: F);
     fn reset_slice(id: usize);
     fn set_size_limit(limit: usize);
+    fn set_cap_policy(policy: CapPolicy);
+    fn set_auto_trim(trim: bool);
+    fn set_inline_release(inline: bool);
+    fn set_slice_capacity(capacity: usize) -> Result<(), ErrorKind>;
+    fn teardown();
 }
 
-impl PoolManagement for BufferPool {
-    fn make(store: Vec<Vec<u8>>, slice_capacity: usize, worker_chan: Sender<WorkerOp>) {
-        unsafe {
-            if store.len() > SIZE_CAP.load(Ordering::SeqCst) {
-                SIZE_CAP.store(store.len(), Ordering::SeqCst);
-            }
+impl<E: PoolElement> PoolManagement<E> for BufferPool<E> {
+    fn make(
+        store: Vec<Vec<E>>,
+        slice_capacity: usize,
+        clear: ClearPolicy<E>,
+        unzeroed: bool,
+        worker_chan: Option<Sender<WorkerOp>>,
+    ) {
+        let registry = E::registry();
+        if store.len() > registry.size_cap.load(Ordering::SeqCst) {
+            registry.size_cap.store(store.len(), Ordering::SeqCst);
+        }
 
-            BUFFER.replace(BufferPool {
+        let pool = (0..store.len()).collect();
+
+        unsafe {
+            (*registry.buffer.get()).replace(BufferPool {
                 store,
-                //                pool,
+                arena: None,
+                pool,
                 slice_capacity,
+                clear,
+                unzeroed,
                 worker_chan,
                 closing: AtomicBool::new(false),
                 barrier: AtomicBool::new(false),
@@ -59,8 +269,66 @@ impl PoolManagement for BufferPool {
         }
     }
 
+    fn clear_policy() -> ClearPolicy<E> {
+        buffer_ref::<E>().map_or(ClearPolicy::Zero, |buf| buf.clear)
+    }
+
+    fn is_unzeroed() -> bool {
+        buffer_ref::<E>().is_some_and(|buf| buf.unzeroed)
+    }
+
+    fn make_arena(
+        arena: AlignedBuf<E>,
+        slice_capacity: usize,
+        worker_chan: Option<Sender<WorkerOp>>,
+    ) {
+        // the arena can't be extended, so the pool size is fixed once it's made
+        let count = arena.len().checked_div(slice_capacity).unwrap_or(0);
+
+        unsafe {
+            (*E::registry().buffer.get()).replace(BufferPool {
+                store: Vec::new(),
+                arena: Some(arena),
+                pool: (0..count).collect(),
+                slice_capacity,
+                clear: ClearPolicy::Zero,
+                unzeroed: false,
+                worker_chan,
+                closing: AtomicBool::new(false),
+                barrier: AtomicBool::new(false),
+                visitors: AtomicUsize::new(0),
+            });
+        }
+    }
+
+    fn lock_runtime() -> MutexGuard<'static, Option<Runtime>> {
+        E::registry()
+            .runtime
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn expected_capacity() -> &'static AtomicUsize {
+        &E::registry().expected_capacity
+    }
+
+    fn outstanding() -> &'static (AtomicUsize, AtomicUsize) {
+        &E::registry().outstanding
+    }
+
+    fn cap_hits() -> &'static AtomicUsize {
+        &E::registry().cap_hits
+    }
+
+    fn cap_policy() -> CapPolicy {
+        *E::registry()
+            .cap_policy
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
     fn default_capacity() -> usize {
-        if let Some(buf) = buffer_ref() {
+        if let Some(buf) = buffer_ref::<E>() {
             buf.slice_capacity
         } else {
             // guess the capacity
@@ -68,8 +336,22 @@ impl PoolManagement for BufferPool {
         }
     }
 
+    fn alignment() -> usize {
+        buffer_ref::<E>()
+            .and_then(|buf| buf.arena.as_ref())
+            .map_or(1, AlignedBuf::align)
+    }
+
     fn slice_stat(id: usize, query: SliceStatusQuery) -> usize {
-        if let Some(buf) = buffer_ref() {
+        if let Some(buf) = buffer_ref::<E>() {
+            if id >= buf.slice_count() {
+                return 0;
+            }
+
+            if buf.arena.is_some() {
+                return buf.slice_capacity;
+            }
+
             match query {
                 SliceStatusQuery::Length => buf.store[id].len(),
                 SliceStatusQuery::Capacity => buf.store[id].capacity(),
@@ -84,7 +366,7 @@ impl PoolManagement for BufferPool {
             match rx.recv() {
                 Ok(message) => {
                     match message {
-                        WorkerOp::Cleanup(id, dirty) => BufferPool::exec(BufOp::Release(id, dirty)),
+                        WorkerOp::Cleanup(id, dirty) => cleanup::<E>(id, dirty),
                         WorkerOp::Shutdown => return,
                     };
                 }
@@ -93,68 +375,184 @@ impl PoolManagement for BufferPool {
         }
     }
 
-    fn exec(command: BufOp) -> Option<usize> {
-        if lock().is_err() {
+    fn exec(command: BufOp<E>) -> Option<usize> {
+        let registry = E::registry();
+        if lock(&registry.lock).is_err() {
             return None;
         }
 
+        let release = matches!(command, BufOp::Release(..) | BufOp::ReleaseAndExtend(..));
+
         let mut result: Option<usize> = None;
-        if let Some(buf) = buffer_mut() {
+        if let Some(buf) = buffer_mut::<E>() {
             match command {
                 BufOp::Reserve(forced) => {
-                    if let Some(id) = buf.try_reserve() {
-                        result = Some(id)
-                    } else if forced {
-                        //TODO: try extend, and if failed, generate fallback
-                        result = Some(buf.extend(DEFAULT_GROWTH));
-                    }
+                    result = if forced {
+                        let size_cap = registry.size_cap.load(Ordering::SeqCst);
+                        buf.reserve_or_extend(size_cap).ok()
+                    } else {
+                        buf.try_reserve()
+                    };
                 }
                 BufOp::Release(id, dirty) => {
-                    buf.release(id);
-
-                    if dirty {
+                    if dirty || buf.is_stale(id) {
                         buf.reset(id);
                     }
+
+                    buf.release(id);
                 }
                 BufOp::Extend(count) => {
-                    //TODO: try extend, and if failed, fallback to None
                     result = Some(buf.extend(count));
                 }
+                BufOp::Trim(id) => {
+                    buf.trim(id);
+                }
                 BufOp::ReleaseAndExtend(vec, dirty) => {
-                    if buf.store.len() < unsafe { SIZE_CAP.load(Ordering::SeqCst) } {
+                    // the arena can't adopt a foreign vec, let it drop
+                    let size_cap = registry.size_cap.load(Ordering::SeqCst);
+                    if buf.arena.is_none() && buf.store.len() < size_cap {
                         let id = buf.store.len();
 
                         buf.store.push(vec);
-                        //                        buf.pool.push(id);
 
-                        if dirty {
+                        if dirty || buf.is_stale(id) {
                             buf.reset(id);
                         }
+
+                        buf.release(id);
                     }
                 }
             }
         }
 
-        unlock();
+        unlock(&registry.lock);
+
+        // wake up the callers waiting for a free slice
+        if release && registry.release_waiters.load(Ordering::SeqCst) > 0 {
+            let _lock = registry
+                .release_lock
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            registry.release_signal.notify_all();
+        }
+
+        result
+    }
+
+    fn try_reserve_slice() -> Result<usize, SliceError> {
+        let registry = E::registry();
+        if lock(&registry.lock).is_err() {
+            return Err(SliceError::LockTimeout);
+        }
+
+        let result = match buffer_mut::<E>() {
+            Some(buf) => buf.try_reserve().ok_or(SliceError::Empty),
+            None => Err(SliceError::NotInitialized),
+        };
+
+        unlock(&registry.lock);
+        result
+    }
+
+    fn reserve_slice() -> Result<usize, SliceError> {
+        let registry = E::registry();
+        if lock(&registry.lock).is_err() {
+            return Err(SliceError::LockTimeout);
+        }
+
+        let result = match buffer_mut::<E>() {
+            Some(buf) => buf.reserve_or_extend(registry.size_cap.load(Ordering::SeqCst)),
+            None => Err(SliceError::NotInitialized),
+        };
+
+        unlock(&registry.lock);
+
+        if result == Err(SliceError::CapReached) {
+            registry.cap_hits.fetch_add(1, Ordering::Relaxed);
+        }
+
+        result
+    }
+
+    fn reserve_timeout(timeout: Duration) -> Option<usize> {
+        let deadline = Instant::now() + timeout;
+        let registry = E::registry();
+
+        registry.release_waiters.fetch_add(1, Ordering::SeqCst);
+        let mut lock = registry
+            .release_lock
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        let result = loop {
+            if let Some(id) = BufferPool::<E>::exec(BufOp::Reserve(false)) {
+                break Some(id);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                break None;
+            }
+
+            // wait for a release signal, but back off for a short period only in case we've failed
+            // to reserve because of the lock contention rather than an empty pool.
+            let wait = RESERVE_BACKOFF.min(deadline - now);
+            lock = match registry.release_signal.wait_timeout(lock, wait) {
+                Ok((lock, _)) => lock,
+                Err(err) => err.into_inner().0,
+            };
+        };
+
+        drop(lock);
+        registry.release_waiters.fetch_sub(1, Ordering::SeqCst);
+
         result
     }
 
+    fn teardown() {
+        let registry = E::registry();
+
+        // wait for the worker to finish the ongoing operation
+        while lock(&registry.lock).is_err() {
+            thread::yield_now();
+        }
+
+        let pool = unsafe { (*registry.buffer.get()).take() };
+        unlock(&registry.lock);
+
+        // the worker will be signaled to quit when the pool is dropped, the pending cleanups will be
+        // no-op since the pool is gone.
+        drop(pool);
+    }
+
     fn reset_and_release(id: usize, dirty: bool) {
-        if let Some(buf) = buffer_ref() {
-            buf.worker_chan
-                .send(WorkerOp::Cleanup(id, dirty))
-                .unwrap_or_else(|err| {
-                    eprintln!("Failed to release buffer slice: {}, err: {}", id, err);
-                });
+        if let Some(buf) = buffer_ref::<E>() {
+            let inline = E::registry().inline_release.load(Ordering::Acquire);
+
+            match buf.worker_chan {
+                Some(ref chan) if !inline => {
+                    chan.send(WorkerOp::Cleanup(id, dirty))
+                        .unwrap_or_else(|err| {
+                            eprintln!("Failed to release buffer slice: {}, err: {}", id, err);
+                        });
+                }
+                _ => {
+                    cleanup::<E>(id, dirty);
+                }
+            }
         }
     }
 
-    fn get_writable(id: usize) -> Result<&'static mut Vec<u8>, ErrorKind> {
-        if let Some(buf) = buffer_mut() {
+    fn get_writable(id: usize) -> Result<&'static mut Vec<E>, ErrorKind> {
+        if let Some(buf) = buffer_mut::<E>() {
             if buf.closing.load(Ordering::SeqCst) {
                 return Err(ErrorKind::NotConnected);
             }
 
+            if buf.arena.is_some() {
+                return Err(ErrorKind::Unsupported);
+            }
+
             if id < buf.store.len() {
                 return Ok(&mut buf.store[id]);
             } else {
@@ -165,12 +563,16 @@ impl PoolManagement for BufferPool {
         Err(ErrorKind::NotConnected)
     }
 
-    fn get_readable(id: usize) -> Result<&'static Vec<u8>, ErrorKind> {
-        if let Some(buf) = buffer_ref() {
+    fn get_readable(id: usize) -> Result<&'static Vec<E>, ErrorKind> {
+        if let Some(buf) = buffer_ref::<E>() {
             if buf.closing.load(Ordering::SeqCst) {
                 return Err(ErrorKind::NotConnected);
             }
 
+            if buf.arena.is_some() {
+                return Err(ErrorKind::Unsupported);
+            }
+
             if id < buf.store.len() {
                 return Ok(&buf.store[id]);
             } else {
@@ -181,76 +583,234 @@ impl PoolManagement for BufferPool {
         Err(ErrorKind::NotConnected)
     }
 
+    fn get_slice_mut(id: usize) -> Result<&'static mut [E], ErrorKind> {
+        if let Some(buf) = buffer_mut::<E>() {
+            if buf.closing.load(Ordering::SeqCst) {
+                return Err(ErrorKind::NotConnected);
+            }
+
+            if id >= buf.slice_count() {
+                return Err(ErrorKind::InvalidData);
+            }
+
+            let capacity = buf.slice_capacity;
+            return Ok(match buf.arena.as_mut() {
+                Some(arena) => &mut arena[id * capacity..(id + 1) * capacity],
+                None => buf.store[id].as_mut_slice(),
+            });
+        }
+
+        Err(ErrorKind::NotConnected)
+    }
+
+    fn get_slice(id: usize) -> Result<&'static [E], ErrorKind> {
+        if let Some(buf) = buffer_ref::<E>() {
+            if buf.closing.load(Ordering::SeqCst) {
+                return Err(ErrorKind::NotConnected);
+            }
+
+            if id >= buf.slice_count() {
+                return Err(ErrorKind::InvalidData);
+            }
+
+            let capacity = buf.slice_capacity;
+            return Ok(match buf.arena.as_ref() {
+                Some(arena) => &arena[id * capacity..(id + 1) * capacity],
+                None => buf.store[id].as_slice(),
+            });
+        }
+
+        Err(ErrorKind::NotConnected)
+    }
+
+    fn for_each_slice<F: FnMut(usize, &[E])>(mut f: F) {
+        // the lock shall be released even if `f` panics
+        let _guard = match LockGuard::acquire(&E::registry().lock) {
+            Ok(guard) => guard,
+            Err(_) => return,
+        };
+
+        let buf = match buffer_ref::<E>() {
+            Some(buf) => buf,
+            None => return,
+        };
+
+        // only the free slices can be read safely: the checked-out ones may be written by their
+        // owners at the same time, and the released ones are reset under the lock before they're
+        // back in the free list.
+        for &id in buf.pool.iter() {
+            if let Ok(slice) = BufferPool::<E>::get_slice(id) {
+                f(id, slice);
+            }
+        }
+    }
+
     fn reset_slice(id: usize) {
-        if let Some(buf) = buffer_mut() {
+        if let Some(buf) = buffer_mut::<E>() {
             buf.reset(id);
         }
     }
 
     fn set_size_limit(limit: usize) {
-        unsafe {
-            SIZE_CAP.store(limit, Ordering::SeqCst);
+        E::registry().size_cap.store(limit, Ordering::SeqCst);
+    }
+
+    fn set_cap_policy(policy: CapPolicy) {
+        *E::registry()
+            .cap_policy
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = policy;
+    }
+
+    fn set_auto_trim(trim: bool) {
+        E::registry().auto_trim.store(trim, Ordering::Release);
+    }
+
+    fn set_inline_release(inline: bool) {
+        E::registry()
+            .inline_release
+            .store(inline, Ordering::Release);
+    }
+
+    fn set_slice_capacity(capacity: usize) -> Result<(), ErrorKind> {
+        let registry = E::registry();
+        if lock(&registry.lock).is_err() {
+            return Err(ErrorKind::TimedOut);
         }
+
+        let result = match buffer_mut::<E>() {
+            // the slices are carved out of the arena by the capacity, which can't be changed
+            Some(buf) if buf.arena.is_some() => Err(ErrorKind::Unsupported),
+            Some(buf) => {
+                buf.slice_capacity = capacity;
+
+                // the slices being checked out will be resized once they're released
+                for i in 0..buf.pool.len() {
+                    let id = buf.pool[i];
+                    buf.reset(id);
+                }
+
+                Ok(())
+            }
+            None => Err(ErrorKind::NotConnected),
+        };
+
+        unlock(&registry.lock);
+        result
     }
 }
 
 trait PoolOps {
+    fn slice_count(&self) -> usize;
     fn try_reserve(&mut self) -> Option<usize>;
+    fn reserve_or_extend(&mut self, size_cap: usize) -> Result<usize, SliceError>;
     fn release(&mut self, id: usize);
     fn reset(&mut self, id: usize);
+    fn is_stale(&self, id: usize) -> bool;
     fn extend(&mut self, additional: usize) -> usize;
     fn expand_slice(&mut self, id: usize, additional: usize);
+    fn trim(&mut self, id: usize);
 }
 
-impl PoolOps for BufferPool {
+impl<E: PoolElement> PoolOps for BufferPool<E> {
+    #[inline]
+    fn slice_count(&self) -> usize {
+        match self.arena.as_ref() {
+            Some(arena) => arena.len().checked_div(self.slice_capacity).unwrap_or(0),
+            None => self.store.len(),
+        }
+    }
+
     #[inline]
     fn try_reserve(&mut self) -> Option<usize> {
-        //        self.pool.pop()
-        None
+        self.pool.pop()
+    }
+
+    fn reserve_or_extend(&mut self, size_cap: usize) -> Result<usize, SliceError> {
+        if let Some(id) = self.try_reserve() {
+            return Ok(id);
+        }
+
+        // grow by the default steps, but never beyond the size limit
+        let room = size_cap.saturating_sub(self.slice_count());
+        if room == 0 || self.extend(DEFAULT_GROWTH.min(room)) == 0 {
+            return Err(SliceError::CapReached);
+        }
+
+        self.try_reserve().ok_or(SliceError::Empty)
     }
 
     fn release(&mut self, id: usize) {
-        if id < self.store.len() {
-            //            self.pool.push(id);
+        if id < self.slice_count() {
+            self.pool.push(id);
         }
     }
 
     fn reset(&mut self, id: usize) {
-        assert!(id < self.store.len());
+        assert!(id < self.slice_count());
 
-        let capacity: usize = self.slice_capacity;
-        let vec_cap: usize = self.store[id].capacity();
+        if let Some(arena) = self.arena.as_mut() {
+            let capacity = self.slice_capacity;
+            self.clear
+                .apply(&mut arena[id * capacity..(id + 1) * capacity]);
 
-        if vec_cap > capacity {
-            self.store[id].truncate(capacity);
-        } else if vec_cap < capacity {
-            self.store[id].reserve(capacity - vec_cap);
+            return;
         }
 
-        self.store[id].iter_mut().for_each(|val| {
-            *val = 0;
-        });
+        let capacity: usize = self.slice_capacity;
+        let slice = &mut self.store[id];
+
+        // clear the bytes we will keep, then pad or cut the slice to exactly the configured capacity,
+        // such that the length of a reused slice is deterministic, and so is the content unless the
+        // clear policy says otherwise.
+        slice.truncate(capacity);
+        self.clear.apply(slice);
+        slice.resize(capacity, E::default());
+    }
+
+    /// If the slice is sized for a capacity other than the configured one, e.g. the slice has been
+    /// checked out when the capacity is changed, such that it shall be reset even if it's clean.
+    #[inline]
+    fn is_stale(&self, id: usize) -> bool {
+        self.arena.is_none()
+            && self
+                .store
+                .get(id)
+                .is_some_and(|vec| vec.len() != self.slice_capacity)
     }
 
     fn extend(&mut self, additional: usize) -> usize {
         assert!(additional > 0);
 
-        //TODO: do not blow up the roof
+        // the arena is allocated once and for all
+        if self.arena.is_some() {
+            return 0;
+        }
 
         let capacity = self.slice_capacity;
+        let start = self.store.len();
 
         self.store.reserve(additional);
+        self.pool.reserve(additional);
+
+        (0..additional).for_each(|i| {
+            let slice = if self.unzeroed {
+                // the slices are flagged as uninitialized when they're checked out
+                unsafe { make_unzeroed(capacity) }
+            } else {
+                vec::from_elem(E::default(), capacity)
+            };
 
-        (0..additional).for_each(|_| {
-            self.store.push(vec::from_elem(0, capacity));
+            self.store.push(slice);
+            self.pool.push(start + i);
         });
 
-        // return the last element in the buffer
-        self.store.len() - 1
+        // return the number of slices added to the buffer
+        additional
     }
 
     fn expand_slice(&mut self, id: usize, additional: usize) {
-        if id >= self.store.len() {
+        if self.arena.is_some() || id >= self.store.len() {
             return;
         }
 
@@ -259,29 +819,91 @@ impl PoolOps for BufferPool {
 
         let end = self.store[id].capacity();
         (start..end).for_each(|_| {
-            self.store[id].push(0);
+            self.store[id].push(E::default());
         });
     }
+
+    fn trim(&mut self, id: usize) {
+        if self.arena.is_some() || id >= self.store.len() {
+            return;
+        }
+
+        let capacity = self.slice_capacity;
+        let vec = &mut self.store[id];
+
+        // only trim the slice if it has grown beyond the capacity, this will reallocate the vec.
+        if vec.capacity() > capacity {
+            vec.truncate(capacity);
+            vec.shrink_to(capacity);
+        }
+    }
 }
 
-impl Drop for BufferPool {
+impl<E> Drop for BufferPool<E> {
     fn drop(&mut self) {
         *self.closing.get_mut() = true;
 
-        self.worker_chan
-            .send(WorkerOp::Shutdown)
-            .unwrap_or_else(|err| {
+        if let Some(ref chan) = self.worker_chan {
+            chan.send(WorkerOp::Shutdown).unwrap_or_else(|err| {
                 eprintln!("Failed to close the worker thread, error code: {}", err);
             });
+        }
+    }
+}
+
+/// Reset the slice and release it back to the pool, either on the worker thread or synchronously if
+/// there's no worker.
+fn cleanup<E: PoolElement>(id: usize, dirty: bool) {
+    // trim before the release, since the slice could be reserved right after being released, and
+    // trimming will reallocate the vec.
+    if E::registry().auto_trim.load(Ordering::Acquire) {
+        BufferPool::<E>::exec(BufOp::Trim(id));
     }
+
+    BufferPool::<E>::exec(BufOp::Release(id, dirty));
 }
 
 #[inline]
-fn buffer_ref() -> Option<&'static BufferPool> {
-    unsafe { BUFFER.as_ref() }
+fn buffer_ref<E: PoolElement>() -> Option<&'static BufferPool<E>> {
+    unsafe { (*E::registry().buffer.get()).as_ref() }
 }
 
 #[inline]
-fn buffer_mut() -> Option<&'static mut BufferPool> {
-    unsafe { BUFFER.as_mut() }
+fn buffer_mut<E: PoolElement>() -> Option<&'static mut BufferPool<E>> {
+    unsafe { (*E::registry().buffer.get()).as_mut() }
+}
+
+#[cfg(test)]
+mod buffer_tests {
+    use super::*;
+    use crate::manager::TypedBufferPool;
+
+    // each test uses its own element type, since the pools and the trim flag are global.
+
+    #[test]
+    fn auto_trim_on_release() {
+        TypedBufferPool::<u32>::init_sync(1, 8);
+        TypedBufferPool::<u32>::set_auto_trim(true);
+
+        let buffer = TypedBufferPool::<u32>::slice();
+        buffer_mut::<u32>().unwrap().expand_slice(0, 64);
+        assert!(buffer_ref::<u32>().unwrap().store[0].capacity() > 8);
+
+        // the slice is trimmed back to the capacity once it's released
+        drop(buffer);
+        assert_eq!(buffer_ref::<u32>().unwrap().store[0].capacity(), 8);
+    }
+
+    #[test]
+    fn no_auto_trim_keeps_capacity() {
+        TypedBufferPool::<u64>::init_sync(1, 8);
+
+        let buffer = TypedBufferPool::<u64>::slice();
+        buffer_mut::<u64>().unwrap().expand_slice(0, 64);
+        let grown = buffer_ref::<u64>().unwrap().store[0].capacity();
+        assert!(grown >= 8 + 64);
+
+        drop(buffer);
+        assert_eq!(buffer_ref::<u64>().unwrap().store[0].capacity(), grown);
+    }
 }
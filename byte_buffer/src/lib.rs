@@ -28,7 +28,7 @@
 //!   io::repeat(0b101).read_exact(buffer.as_writable()).unwrap();
 //!
 //!   // Read the data out. The buffer will be released back to the pool after going out of the scope
-//!   assert_eq!(buffer.as_readable().unwrap(), [0b101, 0b101, 0b101]);
+//!   assert_eq!(buffer.read().unwrap(), [0b101, 0b101, 0b101]);
 //! }
 //! ```
 
@@ -47,13 +47,13 @@ pub mod prelude {
 #[macro_export]
 macro_rules! slice_buffer {
     () => {{
-        crate::manager::slice()
+        $crate::manager::ByteBuffer::slice()
     }};
 }
 
 #[macro_export]
 macro_rules! try_slice {
     () => {{
-        crate::manager::try_slice()
+        $crate::manager::ByteBuffer::try_slice()
     }};
 }
@@ -1,14 +1,21 @@
 use std::io::ErrorKind;
-use std::sync::atomic::{self, AtomicBool, Ordering};
+use std::hint;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
 
 const LOCK_TIMEOUT: usize = 64;
-static LOCK: AtomicBool = AtomicBool::new(false);
+const RELAX_CAP: usize = 6;
 
-pub(crate) fn lock() -> Result<(), ErrorKind> {
+static PARALLELISM: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn lock(flag: &AtomicBool) -> Result<(), ErrorKind> {
     let mut count = 1;
 
     loop {
-        if let Ok(true) = LOCK.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed) {
+        if flag
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
             break;
         }
 
@@ -24,13 +31,49 @@ pub(crate) fn lock() -> Result<(), ErrorKind> {
 }
 
 #[inline]
-pub(crate) fn unlock() {
-    LOCK.store(false, Ordering::SeqCst);
+pub(crate) fn unlock(flag: &AtomicBool) {
+    flag.store(false, Ordering::SeqCst);
+}
+
+/// Hold the lock until the guard is dropped, such that the lock is released even if the holder
+/// panics, e.g. while running a callback passed in by the user.
+pub(crate) struct LockGuard<'a>(&'a AtomicBool);
+
+impl<'a> LockGuard<'a> {
+    pub(crate) fn acquire(flag: &'a AtomicBool) -> Result<Self, ErrorKind> {
+        lock(flag).map(|()| LockGuard(flag))
+    }
+}
+
+impl Drop for LockGuard<'_> {
+    fn drop(&mut self) {
+        unlock(self.0);
+    }
 }
 
+/// Back off for `2 ^ count` spins with the exponent capped, or yield the thread if we're running on
+/// a single CPU, where spinning only burns the time slice of the lock holder.
 #[inline(always)]
 pub(crate) fn cpu_relax(count: usize) {
-    for _ in 0..(1 << count) {
-        atomic::spin_loop_hint()
+    if parallelism() == 1 {
+        thread::yield_now();
+        return;
     }
+
+    for _ in 0..(1 << count.min(RELAX_CAP)) {
+        hint::spin_loop()
+    }
+}
+
+#[inline]
+fn parallelism() -> usize {
+    let count = PARALLELISM.load(Ordering::Relaxed);
+    if count > 0 {
+        return count;
+    }
+
+    let count = thread::available_parallelism().map_or(1, |n| n.get());
+    PARALLELISM.store(count, Ordering::Relaxed);
+
+    count
 }
@@ -1,154 +1,1035 @@
 #![allow(dead_code)]
 
-use crate::buffer::{BufferPool, PoolManagement};
-use crate::channel::{self as channel};
+use crate::buffer::{BufferPool, PoolManagement, Runtime};
+use crate::channel::{self as channel, Sender};
 use crate::utils::*;
-use std::io::ErrorKind;
+use std::hash::{Hash, Hasher};
+use std::io::{self, ErrorKind, IoSlice, Read, Write};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ops::{Deref, Index, IndexMut, Range};
+use std::slice;
 use std::str;
-use std::sync::Once;
-use std::thread;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 use std::vec;
 
-static ONCE: Once = Once::new();
+pub use crate::buffer::{CapPolicy, ClearPolicy, PoolElement, SliceError};
 
-pub struct ByteBuffer;
+/// The pool of buffers of the element type `E`, e.g. `TypedBufferPool<f32>` for the audio frames.
+/// Each element type is backed by its own global pool and cleanup worker, which shall be initialized
+/// separately. `ByteBuffer` is the pool of bytes.
+///
+/// # Examples
+///
+/// ```
+/// extern crate byte_buffer;
+/// use byte_buffer::prelude::*;
+///
+/// TypedBufferPool::<f32>::init(4, 8);
+///
+/// let mut frame = TypedBufferPool::<f32>::slice();
+/// assert_eq!(frame.append(&[0.5, -0.5]), 2);
+/// assert_eq!(frame.read().unwrap(), [0.5, -0.5]);
+/// ```
+pub struct TypedBufferPool<E: PoolElement> {
+    _marker: PhantomData<E>,
+}
+
+/// The pool of byte buffers.
+pub type ByteBuffer = TypedBufferPool<u8>;
 
-impl ByteBuffer {
+impl<E: PoolElement> TypedBufferPool<E> {
     pub fn init(size: usize, capacity: usize) {
-        ONCE.call_once(|| {
-            let mut store = Vec::with_capacity(size);
-            let mut pool = Vec::with_capacity(size);
+        Self::init_store(size, capacity, ClearPolicy::Zero, false, true);
+    }
 
-            (0..size).for_each(|id| {
-                store.push(vec::from_elem(0, capacity));
-                pool.push(id);
-            });
+    /// Initialize the buffer pool with `per_thread` slices for each of the `threads` worker threads,
+    /// and return the computed pool size, e.g. for logging. The pool shall be sized for the maximum
+    /// number of slices checked out at the same time: too small and the callers will fall back to the
+    /// allocated vectors, too large and the memory sits idle. Since each worker usually holds a few
+    /// slices at a time (e.g. one to read into and one to write out of), the number of workers times
+    /// the slices held by each is a good estimation.
+    ///
+    /// Just like `init`, the call is ignored if the pool has been initialized, though the computed
+    /// size is still returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// let size = ByteBuffer::init_for_threads(4, 2, 16);
+    /// assert_eq!(size, 8);
+    ///
+    /// let buffers: Vec<_> = (0..size).map(|_| ByteBuffer::try_slice().unwrap()).collect();
+    /// assert!(ByteBuffer::try_slice().is_none());
+    /// ```
+    pub fn init_for_threads(threads: usize, per_thread: usize, capacity: usize) -> usize {
+        let size = threads * per_thread;
+        Self::init(size, capacity);
+        size
+    }
 
-            let (sender, receiver) = channel::bounded(8);
-            thread::spawn(move || {
-                BufferPool::handle_work(receiver);
-            });
+    /// Initialize the buffer pool the same way as `init`, but the slices will be cleared with the
+    /// `clear` policy when they're reset, instead of being zeroed. E.g. `ClearPolicy::None` skips the
+    /// clearing entirely for the callers that always overwrite the whole slice, at the risk of
+    /// reading the stale content of the last user if they don't.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init_with_clear(1, 4, ClearPolicy::Custom(|slice| slice.fill(0xff)));
+    ///
+    /// let mut buffer = ByteBuffer::slice_from(b"abcd");
+    /// buffer.reset();
+    ///
+    /// assert_eq!(buffer.as_writable(), [0xff; 4]);
+    /// ```
+    pub fn init_with_clear(size: usize, capacity: usize, clear: ClearPolicy<E>) {
+        Self::init_store(size, capacity, clear, false, true);
+    }
 
-            BufferPool::make(store, capacity, sender);
-        });
+    /// Initialize the buffer pool the same way as `init`, but the slices are allocated without being
+    /// zeroed, and they won't be cleared when they're reset either, which saves the cost of zeroing
+    /// large slices for the callers that always overwrite the slices before reading them. Use
+    /// `BufferSlice::as_uninit_mut` to fill a slice, then commit the written length via `set_len`.
+    ///
+    /// Pooled slices are handed out as uninitialized, such that the other mutable accessors (e.g.
+    /// `as_writable`) will still zero the bytes beyond the written mark before exposing them, which
+    /// forfeits the savings. The fallback vectors handed out when the pool is exhausted are zeroed.
+    ///
+    /// This is only available with the `unzeroed` feature.
+    ///
+    /// # Safety
+    ///
+    /// A reused slice still holds whatever the last user has written into it, and a fresh slice holds
+    /// uninitialized memory. The caller must not observe the slices beyond what has been written
+    /// since they were checked out, in particular via `for_each_slice`, which visits the whole slices
+    /// regardless of their written marks. Never use this pool for the data that must not leak from
+    /// one user of the slice to the next.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    /// use std::mem::MaybeUninit;
+    ///
+    /// unsafe { ByteBuffer::init_unzeroed(2, 8) };
+    ///
+    /// let mut buffer = ByteBuffer::slice();
+    /// let region = buffer.as_uninit_mut();
+    /// region[0] = MaybeUninit::new(1);
+    /// region[1] = MaybeUninit::new(2);
+    ///
+    /// unsafe { buffer.set_len(2) };
+    /// assert_eq!(buffer.read().unwrap(), [1, 2]);
+    ///
+    /// // the safe accessors zero the bytes that have not been written
+    /// assert_eq!(buffer.as_writable(), [1, 2, 0, 0, 0, 0, 0, 0]);
+    /// ```
+    #[cfg(feature = "unzeroed")]
+    pub unsafe fn init_unzeroed(size: usize, capacity: usize) {
+        Self::init_store(size, capacity, ClearPolicy::None, true, true);
     }
 
-    pub fn slice() -> BufferSlice {
-        match BufferPool::exec(BufOp::Reserve(true)) {
-            Some(val) => BufferSlice {
-                id: val,
-                fallback: None,
-                dirty: false,
-            },
-            None => BufferSlice {
-                id: 0,
-                fallback: Some(vec::from_elem(0, BufferPool::default_capacity())),
-                dirty: false,
+    /// Initialize the buffer pool the same way as `init`, but without spawning the background cleanup
+    /// worker: released slices will be reset and returned to the pool synchronously when they're
+    /// dropped. This saves a thread and a channel in single-threaded, embedded or test scenarios, at
+    /// the cost of doing the cleanup on the caller's thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init_sync(1, 8);
+    ///
+    /// let buffer = ByteBuffer::slice_from(b"hello");
+    /// drop(buffer);
+    ///
+    /// // the slice has been released synchronously
+    /// let buffer = ByteBuffer::try_slice().unwrap();
+    /// assert_eq!(buffer.remaining_capacity(), 8);
+    /// ```
+    pub fn init_sync(size: usize, capacity: usize) {
+        Self::init_store(size, capacity, ClearPolicy::Zero, false, false);
+    }
+
+    /// Initialize the buffer pool such that all `size` slices are carved out of a single contiguous
+    /// arena of `size * capacity` bytes, which keeps consecutively allocated slices close to each
+    /// other in memory and improves the cache behavior for workers touching many slices.
+    ///
+    /// The arena is allocated once and won't be extended: when all slices are checked out, new
+    /// slices will be backed by fallback vectors instead. Since the arena slices are not standalone
+    /// vectors, `BufferSlice::read_as_vec` will not be available for pooled slices, and calling
+    /// `BufferSlice::as_writable_vec` will move the content out of the arena into a fallback vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use std::io::{self, Read};
+    /// use byte_buffer::prelude::*;
+    ///
+    /// // Count of buffer: 10; Buffer capacity: 3
+    /// ByteBuffer::init_arena(10, 3);
+    ///
+    /// let mut buffer = ByteBuffer::slice();
+    /// io::repeat(0b101).read_exact(buffer.as_writable()).unwrap();
+    ///
+    /// assert_eq!(buffer.read().unwrap(), [0b101, 0b101, 0b101]);
+    /// ```
+    pub fn init_arena(size: usize, capacity: usize) {
+        Self::init_arena_store(size, capacity, 1);
+    }
+
+    /// Initialize the buffer pool like `init_arena`, but the arena will be allocated with the given
+    /// `align`ment, and the `capacity` of each slice will be rounded up to a multiple of `align`,
+    /// such that every pooled slice starts at an aligned address, e.g. to be used for direct I/O
+    /// or SIMD operations. The `align` must be a power of 2. Use `BufferSlice::as_aligned_ptr` to
+    /// obtain the aligned address of a slice.
+    ///
+    /// Note that the fallback vectors handed out when the pool is exhausted are not aligned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init_aligned(4, 1000, 512);
+    ///
+    /// let mut buffer = ByteBuffer::slice();
+    /// let ptr = buffer.as_aligned_ptr().unwrap();
+    ///
+    /// assert_eq!(ptr as usize % 512, 0);
+    /// assert_eq!(buffer.as_writable().len(), 1024);
+    /// ```
+    pub fn init_aligned(size: usize, capacity: usize, align: usize) {
+        assert!(
+            align.is_power_of_two(),
+            "the alignment must be a power of 2"
+        );
+        Self::init_arena_store(size, capacity.div_ceil(align) * align, align);
+    }
+
+    /// Drop the buffer pool and shut down the worker thread, such that the memory held by the pool is
+    /// freed, and the pool can be initialized again with fresh parameters, e.g. in tests that need
+    /// pools of different sizes. The API is no-op if the pool has not been initialized.
+    ///
+    /// # Safety
+    ///
+    /// All outstanding pooled `BufferSlice`s, including the ones held by other threads, must have been
+    /// dropped before calling this API. The slices they refer to are freed along with the pool, and
+    /// their ids would be handed out again by a re-initialized pool, such that dropping such a slice
+    /// later releases a slice now owned by someone else, and two live `BufferSlice`s end up sharing
+    /// the same storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init(2, 4);
+    /// assert_eq!(ByteBuffer::slice().as_writable().len(), 4);
+    ///
+    /// unsafe { ByteBuffer::teardown() };
+    ///
+    /// ByteBuffer::init(1, 16);
+    /// assert_eq!(ByteBuffer::slice().as_writable().len(), 16);
+    /// ```
+    pub unsafe fn teardown() {
+        let mut runtime = BufferPool::<E>::lock_runtime();
+
+        if let Some(rt) = runtime.take() {
+            // dropping the pool will signal the worker to quit
+            BufferPool::<E>::teardown();
+
+            if let Some(handle) = rt.worker {
+                handle.join().unwrap_or_else(|_| {
+                    eprintln!("The worker thread has panicked before the teardown...");
+                });
+            }
+        }
+    }
+
+    pub fn slice() -> BufferSlice<E> {
+        Self::slice_checked().unwrap_or_else(Self::fallback_slice)
+    }
+
+    /// Same as `slice`, but return `None` instead of an unpooled slice if all slices are in use and
+    /// the pool can't be extended beyond its size limit, when the policy set via `set_on_cap_reached`
+    /// is `Error`, or `Block` and no slice is released before the timeout. With the `Fallback` policy,
+    /// which is the default, this is the same as `slice`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init_sync(2, 8);
+    /// ByteBuffer::set_size_limit(2);
+    /// ByteBuffer::set_on_cap_reached(CapPolicy::Error);
+    ///
+    /// let first = ByteBuffer::slice_checked().unwrap();
+    /// let second = ByteBuffer::slice_checked().unwrap();
+    /// assert!(ByteBuffer::slice_checked().is_none());
+    ///
+    /// drop(first);
+    /// assert!(ByteBuffer::slice_checked().is_some());
+    /// ```
+    pub fn slice_checked() -> Option<BufferSlice<E>> {
+        debug_assert!(
+            Self::capacity_as_expected(),
+            "the slice capacity doesn't match the one set via `assert_capacity`"
+        );
+
+        let id = match BufferPool::<E>::reserve_slice() {
+            Ok(id) => Some(id),
+            Err(SliceError::CapReached) => match BufferPool::<E>::cap_policy() {
+                CapPolicy::Fallback => return Some(Self::fallback_slice()),
+                CapPolicy::Block(timeout) => BufferPool::<E>::reserve_timeout(timeout),
+                CapPolicy::Error => None,
             },
+            // the lock contention or an uninitialized pool is not a sign of leaking slices
+            Err(_) => return Some(Self::fallback_slice()),
+        };
+
+        id.map(|id| BufferSlice::new(Some(id), None))
+    }
+
+    fn fallback_slice() -> BufferSlice<E> {
+        BufferSlice::new(
+            None,
+            Some(vec::from_elem(
+                E::default(),
+                BufferPool::<E>::default_capacity(),
+            )),
+        )
+    }
+
+    pub fn try_slice() -> Option<BufferSlice<E>> {
+        Self::try_slice_detailed().ok()
+    }
+
+    /// Same as `try_slice`, but return the reason that we are unable to reserve a slice, such that
+    /// the caller can tell the lock contention apart from an exhausted pool.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// assert_eq!(ByteBuffer::try_slice_detailed().err(), Some(SliceError::NotInitialized));
+    ///
+    /// ByteBuffer::init(1, 8);
+    ///
+    /// let buffer = ByteBuffer::try_slice_detailed().unwrap();
+    /// assert_eq!(ByteBuffer::try_slice_detailed().err(), Some(SliceError::Empty));
+    /// ```
+    pub fn try_slice_detailed() -> Result<BufferSlice<E>, SliceError> {
+        BufferPool::<E>::try_reserve_slice().map(|id| BufferSlice::new(Some(id), None))
+    }
+
+    /// Obtain a slice already containing a copy of `src`, with the written length set to the size of
+    /// `src`. The slice will be reserved from the pool if `src` can fit into a pooled slice, otherwise
+    /// a fallback slice of exactly the size of `src` will be created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// let buffer = ByteBuffer::slice_from(b"hello");
+    /// assert_eq!(buffer.read().unwrap(), b"hello");
+    ///
+    /// let buffer = ByteBuffer::slice_from(b"hello, world");
+    /// assert_eq!(buffer.try_into_string().unwrap(), "hello, world");
+    /// ```
+    pub fn slice_from(src: &[E]) -> BufferSlice<E> {
+        if src.len() <= BufferPool::<E>::default_capacity() {
+            let mut slice = Self::slice();
+            if slice.write_at(0, src).is_ok() {
+                return slice;
+            }
         }
+
+        // too large for a pooled slice, the fallback vector is entirely written
+        let mut slice = BufferSlice::new(None, Some(src.to_vec()));
+        slice.dirty = true;
+        slice.written = None;
+
+        slice
     }
 
-    pub fn try_slice() -> Option<BufferSlice> {
-        BufferPool::exec(BufOp::Reserve(false))
-            .map(|id|
-                BufferSlice {
-                    id,
-                    fallback: None,
-                    dirty: false,
-                }
-            )
+    /// Try to reserve a free slice from the pool, and wait up to `timeout` for a slice to be released
+    /// back to the pool if none is free at the moment. Unlike `slice`, the pool won't be extended,
+    /// and `None` will be returned if we're unable to obtain a slice before the timeout. This bounds
+    /// the size of the pool under steady state by preferring reuse over extension.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// ByteBuffer::init(1, 8);
+    ///
+    /// let first = ByteBuffer::slice_blocking(Duration::from_millis(10));
+    /// assert!(first.is_some());
+    /// assert!(ByteBuffer::slice_blocking(Duration::from_millis(10)).is_none());
+    ///
+    /// // the slice will be released to the pool by the worker
+    /// drop(first);
+    /// assert!(ByteBuffer::slice_blocking(Duration::from_secs(1)).is_some());
+    /// ```
+    pub fn slice_blocking(timeout: Duration) -> Option<BufferSlice<E>> {
+        BufferPool::<E>::reserve_timeout(timeout).map(|id| BufferSlice::new(Some(id), None))
+    }
+
+    /// Declare the slice capacity that the caller expects the pool to be initialized with, e.g. when
+    /// multiple modules may call `init`, and only the first call takes effect. In debug builds, the
+    /// expectation is checked right away if the pool has been initialized, and on every `slice`
+    /// call, which panics if the capacity of the pool doesn't match. Release builds skip the checks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// // the later call is ignored, which is benign if the parameters are the same
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// ByteBuffer::assert_capacity(8);
+    /// let buffer = ByteBuffer::slice();
+    /// ```
+    pub fn assert_capacity(expected: usize) {
+        BufferPool::<E>::expected_capacity().store(expected, Ordering::Release);
+
+        debug_assert!(
+            Self::capacity_as_expected(),
+            "the buffer pool is configured with a slice capacity other than {}",
+            expected
+        );
+    }
+
+    /// If the pool has been initialized with the slice capacity set via `assert_capacity`, which is
+    /// always true if no expectation has been set, or the pool has not been initialized.
+    fn capacity_as_expected() -> bool {
+        let expected = BufferPool::<E>::expected_capacity().load(Ordering::Acquire);
+        if expected == 0 {
+            return true;
+        }
+
+        BufferPool::<E>::lock_runtime()
+            .as_ref()
+            .is_none_or(|rt| rt.params.1 == expected)
+    }
+
+    fn init_store(
+        size: usize,
+        capacity: usize,
+        clear: ClearPolicy<E>,
+        unzeroed: bool,
+        with_worker: bool,
+    ) {
+        let mut runtime = BufferPool::<E>::lock_runtime();
+        if runtime.is_some() {
+            return;
+        }
+
+        let mut store = Vec::with_capacity(size);
+
+        (0..size).for_each(|_| {
+            store.push(if unzeroed {
+                // the slices are flagged as uninitialized when they're checked out
+                unsafe { make_unzeroed(capacity) }
+            } else {
+                vec::from_elem(E::default(), capacity)
+            });
+        });
+
+        let (sender, worker) = if with_worker {
+            let (sender, handle) = start_worker::<E>();
+            (Some(sender), Some(handle))
+        } else {
+            (None, None)
+        };
+
+        BufferPool::<E>::make(store, capacity, clear, unzeroed, sender);
+        runtime.replace(Runtime {
+            worker,
+            params: (size, capacity),
+        });
+    }
+
+    fn init_arena_store(size: usize, capacity: usize, align: usize) {
+        let mut runtime = BufferPool::<E>::lock_runtime();
+        if runtime.is_some() {
+            return;
+        }
+
+        let arena = AlignedBuf::new(size * capacity, align);
+
+        let (sender, handle) = start_worker::<E>();
+        BufferPool::<E>::make_arena(arena, capacity, Some(sender));
+        runtime.replace(Runtime {
+            worker: Some(handle),
+            params: (size, capacity),
+        });
     }
 
     #[inline]
     pub fn extend(additional: usize) {
-        BufferPool::exec(BufOp::Extend(additional));
+        BufferPool::<E>::exec(BufOp::Extend(additional));
+    }
+
+    /// Visit every free slice of the pool with its id, in no particular order, e.g. to dump the content
+    /// of the pool for debugging. The slices being checked out are skipped, since their owners may be
+    /// writing to them at the same time. The pool is locked during the visit, so `f` shall not reserve
+    /// or release slices, and the visit will be skipped if we're unable to obtain the lock in time.
+    /// The slices may be reserved right after the visit, so it's only a best-effort snapshot of the
+    /// pool.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init_sync(2, 4);
+    ///
+    /// let buffer = ByteBuffer::slice_from(b"abcd");
+    ///
+    /// let mut dump = Vec::new();
+    /// ByteBuffer::for_each_slice(|id, slice| dump.push((id, slice.to_vec())));
+    ///
+    /// // the checked-out slice is skipped
+    /// assert_eq!(dump.len(), 1);
+    /// assert_ne!(Some(dump[0].0), buffer.id());
+    /// assert_eq!(dump[0].1, [0; 4]);
+    /// ```
+    pub fn for_each_slice<F: FnMut(usize, &[E])>(f: F) {
+        BufferPool::<E>::for_each_slice(f);
+    }
+
+    /// The number of slices reserved from the pool that are being checked out at the moment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init(1, 8);
+    ///
+    /// let pooled = ByteBuffer::slice();
+    /// let fallback = ByteBuffer::slice_from(b"too large to be pooled");
+    /// assert_eq!(ByteBuffer::outstanding_pooled(), 1);
+    /// assert_eq!(ByteBuffer::outstanding_fallback(), 1);
+    ///
+    /// drop(pooled);
+    /// drop(fallback);
+    /// assert_eq!(ByteBuffer::outstanding_pooled(), 0);
+    /// assert_eq!(ByteBuffer::outstanding_fallback(), 0);
+    /// ```
+    pub fn outstanding_pooled() -> usize {
+        BufferPool::<E>::outstanding().0.load(Ordering::Relaxed)
+    }
+
+    /// The number of fallback slices, i.e. the ones created because the pool was unable to offer a
+    /// slice, that are being checked out at the moment. A fallback count that stays high is a sign
+    /// that the pool is undersized for the workload.
+    pub fn outstanding_fallback() -> usize {
+        BufferPool::<E>::outstanding().1.load(Ordering::Relaxed)
+    }
+
+    /// Change the capacity of the slices after the pool has been initialized, e.g. when the messages
+    /// turn out to be larger than expected, without tearing down the pool. The free slices are
+    /// resized right away, and the slices being checked out will be resized when they're released
+    /// back to the pool. Shrinking the capacity truncates the slices, and their content beyond the
+    /// new capacity is lost.
+    ///
+    /// `Unsupported` will be returned for a pool backed by an arena (see `init_arena`), whose slices
+    /// can't be resized; `NotConnected` if the pool has not been initialized; and `TimedOut` if we're
+    /// unable to obtain the lock of the pool in time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init_sync(2, 8);
+    /// assert!(ByteBuffer::set_slice_capacity(16).is_ok());
+    ///
+    /// let mut buffer = ByteBuffer::slice();
+    /// assert_eq!(buffer.as_writable().len(), 16);
+    /// ```
+    pub fn set_slice_capacity(capacity: usize) -> Result<(), ErrorKind> {
+        BufferPool::<E>::set_slice_capacity(capacity)?;
+
+        if let Some(rt) = BufferPool::<E>::lock_runtime().as_mut() {
+            rt.params.1 = capacity;
+        }
+
+        Ok(())
+    }
+
+    /// Set the maximum number of slices the pool can hold, which is 512 or the size the pool has been
+    /// initialized with, whichever is larger, by default. `slice` extends the pool up to the limit
+    /// when all slices are in use, and what happens beyond the limit is decided by the policy set via
+    /// `set_on_cap_reached`. Lowering the limit doesn't shrink a pool that has grown beyond it.
+    #[inline]
+    pub fn set_size_limit(limit: usize) {
+        BufferPool::<E>::set_size_limit(limit);
+    }
+
+    /// Set what `slice` and `slice_checked` do when all slices are in use and the pool can't be
+    /// extended beyond its size limit: falling back to an unpooled slice, which is the default, may
+    /// mask a leak of slices that keeps the pool exhausted; blocking applies backpressure to the
+    /// callers until a slice is released; and failing makes `slice_checked` return `None`, such that
+    /// the leak is caught early. The number of times the limit is hit is counted regardless of the
+    /// policy, see `cap_hits`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// ByteBuffer::init_sync(1, 8);
+    /// ByteBuffer::set_size_limit(1);
+    /// ByteBuffer::set_on_cap_reached(CapPolicy::Block(Duration::from_millis(10)));
+    ///
+    /// let first = ByteBuffer::slice();
+    /// assert!(ByteBuffer::slice_checked().is_none());
+    ///
+    /// // `slice` can't fail, it falls back after waiting in vain
+    /// assert!(!ByteBuffer::slice().is_pooled());
+    /// assert_eq!(ByteBuffer::cap_hits(), 2);
+    /// ```
+    pub fn set_on_cap_reached(policy: CapPolicy) {
+        BufferPool::<E>::set_cap_policy(policy);
+    }
+
+    /// The number of times that `slice` or `slice_checked` has found all slices in use with the pool
+    /// unable to grow beyond its size limit. A steadily growing count is a sign that the slices are
+    /// leaking, or that the limit is too low for the workload.
+    pub fn cap_hits() -> usize {
+        BufferPool::<E>::cap_hits().load(Ordering::Relaxed)
+    }
+
+    /// Set if a slice that has grown beyond the configured capacity shall be trimmed back to the
+    /// capacity when it's released to the pool. Trimming frees the excess memory held by the slice,
+    /// but it reallocates the slice, so it's turned off by default.
+    #[inline]
+    pub fn set_auto_trim(trim: bool) {
+        BufferPool::<E>::set_auto_trim(trim);
+    }
+
+    /// Set if a dropped slice shall be reset and released back to the pool right on the dropping
+    /// thread, under the pool's lock, instead of being handed over to the cleanup worker. This saves
+    /// the channel round trip and the thread hop on every release, which is pure overhead when the
+    /// pool is used by a single thread or under low contention; while the worker keeps the reset cost
+    /// off the hot path when many threads are releasing slices at the same time. It's turned off by
+    /// default, and has no effect on the pools without a worker (see `init_sync`), which always
+    /// release the slices inline.
+    ///
+    /// Run `examples/release_bench.rs` to compare the two modes on your own hardware and workload.
+    #[inline]
+    pub fn set_inline_release(inline: bool) {
+        BufferPool::<E>::set_inline_release(inline);
     }
 }
 
-pub struct BufferSlice {
-    id: usize,
-    fallback: Option<Vec<u8>>,
+pub struct BufferSlice<E: PoolElement = u8> {
+    id: Option<usize>,
+    fallback: Option<Vec<E>>,
     dirty: bool,
+
+    /// The high-water mark of the bytes written into the slice, and only these bytes are visible to
+    /// `read`. `None` means the whole slice is considered written, since the caller has been handed
+    /// the mutable access to the entire slice.
+    written: Option<usize>,
+
+    /// If the bytes beyond the written mark may be uninitialized, i.e. the slice has been exposed
+    /// via `as_uninit_mut`.
+    uninit: bool,
+
+    /// The cursor shared by the `io::Read` and `io::Write` implementations, see `seek`.
+    position: usize,
 }
 
-impl BufferSlice {
-    pub(crate) fn new(id: usize, fallback: Option<Vec<u8>>) -> Self {
+impl<E: PoolElement> BufferSlice<E> {
+    pub(crate) fn new(id: Option<usize>, fallback: Option<Vec<E>>) -> Self {
+        let outstanding = BufferPool::<E>::outstanding();
+        match id {
+            Some(_) => outstanding.0.fetch_add(1, Ordering::Relaxed),
+            None => outstanding.1.fetch_add(1, Ordering::Relaxed),
+        };
+
         BufferSlice {
             id,
             fallback,
             dirty: false,
+            written: Some(0),
+            uninit: id.is_some() && BufferPool::<E>::is_unzeroed(),
+            position: 0,
         }
     }
 
-    pub fn as_writable(&mut self) -> &mut [u8] {
+    /// Expose the whole slice for writing. Since we can't tell how many bytes the caller will write,
+    /// the whole slice will be considered as written and visible to `read`, unless the caller narrows
+    /// it down via `set_written` afterwards.
+    pub fn as_writable(&mut self) -> &mut [E] {
+        self.reinit();
+        self.written = None;
+        self.writable()
+    }
+
+    /// Expose the slice as a vector for writing, where the whole vector, including the bytes pushed
+    /// by the caller, will be considered as written.
+    pub fn as_writable_vec(&mut self) -> &mut Vec<E> {
         self.dirty = true;
+        self.reinit();
+        self.written = None;
 
-        if let Some(ref mut vec) = self.fallback {
-            return vec.as_mut_slice();
+        if self.fallback.is_none() {
+            if let Some(Ok(vec)) = self.id.map(BufferPool::<E>::get_writable) {
+                return vec;
+            }
         }
 
-        match BufferPool::get_writable(self.id) {
-            Ok(vec) => vec.as_mut_slice(),
-            Err(_) => {
-                self.fallback = Some(vec::from_elem(0, BufferPool::default_capacity()));
-                if let Some(ref mut vec) = self.fallback {
-                    return vec.as_mut_slice();
-                }
+        self.make_fallback()
+    }
 
-                unreachable!();
-            }
+    /// Expose the whole slice as uninitialized bytes, such that the caller can fill the slice (e.g.
+    /// by reading from a socket) without the crate zeroing the slice beforehand. The slice will be
+    /// considered as empty until the caller commits the number of bytes written via `set_len`, and
+    /// only the committed bytes will be visible to `read`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    /// use std::mem::MaybeUninit;
+    ///
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// let mut buffer = ByteBuffer::slice();
+    /// let region = buffer.as_uninit_mut();
+    /// region[0] = MaybeUninit::new(42);
+    /// region[1] = MaybeUninit::new(7);
+    ///
+    /// unsafe { buffer.set_len(2) };
+    /// assert_eq!(buffer.read().unwrap(), [42, 7]);
+    /// ```
+    pub fn as_uninit_mut(&mut self) -> &mut [MaybeUninit<E>] {
+        // nothing will be considered as written, so there's no point in zeroing the slice first
+        self.uninit = false;
+        self.written = Some(0);
+
+        let region = self.writable();
+        let (ptr, len) = (region.as_mut_ptr(), region.len());
+        self.uninit = true;
+
+        // `MaybeUninit<E>` has the same layout as `E`
+        unsafe { slice::from_raw_parts_mut(ptr as *mut MaybeUninit<E>, len) }
+    }
+
+    /// Commit the number of bytes written into the slice obtained from `as_uninit_mut`, such that
+    /// the first `len` bytes will be visible to `read`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that the first `len` bytes of the slice have been initialized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is larger than the size of the slice.
+    pub unsafe fn set_len(&mut self, len: usize) {
+        assert!(
+            len <= self.len(),
+            "the length exceeds the size of the slice"
+        );
+        self.written = Some(len);
+    }
+
+    /// Set the number of bytes written into the slice, for callers that have filled the slice via
+    /// the raw slice from `as_writable`, such that only the first `len` bytes will be visible to
+    /// `read`. If the slice has been exposed via `as_uninit_mut`, the bytes beyond the previously
+    /// committed length will be zeroed first; use `set_len` to commit those bytes instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// let mut buffer = ByteBuffer::slice();
+    /// assert_eq!(buffer.read().unwrap(), []);
+    ///
+    /// buffer.as_writable()[..3].copy_from_slice(b"abc");
+    /// assert_eq!(buffer.read().unwrap().len(), 8);
+    ///
+    /// buffer.set_written(3);
+    /// assert_eq!(buffer.try_into_string().unwrap(), "abc");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is larger than the size of the slice.
+    pub fn set_written(&mut self, len: usize) {
+        assert!(
+            len <= self.len(),
+            "the length exceeds the size of the slice"
+        );
+
+        self.reinit();
+        self.written = Some(len);
+    }
+
+    /// Shorten the written region of the slice to `len` bytes, such that reads after the call
+    /// will only see the first `len` bytes. Just like `Vec::truncate`, this is a no-op if `len` is
+    /// not less than the number of bytes written, and the slice keeps its size, so the truncated
+    /// bytes can be written over again without reallocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// let mut buffer = ByteBuffer::slice_from(b"abcdef");
+    /// buffer.truncate(3);
+    /// assert_eq!(buffer.read().unwrap(), b"abc");
+    ///
+    /// // growing the written region is left to `set_written`
+    /// buffer.truncate(5);
+    /// assert_eq!(buffer.read().unwrap(), b"abc");
+    /// ```
+    pub fn truncate(&mut self, len: usize) {
+        let written = self.written.unwrap_or_else(|| self.len());
+        if len >= written {
+            return;
         }
+
+        self.written = Some(len);
+        self.position = self.position.min(len);
     }
 
-    pub fn as_writable_vec(&mut self) -> &mut Vec<u8> {
-        self.dirty = true;
+    /// Copy `src` into the slice at `offset`, and move the written mark forward to cover the copied
+    /// bytes if needed. `InvalidInput` will be returned and the slice is left untouched if `src`
+    /// can't fit into the slice at `offset` without growing it.
+    pub fn write_at(&mut self, offset: usize, src: &[E]) -> Result<(), ErrorKind> {
+        let end = match offset.checked_add(src.len()) {
+            Some(end) if end <= self.len() => end,
+            _ => return Err(ErrorKind::InvalidInput),
+        };
 
-        if let Some(ref mut vec) = self.fallback {
-            return vec;
+        self.writable()[offset..end].copy_from_slice(src);
+
+        if let Some(written) = self.written {
+            self.written = Some(written.max(end));
         }
 
-        match BufferPool::get_writable(self.id) {
-            Ok(vec) => vec,
-            Err(_) => {
-                self.fallback = Some(vec::from_elem(0, BufferPool::default_capacity()));
-                if let Some(vec) = self.fallback.as_mut() {
-                    return vec;
-                }
+        Ok(())
+    }
 
-                unreachable!();
-            }
+    /// The id of the slice in the pool, which stays the same when the slice is reused, such that the
+    /// usage of the slices can be correlated across a trace. `None` will be returned if the slice is
+    /// a fallback vector created because the pool was unable to offer a slice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init(1, 8);
+    ///
+    /// let buffer = ByteBuffer::slice_from(b"pooled");
+    /// assert_eq!(buffer.id(), Some(0));
+    /// assert!(buffer.is_pooled());
+    ///
+    /// let fallback = ByteBuffer::slice_from(b"too large to be pooled");
+    /// assert_eq!(fallback.id(), None);
+    /// assert!(!fallback.is_pooled());
+    /// ```
+    pub fn id(&self) -> Option<usize> {
+        self.id
+    }
+
+    /// If the slice is reserved from the pool rather than being a fallback vector, see `id`.
+    pub fn is_pooled(&self) -> bool {
+        self.id.is_some()
+    }
+
+    /// The address of the pooled slice, which is aligned to the alignment given to
+    /// `ByteBuffer::init_aligned`. `None` will be returned if the pool is not aligned, or if the
+    /// slice is backed by a fallback vector, whose alignment is not guaranteed. The slice can be
+    /// written via `as_writable` without losing the alignment, since the aligned slices are never
+    /// moved.
+    pub fn as_aligned_ptr(&self) -> Option<*const E> {
+        if self.fallback.is_some() || BufferPool::<E>::alignment() <= 1 {
+            return None;
         }
+
+        BufferPool::<E>::get_slice(self.id?).ok().map(<[E]>::as_ptr)
     }
 
-    pub fn read(&self) -> Option<&[u8]> {
-        if let Some(ref vec) = self.fallback {
-            return Some(vec.as_slice());
+    pub fn read(&self) -> Option<&[E]> {
+        let slice = match self.fallback {
+            Some(ref vec) => vec.as_slice(),
+            None => match BufferPool::<E>::get_slice(self.id?) {
+                Ok(slice) => slice,
+                Err(e) => {
+                    eprintln!("Failed to read the buffer: {:?}...", e);
+                    return None;
+                }
+            },
+        };
+
+        match self.written {
+            Some(len) => Some(&slice[..len]),
+            None => Some(slice),
         }
+    }
 
-        match BufferPool::get_readable(self.id) {
-            Ok(vec) => Some(vec.as_slice()),
-            Err(e) => {
-                eprintln!("Failed to read the buffer: {:?}...", e);
-                None
-            }
+    /// Iterate over the written content of the slice in chunks of `chunk_size` elements, e.g. for
+    /// record-oriented content, without copying the content out of the slice. The last chunk will be
+    /// shorter if the written length is not a multiple of `chunk_size`, just like `slice::chunks`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init(4, 16);
+    ///
+    /// let buffer = ByteBuffer::slice_from(b"aaaabbbbcc");
+    /// let chunks: Vec<&[u8]> = buffer.as_chunks(4).collect();
+    ///
+    /// assert_eq!(chunks, vec![&b"aaaa"[..], &b"bbbb"[..], &b"cc"[..]]);
+    /// ```
+    pub fn as_chunks(&self, chunk_size: usize) -> impl Iterator<Item = &[E]> {
+        assert!(chunk_size > 0, "the chunk size must be positive");
+        self.read().unwrap_or(&[]).chunks(chunk_size)
+    }
+
+    /// The mutable version of `as_chunks`, which iterates over the written content of the slice in
+    /// chunks of `chunk_size` elements, such that the records can be updated in place. The written
+    /// mark stays where it is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn as_chunks_mut(&mut self, chunk_size: usize) -> impl Iterator<Item = &mut [E]> {
+        assert!(chunk_size > 0, "the chunk size must be positive");
+
+        let written = self.written;
+        let slice = self.writable();
+        let len = written.map_or(slice.len(), |len| len.min(slice.len()));
+
+        slice[..len].chunks_mut(chunk_size)
+    }
+
+    /// The number of bytes that can still be written after the written mark, before the slice has
+    /// to grow or fall back to a larger vector.
+    pub fn remaining_capacity(&self) -> usize {
+        match self.written {
+            Some(written) => self.len().saturating_sub(written),
+            None => 0,
         }
     }
 
-    pub fn read_as_vec(&self) -> Option<&Vec<u8>> {
-        if let Some(ref vec) = self.fallback {
-            return Some(vec);
+    /// Append as many bytes of `data` as can fit into the remaining capacity after the written mark,
+    /// and return the number of bytes being appended, just like `Write::write`. The slice will never
+    /// grow, and it's up to the caller to handle the remainder of `data`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// let mut buffer = ByteBuffer::slice();
+    /// assert_eq!(buffer.append(b"hello"), 5);
+    /// assert_eq!(buffer.remaining_capacity(), 3);
+    ///
+    /// assert_eq!(buffer.append(b", world"), 3);
+    /// assert_eq!(buffer.remaining_capacity(), 0);
+    /// assert_eq!(buffer.read().unwrap(), b"hello, w");
+    /// ```
+    pub fn append(&mut self, data: &[E]) -> usize {
+        let size = data.len().min(self.remaining_capacity());
+        if size == 0 {
+            return 0;
         }
 
-        match BufferPool::get_readable(self.id) {
-            Ok(vec) => Some(vec),
-            Err(e) => {
-                eprintln!("Failed to read the buffer: {:?}...", e);
-                None
-            }
+        let offset = self.written.unwrap_or(0);
+        match self.write_at(offset, &data[..size]) {
+            Ok(()) => size,
+            Err(_) => 0,
         }
     }
 
-    pub fn copy_to_vec(&self) -> Vec<u8> {
+    /// Read the buffer as a vector. Slices carved out of the arena (see `ByteBuffer::init_arena`)
+    /// are not standalone vectors, and `None` will be returned for them, use `read` instead. Since
+    /// the vector can't be cut short, `None` will also be returned if only a part of the vector has
+    /// been written.
+    pub fn read_as_vec(&self) -> Option<&Vec<E>> {
+        let vec = match self.fallback {
+            Some(ref vec) => vec,
+            None => match BufferPool::<E>::get_readable(self.id?) {
+                Ok(vec) => vec,
+                Err(e) => {
+                    eprintln!("Failed to read the buffer: {:?}...", e);
+                    return None;
+                }
+            },
+        };
+
+        match self.written {
+            Some(len) if len < vec.len() => None,
+            _ => Some(vec),
+        }
+    }
+
+    pub fn copy_to_vec(&self) -> Vec<E> {
         // this will hard-copy the vec content
         match self.read() {
             Some(slice) => slice.to_vec(),
@@ -156,16 +1037,240 @@ impl BufferSlice {
         }
     }
 
+    /// Copy the written content into a new slice from the pool, or a fallback vector if the pool
+    /// has no slice to offer or the content can't fit into a pooled slice, see `slice_from`. Unlike
+    /// `copy_to_vec`, the copy will be released back to the pool when it's dropped. The cursor of the
+    /// copy starts from the beginning.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    ///
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// let buffer = ByteBuffer::slice_from(b"hello");
+    /// let mut copy = buffer.clone_pooled();
+    /// copy[0] = b'j';
+    ///
+    /// assert_eq!(buffer.read().unwrap(), b"hello");
+    /// assert_eq!(copy.read().unwrap(), b"jello");
+    /// ```
+    pub fn clone_pooled(&self) -> BufferSlice<E> {
+        TypedBufferPool::<E>::slice_from(readable(self))
+    }
+
     pub fn reset(&mut self) {
         if !self.dirty {
             return;
         }
 
-        BufferPool::reset_slice(self.id);
+        if let Some(id) = self.id {
+            BufferPool::<E>::reset_slice(id);
+        }
 
         if let Some(fb) = self.fallback.as_mut() {
-            fb.iter_mut().for_each(|val| *val = 0);
+            BufferPool::<E>::clear_policy().apply(fb);
+        }
+
+        // the whole slice is cleared, hence initialized and nothing is written, unless the pool
+        // doesn't zero its slices
+        self.written = Some(0);
+        self.uninit =
+            self.fallback.is_none() && self.id.is_some() && BufferPool::<E>::is_unzeroed();
+        self.position = 0;
+    }
+
+    /// Copy the content of `src` into the beginning of the slice, only if `src` can fit into the
+    /// slice without growing it; otherwise the slice is left untouched and `InvalidInput` will be
+    /// returned. This is the same as `write_at(0, src)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    /// use std::io::ErrorKind;
+    ///
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// let mut buffer = ByteBuffer::slice();
+    /// assert!(buffer.copy_from_slice_checked(b"hello").is_ok());
+    /// assert_eq!(buffer.read().unwrap(), b"hello");
+    ///
+    /// assert_eq!(buffer.copy_from_slice_checked(b"hello world"), Err(ErrorKind::InvalidInput));
+    /// ```
+    pub fn copy_from_slice_checked(&mut self, src: &[E]) -> Result<(), ErrorKind> {
+        self.write_at(0, src)
+    }
+
+    /// Turn the slice into an immutable view of its written content, which can be cloned cheaply and
+    /// shared among multiple consumers. The slice is released back to the pool once the last clone
+    /// of the view is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    /// use std::thread;
+    ///
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// let frozen = ByteBuffer::slice_from(b"fan-out").freeze();
+    ///
+    /// let handles: Vec<_> = (0..2)
+    ///     .map(|_| {
+    ///         let view = frozen.clone();
+    ///         thread::spawn(move || view.len())
+    ///     })
+    ///     .collect();
+    ///
+    /// for handle in handles {
+    ///     assert_eq!(handle.join().unwrap(), 7);
+    /// }
+    ///
+    /// assert_eq!(&frozen[..], b"fan-out");
+    /// ```
+    pub fn freeze(self) -> FrozenSlice<E> {
+        FrozenSlice {
+            inner: Arc::new(self),
+        }
+    }
+
+    fn len(&self) -> usize {
+        if let Some(ref vec) = self.fallback {
+            return vec.len();
         }
+
+        self.id.map_or(0, |id| {
+            BufferPool::<E>::slice_stat(id, SliceStatusQuery::Length)
+        })
+    }
+
+    fn capacity(&self) -> usize {
+        if let Some(ref vec) = self.fallback {
+            return vec.capacity();
+        }
+
+        self.id.map_or(0, |id| {
+            BufferPool::<E>::slice_stat(id, SliceStatusQuery::Capacity)
+        })
+    }
+
+    /// Obtain the mutable access to the whole slice without touching the written mark, the slice
+    /// will be moved to a fallback vector if the pooled slice can't be written to.
+    fn writable(&mut self) -> &mut [E] {
+        self.dirty = true;
+        self.reinit();
+
+        if self.fallback.is_none() {
+            if let Some(Ok(slice)) = self.id.map(BufferPool::<E>::get_slice_mut) {
+                return slice;
+            }
+        }
+
+        self.make_fallback().as_mut_slice()
+    }
+
+    /// Zero out the bytes beyond the committed length if the slice has been exposed as uninitialized
+    /// bytes, such that the whole slice is initialized again before we hand it out as `&mut [u8]`.
+    fn reinit(&mut self) {
+        if !self.uninit {
+            return;
+        }
+
+        self.uninit = false;
+
+        let len = self.written.unwrap_or(0);
+        self.writable()[len..]
+            .iter_mut()
+            .for_each(|val| *val = E::default());
+    }
+
+    /// Create the fallback vector if we can't write to the pooled slice, the content of the pooled
+    /// slice will be copied over if it's still readable (e.g. a slice carved out of the arena).
+    fn make_fallback(&mut self) -> &mut Vec<E> {
+        if self.fallback.is_none() {
+            let vec = match self.id.map(BufferPool::<E>::get_slice) {
+                Some(Ok(slice)) => slice.to_vec(),
+                _ => vec::from_elem(E::default(), BufferPool::<E>::default_capacity()),
+            };
+
+            self.fallback.replace(vec);
+        }
+
+        self.fallback.get_or_insert_with(Vec::new)
+    }
+}
+
+impl BufferSlice<u8> {
+    /// Logically concatenate this slice with `other`, such that the written content of both slices
+    /// can be read in order as a single sequence without copying them into a third buffer, e.g. for
+    /// a frame split across two pooled buffers. Both slices will be released back to the pool when
+    /// the `ChainedSlice` is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    /// use std::io::Read;
+    ///
+    /// ByteBuffer::init(4, 8);
+    ///
+    /// let head = ByteBuffer::slice_from(b"hello, ");
+    /// let tail = ByteBuffer::slice_from(b"world");
+    ///
+    /// let mut content = String::new();
+    /// head.chain(tail).read_to_string(&mut content).unwrap();
+    ///
+    /// assert_eq!(content, "hello, world");
+    /// ```
+    pub fn chain(self, other: BufferSlice) -> ChainedSlice {
+        ChainedSlice {
+            first: self,
+            second: other,
+            pos: 0,
+        }
+    }
+
+    /// Move the cursor used by the `io::Read` and `io::Write` implementations to `pos`, such that the
+    /// slice can be read or written non-sequentially just like a `Cursor`. Seeking beyond the written
+    /// mark is allowed, and the gap will be zero-filled on the next write. Reading or writing at or
+    /// beyond the end of the slice yields 0 bytes, since the slice never grows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    /// use std::io::{Read, Write};
+    ///
+    /// ByteBuffer::init(4, 16);
+    ///
+    /// let mut buffer = ByteBuffer::slice();
+    /// buffer.seek(2);
+    /// buffer.write_all(b"body").unwrap();
+    ///
+    /// // fill in the header after the body has been written
+    /// buffer.seek(0);
+    /// buffer.write_all(&[0, 4]).unwrap();
+    /// assert_eq!(buffer.read().unwrap(), b"\0\x04body");
+    ///
+    /// buffer.seek(2);
+    /// let mut body = String::new();
+    /// buffer.read_to_string(&mut body).unwrap();
+    /// assert_eq!(body, "body");
+    /// ```
+    pub fn seek(&mut self, pos: usize) {
+        self.position = pos;
+    }
+
+    /// The current position of the cursor, see `seek`.
+    pub fn position(&self) -> usize {
+        self.position
     }
 
     pub fn try_into_string(&self) -> Result<&str, ErrorKind> {
@@ -179,24 +1284,269 @@ impl BufferSlice {
         Err(ErrorKind::InvalidData)
     }
 
-    fn len(&self) -> usize {
-        BufferPool::slice_stat(self.id, SliceStatusQuery::Length)
+    /// Expose the written bytes as a mutable string, e.g. to transform the text in place without
+    /// copying it into a `String`. `InvalidData` will be returned if the written bytes are not valid
+    /// UTF-8. Since `str` keeps its content valid UTF-8, the slice stays valid after the borrow ends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate byte_buffer;
+    /// use byte_buffer::prelude::*;
+    /// use std::io::ErrorKind;
+    ///
+    /// ByteBuffer::init(4, 16);
+    ///
+    /// let mut buffer = ByteBuffer::slice_from(b"hello, world");
+    /// buffer.as_str_mut().unwrap().make_ascii_uppercase();
+    /// assert_eq!(buffer.try_into_string().unwrap(), "HELLO, WORLD");
+    ///
+    /// let mut buffer = ByteBuffer::slice_from(&[0xff, 0xfe]);
+    /// assert_eq!(buffer.as_str_mut().err(), Some(ErrorKind::InvalidData));
+    /// ```
+    pub fn as_str_mut(&mut self) -> Result<&mut str, ErrorKind> {
+        let len = self.written.unwrap_or_else(|| self.len());
+
+        str::from_utf8_mut(&mut self.writable()[..len]).map_err(|_| ErrorKind::InvalidData)
     }
 
-    fn capacity(&self) -> usize {
-        BufferPool::slice_stat(self.id, SliceStatusQuery::Capacity)
+    /// Copy the `srcs` one after another into the slice at the cursor, and return the number of bytes
+    /// being copied, which is capped by the room left in the slice after the cursor.
+    fn write_at_cursor<B: Deref<Target = [u8]>>(&mut self, srcs: &[B]) -> usize {
+        let pos = self.position;
+        let total = srcs.iter().map(|src| src.len()).sum::<usize>();
+        let size = total.min(self.len().saturating_sub(pos));
+        if size == 0 {
+            return 0;
+        }
+
+        let written = self.written;
+        let dst = self.writable();
+
+        // zero-fill the gap between the written mark and the cursor
+        if let Some(written) = written {
+            if written < pos {
+                dst[written..pos].iter_mut().for_each(|val| *val = 0);
+            }
+        }
+
+        let mut offset = pos;
+        for src in srcs {
+            let count = src.len().min(pos + size - offset);
+            dst[offset..offset + count].copy_from_slice(&src[..count]);
+            offset += count;
+        }
+
+        if let Some(written) = written {
+            self.written = Some(written.max(offset));
+        }
+
+        self.position = offset;
+        size
     }
 }
 
-impl Drop for BufferSlice {
+impl<E: PoolElement> Drop for BufferSlice<E> {
     fn drop(&mut self) {
-        if self.id == 0 && self.fallback.is_some() {
-            BufferPool::exec(BufOp::ReleaseAndExtend(
-                self.fallback.take().unwrap(),
-                self.dirty,
-            ));
-        } else {
-            BufferPool::reset_and_release(self.id, self.dirty);
+        let outstanding = BufferPool::<E>::outstanding();
+
+        match self.id {
+            Some(id) => {
+                outstanding.0.fetch_sub(1, Ordering::Relaxed);
+                BufferPool::<E>::reset_and_release(id, self.dirty);
+            }
+            None => {
+                outstanding.1.fetch_sub(1, Ordering::Relaxed);
+
+                if let Some(vec) = self.fallback.take() {
+                    BufferPool::<E>::exec(BufOp::ReleaseAndExtend(vec, self.dirty));
+                }
+            }
+        }
+    }
+}
+
+/// Read a single element of the written content, which panics if `index` is beyond the written mark.
+impl<E: PoolElement> Index<usize> for BufferSlice<E> {
+    type Output = E;
+
+    fn index(&self, index: usize) -> &E {
+        &readable(self)[index]
+    }
+}
+
+/// Read a part of the written content, which panics if `range` is beyond the written mark.
+impl<E: PoolElement> Index<Range<usize>> for BufferSlice<E> {
+    type Output = [E];
+
+    fn index(&self, range: Range<usize>) -> &[E] {
+        &readable(self)[range]
+    }
+}
+
+/// Compare the written content of the slices, regardless of the size of the slices or whether they
+/// are pooled or backed by the fallback vectors. Note that reading a pooled slice goes through the
+/// pool, so the comparison is not as cheap as comparing two `Vec`s.
+impl<E: PoolElement + PartialEq> PartialEq for BufferSlice<E> {
+    fn eq(&self, other: &Self) -> bool {
+        readable(self) == readable(other)
+    }
+}
+
+impl<E: PoolElement + Eq> Eq for BufferSlice<E> {}
+
+/// Hash the written content of the slice, which is consistent with the equality, such that the slices
+/// can be used as the keys of a `HashMap` for deduplication.
+impl<E: PoolElement + Hash> Hash for BufferSlice<E> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        readable(self).hash(state);
+    }
+}
+
+/// Write a single element anywhere within the capacity of the slice, and move the written mark
+/// forward to cover the element if needed, where the gap in between will be zero-filled. Panics if
+/// `index` is beyond the capacity, since the slice never grows.
+///
+/// # Examples
+///
+/// ```
+/// extern crate byte_buffer;
+/// use byte_buffer::prelude::*;
+///
+/// ByteBuffer::init(4, 8);
+///
+/// let mut buffer = ByteBuffer::slice_from(b"ab");
+/// buffer[0] = b'x';
+/// buffer[3] = b'y';
+///
+/// assert_eq!(buffer[0], b'x');
+/// assert_eq!(&buffer[1..4], b"b\0y");
+/// ```
+impl<E: PoolElement> IndexMut<usize> for BufferSlice<E> {
+    fn index_mut(&mut self, index: usize) -> &mut E {
+        let written = self.written;
+        let slice = self.writable();
+        assert!(
+            index < slice.len(),
+            "index {} is beyond the capacity of the slice: {}",
+            index,
+            slice.len()
+        );
+
+        if let Some(written) = written {
+            if written <= index {
+                slice[written..index]
+                    .iter_mut()
+                    .for_each(|val| *val = E::default());
+
+                self.written = Some(index + 1);
+            }
         }
+
+        &mut self.writable()[index]
+    }
+}
+
+impl Read for BufferSlice<u8> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let src = readable(self);
+        let offset = self.position.min(src.len());
+
+        let size = buf.len().min(src.len() - offset);
+        buf[..size].copy_from_slice(&src[offset..offset + size]);
+        self.position += size;
+
+        Ok(size)
+    }
+}
+
+impl Write for BufferSlice<u8> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.write_at_cursor(&[buf]))
     }
+
+    /// Copy the buffers one after another into the slice in a single pass, as many bytes as can fit
+    /// into the slice after the cursor.
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        Ok(self.write_at_cursor(bufs))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Two `BufferSlice`s concatenated by `BufferSlice::chain`, which reads the written content of the
+/// first slice and then the second one. Both slices are released back to the pool on drop.
+pub struct ChainedSlice {
+    first: BufferSlice,
+    second: BufferSlice,
+
+    /// The read cursor over the concatenated content
+    pos: usize,
+}
+
+impl ChainedSlice {
+    /// The total number of bytes readable from both slices.
+    pub fn len(&self) -> usize {
+        readable(&self.first).len() + readable(&self.second).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Split the chain back into the two slices.
+    pub fn into_inner(self) -> (BufferSlice, BufferSlice) {
+        (self.first, self.second)
+    }
+}
+
+impl Read for ChainedSlice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let first = readable(&self.first);
+
+        let (src, offset) = if self.pos < first.len() {
+            (first, self.pos)
+        } else {
+            (readable(&self.second), self.pos - first.len())
+        };
+
+        let size = buf.len().min(src.len().saturating_sub(offset));
+        buf[..size].copy_from_slice(&src[offset..offset + size]);
+        self.pos += size;
+
+        Ok(size)
+    }
+}
+
+/// An immutable view of a `BufferSlice` created by `BufferSlice::freeze`. Cloning the view only
+/// bumps a reference count, and the underlying slice is released back to the pool when the last
+/// clone is dropped.
+#[derive(Clone)]
+pub struct FrozenSlice<E: PoolElement = u8> {
+    inner: Arc<BufferSlice<E>>,
+}
+
+impl<E: PoolElement> Deref for FrozenSlice<E> {
+    type Target = [E];
+
+    fn deref(&self) -> &[E] {
+        readable(&self.inner)
+    }
+}
+
+#[inline]
+fn readable<E: PoolElement>(slice: &BufferSlice<E>) -> &[E] {
+    slice.read().unwrap_or(&[])
+}
+
+fn start_worker<E: PoolElement>() -> (Sender<WorkerOp>, JoinHandle<()>) {
+    let (sender, receiver) = channel::bounded(8);
+
+    let handle = thread::spawn(move || {
+        BufferPool::<E>::handle_work(receiver);
+    });
+
+    (sender, handle)
 }
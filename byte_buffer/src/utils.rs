@@ -1,11 +1,17 @@
+use std::alloc::{self, Layout};
+use std::marker::PhantomData;
 use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::slice;
 use std::vec;
 
-pub(crate) enum BufOp {
+pub(crate) enum BufOp<E> {
     Reserve(bool),
     Release(usize, bool),
-    ReleaseAndExtend(Vec<u8>, bool),
+    ReleaseAndExtend(Vec<E>, bool),
     Extend(usize),
+    Trim(usize),
 }
 
 pub(crate) enum WorkerOp {
@@ -24,3 +30,86 @@ pub(crate) fn make_buffer(cap: usize) -> *mut u8 {
     mem::forget(v);
     p
 }
+
+/// Allocate a vector of `len` elements without initializing them.
+///
+/// # Safety
+///
+/// The caller must not read any element before it has been written.
+#[allow(clippy::uninit_vec)]
+pub(crate) unsafe fn make_unzeroed<E>(len: usize) -> Vec<E> {
+    let mut vec = Vec::with_capacity(len);
+    vec.set_len(len);
+    vec
+}
+
+/// A zeroed block of `len` elements allocated with a custom alignment, which shall be freed with the
+/// exact same `Layout`, hence it can't be handed out as a `Vec` or a boxed slice. The element type
+/// must be valid when all of its bits are zero, which holds for all the `PoolElement` types.
+pub(crate) struct AlignedBuf<E> {
+    ptr: NonNull<E>,
+    len: usize,
+    layout: Layout,
+    _marker: PhantomData<E>,
+}
+
+impl<E> AlignedBuf<E> {
+    pub(crate) fn new(len: usize, align: usize) -> Self {
+        let layout = len
+            .checked_mul(mem::size_of::<E>())
+            .and_then(|size| Layout::from_size_align(size, align.max(mem::align_of::<E>())).ok())
+            .expect("the alignment must be a power of 2, and the size shall not overflow");
+
+        // zero-sized allocations are not allowed, and the block will never be accessed anyway
+        if layout.size() == 0 {
+            return AlignedBuf {
+                ptr: NonNull::dangling(),
+                len,
+                layout,
+                _marker: PhantomData,
+            };
+        }
+
+        let ptr = match NonNull::new(unsafe { alloc::alloc_zeroed(layout) }) {
+            Some(ptr) => ptr.cast(),
+            None => alloc::handle_alloc_error(layout),
+        };
+
+        AlignedBuf {
+            ptr,
+            len,
+            layout,
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn align(&self) -> usize {
+        self.layout.align()
+    }
+}
+
+impl<E> Deref for AlignedBuf<E> {
+    type Target = [E];
+
+    fn deref(&self) -> &[E] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<E> DerefMut for AlignedBuf<E> {
+    fn deref_mut(&mut self) -> &mut [E] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<E> Drop for AlignedBuf<E> {
+    fn drop(&mut self) {
+        if self.layout.size() > 0 {
+            unsafe { alloc::dealloc(self.ptr.as_ptr().cast(), self.layout) };
+        }
+    }
+}
+
+/// The buffer owns the elements exclusively, just like a `Box<[E]>`.
+unsafe impl<E: Send> Send for AlignedBuf<E> {}
+unsafe impl<E: Sync> Sync for AlignedBuf<E> {}
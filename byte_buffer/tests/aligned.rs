@@ -0,0 +1,52 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::time::Duration;
+
+#[test]
+fn aligned_slices_are_aligned_and_padded() {
+    ByteBuffer::init_aligned(3, 100, 64);
+
+    let mut buffers: Vec<_> = (0..3).map(|_| ByteBuffer::slice()).collect();
+    for buffer in buffers.iter_mut() {
+        let ptr = buffer.as_aligned_ptr().unwrap();
+        assert_eq!(ptr as usize % 64, 0);
+
+        // the capacity is rounded up to the alignment
+        assert_eq!(buffer.as_writable().len(), 128);
+    }
+}
+
+#[test]
+fn aligned_exhaustion_falls_back_to_unaligned() {
+    TypedBufferPool::<u16>::init_aligned(1, 8, 16);
+
+    let pooled = TypedBufferPool::<u16>::slice();
+    assert!(pooled.as_aligned_ptr().is_some());
+
+    let fallback = TypedBufferPool::<u16>::slice();
+    assert!(!fallback.is_pooled());
+    assert!(fallback.as_aligned_ptr().is_none());
+}
+
+#[test]
+fn aligned_release_keeps_the_address() {
+    TypedBufferPool::<u32>::init_aligned(1, 4, 32);
+
+    let mut buffer = TypedBufferPool::<u32>::slice();
+    let ptr = buffer.as_aligned_ptr().unwrap();
+    buffer.as_writable().fill(7);
+    drop(buffer);
+
+    let mut buffer = TypedBufferPool::<u32>::slice_blocking(Duration::from_secs(1)).unwrap();
+    assert_eq!(buffer.as_aligned_ptr(), Some(ptr));
+
+    // the capacity is rounded up to a multiple of the alignment
+    assert_eq!(buffer.as_writable(), [0; 32]);
+}
+
+#[test]
+fn unaligned_pool_has_no_aligned_ptr() {
+    TypedBufferPool::<u64>::init(1, 4);
+    assert!(TypedBufferPool::<u64>::slice().as_aligned_ptr().is_none());
+}
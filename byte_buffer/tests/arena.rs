@@ -0,0 +1,60 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::time::Duration;
+
+#[test]
+fn arena_slices_are_carved_from_one_block() {
+    ByteBuffer::init_arena(2, 4);
+
+    let mut first = ByteBuffer::slice();
+    let mut second = ByteBuffer::slice();
+    assert!(first.is_pooled() && second.is_pooled());
+
+    // the slices sit next to each other in the arena, ordered by their ids
+    let (id_1, id_2) = (first.id().unwrap(), second.id().unwrap());
+    let (ptr_1, ptr_2) = (first.as_writable().as_ptr(), second.as_writable().as_ptr());
+    assert_eq!(
+        ptr_2 as isize - ptr_1 as isize,
+        4 * (id_2 as isize - id_1 as isize)
+    );
+
+    // arena slices are not standalone vectors
+    assert!(first.read_as_vec().is_none());
+}
+
+#[test]
+fn arena_exhaustion_falls_back_without_extending() {
+    TypedBufferPool::<u16>::init_arena(1, 4);
+
+    let pooled = TypedBufferPool::<u16>::slice();
+    assert!(pooled.is_pooled());
+
+    // the arena can't be extended, so the next slice is backed by a fallback vector
+    let fallback = TypedBufferPool::<u16>::slice();
+    assert!(!fallback.is_pooled());
+    assert!(TypedBufferPool::<u16>::try_slice().is_none());
+    assert_eq!(
+        TypedBufferPool::<u16>::try_slice_detailed().err(),
+        Some(SliceError::Empty)
+    );
+
+    // the fallback vector is not adopted by the arena when it's dropped
+    drop(fallback);
+    assert!(TypedBufferPool::<u16>::try_slice().is_none());
+}
+
+#[test]
+fn arena_release_zeroes_and_reuses_the_slice() {
+    TypedBufferPool::<u32>::init_arena(1, 4);
+
+    let mut buffer = TypedBufferPool::<u32>::slice();
+    let id = buffer.id();
+    buffer.as_writable().copy_from_slice(&[1, 2, 3, 4]);
+    drop(buffer);
+
+    // the only slice is released by the worker, and it's zeroed before being reused
+    let mut buffer = TypedBufferPool::<u32>::slice_blocking(Duration::from_secs(1)).unwrap();
+    assert_eq!(buffer.id(), id);
+    assert_eq!(buffer.as_writable(), [0; 4]);
+}
@@ -0,0 +1,48 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn blocking_gives_up_without_extending() {
+    ByteBuffer::init(1, 8);
+
+    let first = ByteBuffer::slice_blocking(Duration::from_millis(10)).unwrap();
+    assert!(first.is_pooled());
+
+    // the pool is exhausted, and it's not extended to serve the blocking call
+    assert!(ByteBuffer::slice_blocking(Duration::from_millis(10)).is_none());
+    assert!(ByteBuffer::try_slice().is_none());
+}
+
+#[test]
+fn blocking_waits_for_release_on_another_thread() {
+    TypedBufferPool::<u16>::init(1, 4);
+
+    let first = TypedBufferPool::<u16>::slice_from(&[1, 2, 3, 4]);
+    let id = first.id();
+
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        drop(first);
+    });
+
+    // the slice is handed over once the worker has released it
+    let mut second = TypedBufferPool::<u16>::slice_blocking(Duration::from_secs(1)).unwrap();
+    assert_eq!(second.id(), id);
+    assert_eq!(second.as_writable(), [0; 4]);
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn blocking_on_sync_pool_reuses_right_away() {
+    TypedBufferPool::<u32>::init_sync(1, 4);
+
+    let first = TypedBufferPool::<u32>::slice_blocking(Duration::from_millis(10)).unwrap();
+    drop(first);
+
+    // the slice has been released on the dropping thread, so there's nothing to wait for
+    assert!(TypedBufferPool::<u32>::slice_blocking(Duration::from_millis(0)).is_some());
+}
@@ -0,0 +1,60 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn cap_policy_error_fails_fast() {
+    ByteBuffer::init_sync(1, 8);
+    ByteBuffer::set_size_limit(1);
+    ByteBuffer::set_on_cap_reached(CapPolicy::Error);
+
+    let first = ByteBuffer::slice_checked().unwrap();
+    assert!(ByteBuffer::slice_checked().is_none());
+    assert_eq!(ByteBuffer::cap_hits(), 1);
+
+    // `slice` can't fail, so it still falls back
+    assert!(!ByteBuffer::slice().is_pooled());
+    assert_eq!(ByteBuffer::cap_hits(), 2);
+
+    // a released slice lifts the backpressure
+    drop(first);
+    assert!(ByteBuffer::slice_checked().unwrap().is_pooled());
+    assert_eq!(ByteBuffer::cap_hits(), 2);
+}
+
+#[test]
+fn cap_policy_block_waits_for_release() {
+    TypedBufferPool::<u16>::init(1, 4);
+    TypedBufferPool::<u16>::set_size_limit(1);
+    TypedBufferPool::<u16>::set_on_cap_reached(CapPolicy::Block(Duration::from_secs(1)));
+
+    let first = TypedBufferPool::<u16>::slice();
+    let id = first.id();
+
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        drop(first);
+    });
+
+    let second = TypedBufferPool::<u16>::slice_checked().unwrap();
+    assert_eq!(second.id(), id);
+    assert_eq!(TypedBufferPool::<u16>::cap_hits(), 1);
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn cap_policy_fallback_counts_hits() {
+    TypedBufferPool::<u32>::init_sync(1, 4);
+    TypedBufferPool::<u32>::set_size_limit(1);
+
+    let pooled = TypedBufferPool::<u32>::slice();
+    let fallbacks: Vec<_> = (0..3).map(|_| TypedBufferPool::<u32>::slice()).collect();
+
+    assert!(pooled.is_pooled());
+    assert!(fallbacks.iter().all(|buffer| !buffer.is_pooled()));
+    assert_eq!(TypedBufferPool::<u32>::outstanding_fallback(), 3);
+    assert_eq!(TypedBufferPool::<u32>::cap_hits(), 3);
+}
@@ -0,0 +1,64 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::io::ErrorKind;
+
+#[test]
+fn checked_out_slice_is_resized_on_release() {
+    ByteBuffer::init_sync(2, 8);
+
+    let held = ByteBuffer::slice_from(b"abcdefgh");
+    assert!(ByteBuffer::set_slice_capacity(16).is_ok());
+
+    // the free slice is resized right away
+    let mut lens = Vec::new();
+    ByteBuffer::for_each_slice(|_, slice| lens.push(slice.len()));
+    assert_eq!(lens, [16]);
+
+    // the held slice follows once it's released
+    drop(held);
+
+    let mut lens = Vec::new();
+    ByteBuffer::for_each_slice(|_, slice| lens.push(slice.len()));
+    assert_eq!(lens, [16, 16]);
+}
+
+#[test]
+fn shrinking_truncates_the_free_slices() {
+    TypedBufferPool::<u16>::init_sync(1, 8);
+
+    let mut buffer = TypedBufferPool::<u16>::slice();
+    buffer.as_writable().fill(3);
+    drop(buffer);
+
+    assert!(TypedBufferPool::<u16>::set_slice_capacity(4).is_ok());
+
+    let mut buffer = TypedBufferPool::<u16>::try_slice().unwrap();
+    assert_eq!(buffer.as_writable(), [0; 4]);
+}
+
+#[test]
+fn extended_slices_use_the_new_capacity() {
+    TypedBufferPool::<u32>::init_sync(1, 2);
+    assert!(TypedBufferPool::<u32>::set_slice_capacity(6).is_ok());
+
+    // the pool is exhausted and extended with slices of the new capacity
+    let first = TypedBufferPool::<u32>::slice();
+    let mut second = TypedBufferPool::<u32>::slice();
+    assert!(first.is_pooled() && second.is_pooled());
+    assert_eq!(second.as_writable().len(), 6);
+}
+
+#[test]
+fn resize_errors() {
+    assert_eq!(
+        TypedBufferPool::<i8>::set_slice_capacity(4).err(),
+        Some(ErrorKind::NotConnected)
+    );
+
+    TypedBufferPool::<i32>::init_arena(1, 4);
+    assert_eq!(
+        TypedBufferPool::<i32>::set_slice_capacity(8).err(),
+        Some(ErrorKind::Unsupported)
+    );
+}
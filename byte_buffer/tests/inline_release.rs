@@ -0,0 +1,51 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::time::Duration;
+
+#[test]
+fn inline_release_skips_the_worker() {
+    ByteBuffer::init(1, 4);
+    ByteBuffer::set_inline_release(true);
+
+    let buffer = ByteBuffer::slice_from(b"abc");
+    assert!(ByteBuffer::try_slice().is_none());
+    drop(buffer);
+
+    // the slice is reset and back in the pool before `drop` returns
+    let mut buffer = ByteBuffer::try_slice().unwrap();
+    assert_eq!(buffer.read().unwrap(), b"");
+    assert_eq!(buffer.as_writable(), [0; 4]);
+}
+
+#[test]
+fn inline_release_can_be_turned_off() {
+    TypedBufferPool::<u16>::init(1, 4);
+    TypedBufferPool::<u16>::set_inline_release(true);
+    TypedBufferPool::<u16>::set_inline_release(false);
+
+    drop(TypedBufferPool::<u16>::slice_from(&[1, 2]));
+
+    // back to the worker, which releases the slice eventually
+    let mut buffer = TypedBufferPool::<u16>::slice_blocking(Duration::from_secs(1)).unwrap();
+    assert_eq!(buffer.as_writable(), [0; 4]);
+}
+
+#[test]
+fn inline_release_of_extended_slices() {
+    TypedBufferPool::<u32>::init(1, 4);
+    TypedBufferPool::<u32>::set_inline_release(true);
+
+    // the pool is extended when it's exhausted
+    let first = TypedBufferPool::<u32>::slice();
+    let second = TypedBufferPool::<u32>::slice();
+    assert!(first.is_pooled() && second.is_pooled());
+
+    drop(first);
+    drop(second);
+
+    let slices: Vec<_> = (0..2)
+        .map(|_| TypedBufferPool::<u32>::try_slice().unwrap())
+        .collect();
+    assert_ne!(slices[0].id(), slices[1].id());
+}
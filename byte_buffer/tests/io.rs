@@ -0,0 +1,64 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::collections::HashSet;
+use std::io::{IoSlice, Write};
+
+#[test]
+fn write_vectored_assembles_slices() {
+    ByteBuffer::init(1, 16);
+
+    let mut buffer = ByteBuffer::slice();
+    let bufs = [
+        IoSlice::new(b"head"),
+        IoSlice::new(b"-body-"),
+        IoSlice::new(b"tail"),
+    ];
+
+    assert_eq!(buffer.write_vectored(&bufs).unwrap(), 14);
+    assert_eq!(buffer.read().unwrap(), b"head-body-tail");
+    assert_eq!(buffer.position(), 14);
+
+    // only the bytes that can fit into the slice are written
+    assert_eq!(buffer.write_vectored(&bufs).unwrap(), 2);
+    assert_eq!(buffer.read().unwrap(), b"head-body-tailhe");
+    assert_eq!(buffer.write_vectored(&bufs).unwrap(), 0);
+}
+
+#[test]
+fn chunks_over_written_prefix() {
+    ByteBuffer::init(2, 16);
+
+    let mut buffer = ByteBuffer::slice_from(b"aabbccd");
+    assert_eq!(buffer.as_chunks(2).count(), 4);
+
+    for chunk in buffer.as_chunks_mut(2) {
+        chunk[0] = b'x';
+    }
+
+    assert_eq!(buffer.read().unwrap(), b"xaxbxcx");
+    assert_eq!(buffer.as_chunks(16).next().unwrap().len(), 7);
+}
+
+#[test]
+fn equality_over_written_content() {
+    ByteBuffer::init(4, 16);
+
+    let a = ByteBuffer::slice_from(b"hello");
+    let b = ByteBuffer::slice_from(b"hello");
+    let c = ByteBuffer::slice_from(b"hello, world");
+    assert!(a == b);
+    assert!(a != c);
+
+    // the bytes beyond the written mark don't count
+    let mut d = ByteBuffer::slice_from(b"hello, world");
+    d.truncate(5);
+    assert!(a == d);
+
+    // fallback vectors compare the same way as the pooled slices
+    let long = [b'x'; 32];
+    assert!(ByteBuffer::slice_from(&long) == ByteBuffer::slice_from(&long));
+
+    let keys: HashSet<BufferSlice<u8>> = vec![a, b, c, d].into_iter().collect();
+    assert_eq!(keys.len(), 2);
+}
@@ -0,0 +1,40 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::time::Duration;
+
+#[test]
+fn reset_restores_capacity_and_zeroes() {
+    ByteBuffer::init(1, 64);
+
+    let mut buffer = ByteBuffer::slice();
+    let vec = buffer.as_writable_vec();
+    vec.resize(500, 0);
+    vec.iter_mut().for_each(|val| *val = 0xff);
+    drop(buffer);
+
+    // the only slice in the pool can be reserved again once the worker has released it
+    let mut buffer = ByteBuffer::slice_blocking(Duration::from_secs(1)).unwrap();
+    buffer.set_written(64);
+
+    let content = buffer.read().unwrap();
+
+    assert_eq!(content.len(), 64);
+    assert!(content.iter().all(|val| *val == 0));
+}
+
+#[test]
+fn clear_policy_none_keeps_stale_content() {
+    TypedBufferPool::<u16>::init_with_clear(1, 4, ClearPolicy::None);
+
+    let mut buffer = TypedBufferPool::<u16>::slice();
+    let vec = buffer.as_writable_vec();
+    vec.resize(8, 0);
+    vec.iter_mut().for_each(|val| *val = 7);
+    drop(buffer);
+
+    // the slice is cut back to the capacity, but the content is left as is
+    let mut buffer = TypedBufferPool::<u16>::slice_blocking(Duration::from_secs(1)).unwrap();
+    assert_eq!(buffer.read().unwrap().len(), 0);
+    assert_eq!(buffer.as_writable(), [7; 4]);
+}
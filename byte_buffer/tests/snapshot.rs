@@ -0,0 +1,32 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::panic;
+
+#[test]
+fn for_each_slice_skips_checked_out_and_unlocks_on_panic() {
+    TypedBufferPool::<u32>::init_sync(2, 4);
+
+    let buffer = TypedBufferPool::<u32>::slice_from(&[1, 2, 3, 4]);
+
+    let mut ids = Vec::new();
+    TypedBufferPool::<u32>::for_each_slice(|id, _| ids.push(id));
+    assert_eq!(ids.len(), 1);
+    assert_ne!(Some(ids[0]), buffer.id());
+
+    // a panicking visitor shall not leave the pool locked
+    let ret = panic::catch_unwind(|| {
+        TypedBufferPool::<u32>::for_each_slice(|_, _| panic!("visitor panicked"));
+    });
+    assert!(ret.is_err());
+
+    let other = TypedBufferPool::<u32>::slice();
+    assert!(other.is_pooled());
+
+    drop(buffer);
+    drop(other);
+
+    let mut count = 0;
+    TypedBufferPool::<u32>::for_each_slice(|_, _| count += 1);
+    assert_eq!(count, 2);
+}
@@ -0,0 +1,42 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+
+#[test]
+fn sync_release_is_done_on_drop() {
+    ByteBuffer::init_sync(1, 4);
+
+    let buffer = ByteBuffer::slice_from(b"ab");
+    assert!(ByteBuffer::try_slice().is_none());
+    drop(buffer);
+
+    // no worker involved, the slice is reset and back in the pool right away
+    let mut buffer = ByteBuffer::try_slice().unwrap();
+    assert_eq!(buffer.as_writable(), [0; 4]);
+}
+
+#[test]
+fn sync_pool_extends_when_exhausted() {
+    TypedBufferPool::<u16>::init_sync(1, 4);
+
+    let first = TypedBufferPool::<u16>::slice();
+    let second = TypedBufferPool::<u16>::slice();
+    assert!(first.is_pooled() && second.is_pooled());
+    assert_ne!(first.id(), second.id());
+
+    drop(first);
+    drop(second);
+
+    let mut count = 0;
+    TypedBufferPool::<u16>::for_each_slice(|_, _| count += 1);
+    assert!(count >= 2);
+}
+
+#[test]
+fn sync_pool_ignores_inline_release() {
+    TypedBufferPool::<u32>::init_sync(1, 4);
+    TypedBufferPool::<u32>::set_inline_release(false);
+
+    drop(TypedBufferPool::<u32>::slice_from(&[1, 2]));
+    assert!(TypedBufferPool::<u32>::try_slice().is_some());
+}
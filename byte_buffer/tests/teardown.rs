@@ -0,0 +1,50 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::time::Duration;
+
+#[test]
+fn teardown_then_init_with_fresh_parameters() {
+    ByteBuffer::init(1, 4);
+
+    let buffer = ByteBuffer::slice_from(b"abcd");
+    assert!(ByteBuffer::try_slice().is_none());
+    drop(buffer);
+
+    // make sure the worker is done with the slice before tearing down the pool
+    drop(ByteBuffer::slice_blocking(Duration::from_secs(1)).unwrap());
+    unsafe { ByteBuffer::teardown() };
+
+    assert_eq!(
+        ByteBuffer::try_slice_detailed().err(),
+        Some(SliceError::NotInitialized)
+    );
+
+    // without a pool, `slice` can only hand out fallback slices
+    assert!(!ByteBuffer::slice().is_pooled());
+
+    ByteBuffer::init(2, 8);
+
+    let mut first = ByteBuffer::slice();
+    let second = ByteBuffer::slice();
+    assert!(first.is_pooled() && second.is_pooled());
+    assert_eq!(first.as_writable().len(), 8);
+    assert!(ByteBuffer::try_slice().is_none());
+}
+
+#[test]
+fn teardown_sync_pool() {
+    TypedBufferPool::<u16>::init_sync(1, 4);
+    drop(TypedBufferPool::<u16>::slice());
+
+    // there's no worker to join
+    unsafe { TypedBufferPool::<u16>::teardown() };
+    assert!(TypedBufferPool::<u16>::try_slice().is_none());
+
+    // tearing down an uninitialized pool is a no-op
+    unsafe { TypedBufferPool::<u16>::teardown() };
+
+    TypedBufferPool::<u16>::init_sync(1, 2);
+    let mut buffer = TypedBufferPool::<u16>::try_slice().unwrap();
+    assert_eq!(buffer.as_writable(), [0; 2]);
+}
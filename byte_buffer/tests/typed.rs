@@ -0,0 +1,47 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::time::Duration;
+
+#[test]
+fn typed_pools_are_independent() {
+    TypedBufferPool::<u32>::init(1, 4);
+    TypedBufferPool::<f32>::init(1, 2);
+
+    let samples = TypedBufferPool::<u32>::try_slice().unwrap();
+    assert!(TypedBufferPool::<u32>::try_slice().is_none());
+
+    // exhausting the pool of one element type leaves the others alone
+    let mut frame = TypedBufferPool::<f32>::try_slice().unwrap();
+    assert_eq!(frame.append(&[0.5, -0.5, 1.0]), 2);
+    assert_eq!(frame.read().unwrap(), [0.5, -0.5]);
+
+    drop(samples);
+}
+
+#[test]
+fn typed_release_resets_to_default() {
+    TypedBufferPool::<i16>::init(1, 3);
+
+    let buffer = TypedBufferPool::<i16>::slice_from(&[-1, -2, -3]);
+    drop(buffer);
+
+    let mut buffer = TypedBufferPool::<i16>::slice_blocking(Duration::from_secs(1)).unwrap();
+    assert_eq!(buffer.read().unwrap(), []);
+    assert_eq!(buffer.as_writable(), [0; 3]);
+}
+
+#[test]
+fn typed_pool_extends_when_exhausted() {
+    TypedBufferPool::<f64>::init(1, 2);
+
+    let first = TypedBufferPool::<f64>::slice();
+    let mut second = TypedBufferPool::<f64>::slice();
+    assert!(first.is_pooled() && second.is_pooled());
+    assert_eq!(second.as_writable(), [0.0; 2]);
+
+    // a source larger than the slice is copied into a fallback vector
+    let large = TypedBufferPool::<f64>::slice_from(&[1.0; 4]);
+    assert!(!large.is_pooled());
+    assert_eq!(large.read().unwrap(), [1.0; 4]);
+}
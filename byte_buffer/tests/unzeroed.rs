@@ -0,0 +1,52 @@
+#![cfg(feature = "unzeroed")]
+
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::mem::MaybeUninit;
+use std::time::Duration;
+
+#[test]
+fn unzeroed_release_keeps_the_stale_content() {
+    unsafe { ByteBuffer::init_unzeroed(1, 4) };
+
+    let mut buffer = ByteBuffer::slice();
+    buffer
+        .as_uninit_mut()
+        .iter_mut()
+        .for_each(|val| *val = MaybeUninit::new(9));
+    unsafe { buffer.set_len(4) };
+    drop(buffer);
+
+    // the reused slice is handed out with nothing written, but the bytes are not cleared
+    let mut buffer = ByteBuffer::slice_blocking(Duration::from_secs(1)).unwrap();
+    assert_eq!(buffer.read().unwrap(), b"");
+
+    unsafe { buffer.set_len(4) };
+    assert_eq!(buffer.read().unwrap(), [9; 4]);
+}
+
+#[test]
+fn unzeroed_safe_accessors_zero_the_unwritten() {
+    unsafe { TypedBufferPool::<u16>::init_unzeroed(1, 4) };
+
+    let mut buffer = TypedBufferPool::<u16>::slice();
+    buffer.as_uninit_mut()[0] = MaybeUninit::new(5);
+    unsafe { buffer.set_len(1) };
+
+    assert_eq!(buffer.as_writable(), [5, 0, 0, 0]);
+}
+
+#[test]
+fn unzeroed_fallback_is_zeroed() {
+    unsafe { TypedBufferPool::<u32>::init_unzeroed(1, 4) };
+    TypedBufferPool::<u32>::set_size_limit(1);
+
+    let pooled = TypedBufferPool::<u32>::slice();
+    assert!(pooled.is_pooled());
+
+    // the pool can't be extended, and the fallback vector is never left uninitialized
+    let mut fallback = TypedBufferPool::<u32>::slice();
+    assert!(!fallback.is_pooled());
+    assert_eq!(fallback.as_writable(), [0; 4]);
+}
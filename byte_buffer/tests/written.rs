@@ -0,0 +1,56 @@
+extern crate byte_buffer;
+
+use byte_buffer::prelude::*;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+#[test]
+fn written_mark_is_reset_on_release() {
+    ByteBuffer::init(1, 8);
+
+    let buffer = ByteBuffer::slice_from(b"abc");
+    assert_eq!(buffer.read().unwrap(), b"abc");
+    assert_eq!(buffer.copy_to_vec(), b"abc");
+    drop(buffer);
+
+    // the reused slice starts over with nothing written
+    let mut buffer = ByteBuffer::slice_blocking(Duration::from_secs(1)).unwrap();
+    assert_eq!(buffer.read().unwrap(), b"");
+    assert_eq!(buffer.remaining_capacity(), 8);
+
+    buffer.as_writable()[..2].copy_from_slice(b"xy");
+    buffer.set_written(2);
+    assert_eq!(buffer.try_into_string().unwrap(), "xy");
+}
+
+#[test]
+fn writes_beyond_the_slice_are_rejected() {
+    TypedBufferPool::<u16>::init(1, 4);
+
+    let mut buffer = TypedBufferPool::<u16>::slice_from(&[1, 2]);
+    assert_eq!(
+        buffer.write_at(3, &[7, 8]).err(),
+        Some(ErrorKind::InvalidInput)
+    );
+    assert_eq!(buffer.read().unwrap(), [1, 2]);
+
+    // only the elements that fit are appended, and the mark stops at the end of the slice
+    assert_eq!(buffer.append(&[3, 4, 5]), 2);
+    assert_eq!(buffer.read().unwrap(), [1, 2, 3, 4]);
+    assert_eq!(buffer.remaining_capacity(), 0);
+}
+
+#[test]
+fn fallback_slices_track_the_written_mark() {
+    TypedBufferPool::<u32>::init_sync(1, 4);
+    TypedBufferPool::<u32>::set_size_limit(1);
+
+    let pooled = TypedBufferPool::<u32>::slice();
+    let mut fallback = TypedBufferPool::<u32>::slice();
+    assert!(pooled.is_pooled());
+    assert!(!fallback.is_pooled());
+
+    assert_eq!(fallback.read().unwrap(), []);
+    assert!(fallback.write_at(1, &[9]).is_ok());
+    assert_eq!(fallback.read().unwrap(), [0, 9]);
+}
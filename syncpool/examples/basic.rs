@@ -1,8 +1,11 @@
+#![allow(dead_code)]
+
 extern crate syncpool;
 
 use std::collections::HashMap;
 use std::mem::MaybeUninit;
 use std::pin::Pin;
+use std::ptr;
 use std::sync::mpsc;
 use std::sync::mpsc::SyncSender;
 use std::thread;
@@ -32,11 +35,12 @@ unsafe fn pool_setup() -> (
     Pin<&'static mut SyncPool<ComplexStruct>>,
     Pin<&'static mut SyncPool<ComplexStruct>>,
 ) {
-    POOL.as_mut_ptr().write(SyncPool::with_size(COUNT / 2));
+    let pool = &mut *ptr::addr_of_mut!(POOL);
+    pool.as_mut_ptr().write(SyncPool::with_size(COUNT / 2));
 
     (
-        Pin::new(&mut *POOL.as_mut_ptr()),
-        Pin::new(&mut *POOL.as_mut_ptr()),
+        Pin::new(&mut *pool.as_mut_ptr()),
+        Pin::new(&mut *pool.as_mut_ptr()),
     )
 }
 
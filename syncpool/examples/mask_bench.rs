@@ -0,0 +1,52 @@
+extern crate syncpool;
+
+use std::time::{Duration, Instant};
+use syncpool::prelude::*;
+
+/// Number of get/put round trips in each trial
+const OPS: usize = 1_000_000;
+
+/// Number of trials to run for each pool
+const TRIAL_RUNS: usize = 16;
+
+/// Number of elements held by each bucket of the pool
+const SLOT_CAP: usize = 8;
+
+/// Compare the per-op cost of the pools whose number of buckets is a power of 2, where the slot
+/// positions are wrapped with a mask, against the ones where the positions are wrapped with a modulo.
+fn main() {
+    for &(masked, modulo) in &[(4, 5), (16, 15), (64, 63)] {
+        let masked_time = run(masked);
+        let modulo_time = run(modulo);
+
+        println!(
+            "{:>3} buckets (mask): {:>6.2} ns/op; {:>3} buckets (modulo): {:>6.2} ns/op",
+            masked,
+            per_op(masked_time),
+            modulo,
+            per_op(modulo_time),
+        );
+    }
+}
+
+fn run(buckets: usize) -> Duration {
+    let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(buckets * SLOT_CAP);
+    let mut best = Duration::from_secs(u64::MAX);
+
+    for _ in 0..TRIAL_RUNS {
+        let now = Instant::now();
+
+        for _ in 0..OPS {
+            let val = pool.get();
+            pool.put(val);
+        }
+
+        best = best.min(now.elapsed());
+    }
+
+    best
+}
+
+fn per_op(elapsed: Duration) -> f64 {
+    elapsed.as_nanos() as f64 / OPS as f64
+}
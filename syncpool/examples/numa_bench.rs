@@ -0,0 +1,84 @@
+extern crate syncpool;
+
+use std::thread;
+use std::time::{Duration, Instant};
+use syncpool::prelude::*;
+
+/// Number of get/put round trips on each thread in each trial
+const OPS: usize = 200_000;
+
+/// Number of trials to run for each pool
+const TRIAL_RUNS: usize = 8;
+
+/// Number of elements held by the pool
+const POOL_SIZE: usize = 256;
+
+/// The pool is shared by all threads, just like a `static mut` pool would be.
+struct Shared(*mut SyncPool<Vec<u8>>);
+
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+/// Compare the per-op cost of the pool whose threads share the same starting position (and hence
+/// bounce the elements across the NUMA nodes), against the one partitioned by the NUMA nodes. Run
+/// with `--features numa` on a multi-socket Linux machine, otherwise both runs take the same path.
+fn main() {
+    let threads = thread::available_parallelism().map_or(4, |n| n.get());
+
+    let shared_time = run(threads, false);
+    let (numa_time, detected) = (run(threads, true), numa_detected());
+
+    println!(
+        "{} threads, NUMA topology {}",
+        threads,
+        if detected { "detected" } else { "unavailable" }
+    );
+    println!(
+        "shared cursor: {:>6.2} ns/op; NUMA affinity: {:>6.2} ns/op",
+        per_op(shared_time),
+        per_op(numa_time),
+    );
+}
+
+fn numa_detected() -> bool {
+    let mut pool: SyncPool<u8> = SyncPool::with_size(8);
+    pool.with_numa_affinity(true).numa_affinity()
+}
+
+fn run(threads: usize, numa: bool) -> Duration {
+    let mut pool = SyncPool::with_builder_and_size(POOL_SIZE, || vec![0u8; 4096]);
+    pool.with_numa_affinity(numa);
+
+    let mut best = Duration::from_secs(u64::MAX);
+
+    for _ in 0..TRIAL_RUNS {
+        let now = Instant::now();
+
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                let shared = Shared(&mut pool);
+
+                scope.spawn(move || {
+                    let shared = shared;
+                    let pool = unsafe { &mut *shared.0 };
+
+                    for i in 0..OPS {
+                        let mut val = pool.get();
+
+                        // touch the element, such that its cache lines move to this node
+                        val[i % 4096] = i as u8;
+                        pool.put(val);
+                    }
+                });
+            }
+        });
+
+        best = best.min(now.elapsed());
+    }
+
+    best
+}
+
+fn per_op(elapsed: Duration) -> f64 {
+    elapsed.as_nanos() as f64 / OPS as f64
+}
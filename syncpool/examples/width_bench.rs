@@ -0,0 +1,75 @@
+extern crate syncpool;
+
+use std::thread;
+use std::time::{Duration, Instant};
+use syncpool::prelude::*;
+
+/// Number of get/put round trips on each thread in each trial
+const OPS: usize = 200_000;
+
+/// Number of trials to run for each pool
+const TRIAL_RUNS: usize = 8;
+
+/// Number of elements held by the pool
+const POOL_SIZE: usize = 4096;
+
+/// Number of elements each thread holds at a time, such that the threads have to look past the
+/// drained buckets to find the elements
+const BATCH: usize = 16;
+
+/// The pool is shared by all threads, just like a `static mut` pool would be.
+struct Shared(*mut SyncPool<Vec<u8>>);
+
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+/// Compare the per-op cost of a large pool made of the buckets of different widths, where the wider
+/// buckets take fewer hops across the buckets, but more visitors land on the same bitmap.
+fn main() {
+    let threads = thread::available_parallelism().map_or(4, |n| n.get());
+    println!("{} threads, {} elements", threads, POOL_SIZE);
+
+    for &width in [8, 16, 32].iter() {
+        println!(
+            "width {:>2}: {:>6.2} ns/op",
+            width,
+            per_op(run(threads, width), threads)
+        );
+    }
+}
+
+fn run(threads: usize, width: usize) -> Duration {
+    let mut pool = SyncPool::with_slot_width(POOL_SIZE, width);
+    let mut best = Duration::from_secs(u64::MAX);
+
+    for _ in 0..TRIAL_RUNS {
+        let now = Instant::now();
+
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                let shared = Shared(&mut pool);
+
+                scope.spawn(move || {
+                    let shared = shared;
+                    let pool = unsafe { &mut *shared.0 };
+                    let mut batch = Vec::with_capacity(BATCH);
+
+                    for _ in 0..OPS / BATCH {
+                        batch.extend((0..BATCH).map(|_| pool.get()));
+                        batch.drain(..).for_each(|val| {
+                            pool.put(val);
+                        });
+                    }
+                });
+            }
+        });
+
+        best = best.min(now.elapsed());
+    }
+
+    best
+}
+
+fn per_op(elapsed: Duration, threads: usize) -> f64 {
+    elapsed.as_nanos() as f64 / (OPS * threads) as f64
+}
@@ -54,18 +54,62 @@
 //! ```
 #![allow(unused)]
 
-use std::alloc::{alloc, alloc_zeroed, Layout};
-use std::ptr;
+use alloc::alloc::{alloc, alloc_zeroed, handle_alloc_error, Layout};
+use alloc::boxed::Box;
+#[cfg(feature = "allocator_api")]
+use core::alloc::Allocator;
+use core::mem::MaybeUninit;
+use core::ptr::{self, NonNull};
+
+/// Create an uninitialized box on the heap directly, without moving a value from the stack to the
+/// heap. Unlike `raw_box`, the box is typed as `MaybeUninit<T>`, so holding it is always sound; the
+/// caller shall initialize the value (e.g. field by field via raw pointers) before converting it to
+/// `Box<T>` with `Box::assume_init`, or `Box::from_raw(Box::into_raw(boxed) as *mut T)`.
+///
+/// # Examples
+///
+/// ```
+/// use syncpool::raw_box_uninit;
+/// use std::ptr;
+///
+/// struct BigStruct {
+///     a: u32,
+///     b: bool,
+///     c: [u8; 0x1_000_000],
+/// }
+///
+/// let mut uninit = raw_box_uninit::<BigStruct>();
+/// let p = uninit.as_mut_ptr();
+///
+/// // initialize the fields in place, such that the large array won't touch the stack
+/// let big: Box<BigStruct> = unsafe {
+///     ptr::addr_of_mut!((*p).a).write(1);
+///     ptr::addr_of_mut!((*p).b).write(true);
+///     ptr::addr_of_mut!((*p).c).cast::<u8>().write_bytes(42, 0x1_000_000);
+///
+///     Box::from_raw(Box::into_raw(uninit) as *mut BigStruct)
+/// };
+///
+/// assert_eq!(big.a, 1);
+/// assert!(big.b);
+/// assert_eq!(big.c[4200], 42);
+/// ```
+pub fn raw_box_uninit<T>() -> Box<MaybeUninit<T>> {
+    unsafe { Box::from_raw(heap_alloc::<MaybeUninit<T>>(false)) }
+}
 
 /// Create a box structure without moving the wrapped value from the stack to the heap. This API is
 /// most useful when the wrapped value is too large for the default stack size, such that initializing
 /// and packing the valuing into the box is a pain.
 ///
+/// # Safety
+///
 /// Note that calling the API is unsafe, because it only creates a well-aligned memory structure in
 /// the heap, but all fields are in the state of undefined behavior at the moment. You *must* initialize
 /// the fields with default values, or pack it with meaningful placeholders. Using the object directly
 /// after being created by the API is *extremely* dangerous and will almost certainly lead to undefined
-/// behaviors.
+/// behaviors. In fact, even holding the `Box<T>` is undefined behavior if `T` has invalid bit patterns
+/// (e.g. a `bool`, a reference or an enum), so the API is deprecated in favor of `raw_box_uninit`.
 ///
 /// # Examples
 ///
@@ -81,6 +125,7 @@ use std::ptr;
 /// }
 ///
 /// // create the object on the heap directly
+/// # #[allow(deprecated)]
 /// let mut big: Box<BigStruct> = unsafe { raw_box::<BigStruct>() };
 ///
 /// // initialize the fields
@@ -93,15 +138,20 @@ use std::ptr;
 /// assert_eq!(big.c[4200], 0);
 /// assert_eq!(big.a, 0);
 /// ```
+#[deprecated(
+    since = "0.1.7",
+    note = "holding a `Box<T>` over uninitialized memory is unsound, use `raw_box_uninit` instead"
+)]
 pub unsafe fn raw_box<T>() -> Box<T> {
-    let layout = Layout::new::<T>();
-    Box::from_raw(alloc(layout) as *mut T)
+    Box::from_raw(heap_alloc::<T>(false))
 }
 
 /// Similar to `raw_box`, this API creates a box structure without moving the wrapped value from the
 /// stack to the heap. This API is most useful when the wrapped value is too large for the default
 /// stack size, such that initializing and packing the valuing into the box is a pain.
 ///
+/// # Safety
+///
 /// The only difference is that all fields in the object will be initialized to 0. This will initialize
 /// most primitive types, however, there is no warrant that the boxed object is valid or meaningful.
 /// For example, if the source struct contains pointers or another `Box`ed object, the fields are still
@@ -130,8 +180,7 @@ pub unsafe fn raw_box<T>() -> Box<T> {
 /// assert_eq!(big.a, 0);
 /// ```
 pub unsafe fn raw_box_zeroed<T>() -> Box<T> {
-    let layout = Layout::new::<T>();
-    Box::from_raw(alloc_zeroed(layout) as *mut T)
+    Box::from_raw(heap_alloc::<T>(true))
 }
 
 /// This API is a wrapper on the unsafer version of the direct-to-the-heap-box APIs. The API is safe
@@ -230,10 +279,163 @@ pub fn make_box<T, F: Fn(Box<T>) -> Box<T>>(packer: F) -> Box<T> {
 /// assert_eq!(boxed.b, 42);
 ///```
 pub fn default_box<T: Default>() -> Box<T> {
-    unsafe {
-        let p = alloc(Layout::new::<T>()) as *mut T;
-        ptr::write(p, Default::default());
-        Box::from_raw(p)
+    let mut boxed = raw_box_uninit::<T>();
+    boxed.write(Default::default());
+
+    // the value has been initialized
+    unsafe { Box::from_raw(Box::into_raw(boxed) as *mut T) }
+}
+
+/// Similar to `raw_box_uninit`, but the memory is allocated with the given allocator instead of the
+/// global one, e.g. an arena or a NUMA-local allocator. Requires the nightly-only `allocator_api`
+/// feature.
+///
+/// # Examples
+///
+/// ```
+/// #![feature(allocator_api)]
+///
+/// use syncpool::raw_box_uninit_in;
+/// use std::alloc::System;
+///
+/// let mut uninit = raw_box_uninit_in::<[u8; 0x1_000], _>(System);
+/// uninit.write([42; 0x1_000]);
+///
+/// let boxed = unsafe { uninit.assume_init() };
+/// assert_eq!(boxed[4000], 42);
+/// ```
+#[cfg(feature = "allocator_api")]
+pub fn raw_box_uninit_in<T, A: Allocator>(alloc: A) -> Box<MaybeUninit<T>, A> {
+    let p = heap_alloc_in::<MaybeUninit<T>, A>(&alloc, false);
+    unsafe { Box::from_raw_in(p, alloc) }
+}
+
+/// Similar to `raw_box_zeroed`, but the memory is allocated with the given allocator instead of the
+/// global one. Requires the nightly-only `allocator_api` feature.
+///
+/// # Safety
+///
+/// Same as `raw_box_zeroed`: all bytes of the object are zeroed, which is not a valid value for every
+/// type, e.g. a reference or a `NonNull` pointer.
+///
+/// # Examples
+///
+/// ```
+/// #![feature(allocator_api)]
+///
+/// use syncpool::raw_box_zeroed_in;
+/// use std::alloc::System;
+///
+/// let big = unsafe { raw_box_zeroed_in::<[u8; 0x1_000_000], _>(System) };
+/// assert_eq!(big[4200], 0);
+/// ```
+#[cfg(feature = "allocator_api")]
+pub unsafe fn raw_box_zeroed_in<T, A: Allocator>(alloc: A) -> Box<T, A> {
+    let p = heap_alloc_in::<T, A>(&alloc, true);
+    Box::from_raw_in(p, alloc)
+}
+
+/// Similar to `make_box`, but the memory is allocated with the given allocator instead of the global
+/// one, and the zeroed box is handed to the `packer` to be initialized. Requires the nightly-only
+/// `allocator_api` feature.
+///
+/// # Examples
+///
+/// ```
+/// #![feature(allocator_api)]
+///
+/// use syncpool::make_box_in;
+/// use std::alloc::System;
+///
+/// struct BigStruct {
+///     a: u32,
+///     c: [u8; 0x1_000_000],
+/// }
+///
+/// let big = make_box_in(System, |mut src: Box<BigStruct, System>| {
+///     src.a = 42;
+///     src.c[4200] = 125;
+///     src
+/// });
+///
+/// assert_eq!(big.a, 42);
+/// assert_eq!(big.c[4200], 125);
+/// ```
+#[cfg(feature = "allocator_api")]
+pub fn make_box_in<T, A, F>(alloc: A, packer: F) -> Box<T, A>
+where
+    A: Allocator,
+    F: Fn(Box<T, A>) -> Box<T, A>,
+{
+    let boxed = unsafe { raw_box_zeroed_in::<T, A>(alloc) };
+    packer(boxed)
+}
+
+/// Similar to `default_box`, but the memory is allocated with the given allocator instead of the
+/// global one. Requires the nightly-only `allocator_api` feature.
+///
+/// # Examples
+///
+/// ```
+/// #![feature(allocator_api)]
+///
+/// use syncpool::default_box_in;
+/// use std::alloc::System;
+///
+/// let boxed: Box<Vec<u8>, System> = default_box_in(System);
+/// assert!(boxed.is_empty());
+/// ```
+#[cfg(feature = "allocator_api")]
+pub fn default_box_in<T: Default, A: Allocator>(alloc: A) -> Box<T, A> {
+    let mut boxed = raw_box_uninit_in::<T, A>(alloc);
+    boxed.write(Default::default());
+
+    // the value has been initialized
+    unsafe { boxed.assume_init() }
+}
+
+/// Allocate the memory for `T` with the global allocator in the way `Box` expects: zero-sized types
+/// won't allocate, and we'll abort via `handle_alloc_error` if the allocation fails, since a null
+/// pointer can't be wrapped in a `Box`.
+fn heap_alloc<T>(zeroed: bool) -> *mut T {
+    let layout = Layout::new::<T>();
+    if layout.size() == 0 {
+        return NonNull::dangling().as_ptr();
+    }
+
+    let p = unsafe {
+        if zeroed {
+            alloc_zeroed(layout)
+        } else {
+            alloc(layout)
+        }
+    };
+
+    if p.is_null() {
+        handle_alloc_error(layout);
+    }
+
+    p as *mut T
+}
+
+/// Same as `heap_alloc`, but the memory comes from the given allocator, and shall be handed back to
+/// the same allocator when it's freed, i.e. via `Box::from_raw_in`.
+#[cfg(feature = "allocator_api")]
+fn heap_alloc_in<T, A: Allocator>(alloc: &A, zeroed: bool) -> *mut T {
+    let layout = Layout::new::<T>();
+    if layout.size() == 0 {
+        return NonNull::dangling().as_ptr();
+    }
+
+    let result = if zeroed {
+        alloc.allocate_zeroed(layout)
+    } else {
+        alloc.allocate(layout)
+    };
+
+    match result {
+        Ok(p) => p.as_ptr() as *mut T,
+        Err(_) => handle_alloc_error(layout),
     }
 }
 
@@ -281,19 +483,13 @@ mod boxed_tests {
         }
     }
 
-    fn make_test_box<T>(zeroed: bool) -> Box<T> {
-        unsafe {
-            if zeroed {
-                raw_box_zeroed::<T>()
-            } else {
-                raw_box::<T>()
-            }
-        }
+    fn make_test_box<T>() -> Box<T> {
+        unsafe { raw_box_zeroed::<T>() }
     }
 
     fn make_dangerous() -> Box<DangerousStruct> {
-        let mut boxed = make_test_box::<DangerousStruct>(true);
-        let mut big: &mut BigStruct = Box::leak(make_test_box::<BigStruct>(true));
+        let mut boxed = make_test_box::<DangerousStruct>();
+        let mut big: &mut BigStruct = Box::leak(make_test_box::<BigStruct>());
         big.a = 42;
         big.b = 4 * 42;
         big.c[4200] = 125;
@@ -307,7 +503,7 @@ mod boxed_tests {
 
     #[test]
     fn raw_box_test() {
-        let boxed = make_test_box::<BigStruct>(true);
+        let boxed = make_test_box::<BigStruct>();
 
         assert_eq!(boxed.c.len(), 0x1_000_000);
         assert_eq!(boxed.c[4200], 0);
@@ -342,10 +538,16 @@ mod boxed_tests {
 
     #[test]
     fn init() {
-        let mut boxed = make_test_box::<BigStruct>(false);
-        boxed.a = 1;
-        boxed.b = 42;
-        boxed.c = [0u8; 0x1_000_000];
+        let mut uninit = raw_box_uninit::<BigStruct>();
+        let p = uninit.as_mut_ptr();
+
+        let boxed = unsafe {
+            ptr::addr_of_mut!((*p).a).write(1);
+            ptr::addr_of_mut!((*p).b).write(42);
+            ptr::addr_of_mut!((*p).c).write_bytes(0, 1);
+
+            Box::from_raw(Box::into_raw(uninit) as *mut BigStruct)
+        };
 
         assert_eq!(boxed.a, 1);
         assert_eq!(boxed.b, 42);
@@ -364,7 +566,63 @@ mod boxed_tests {
         assert_eq!(big_ref.c[4200], 125);
 
         let atomic = unsafe { &*boxed.b.as_ptr() };
-        assert_eq!(atomic.load(Ordering::Acquire), false);
+        assert!(!atomic.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn zero_sized() {
+        let unit: Box<()> = default_box();
+        assert_eq!(*unit, ());
+
+        let empty = unsafe { raw_box_zeroed::<[u64; 0]>() };
+        assert!(empty.is_empty());
+    }
+
+    #[cfg(feature = "allocator_api")]
+    #[test]
+    fn custom_allocator() {
+        use core::alloc::{AllocError, Allocator};
+        use std::alloc::System;
+        use std::sync::atomic::AtomicUsize;
+
+        #[derive(Default)]
+        struct Counted {
+            allocs: AtomicUsize,
+            frees: AtomicUsize,
+        }
+
+        unsafe impl Allocator for &Counted {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.allocs.fetch_add(1, Ordering::SeqCst);
+                System.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.frees.fetch_add(1, Ordering::SeqCst);
+                System.deallocate(ptr, layout)
+            }
+        }
+
+        let counted = Counted::default();
+
+        let boxed = unsafe { raw_box_zeroed_in::<BigStruct, _>(&counted) };
+        assert_eq!(boxed.c[4200], 0);
+        assert_eq!(counted.allocs.load(Ordering::SeqCst), 1);
+
+        drop(boxed);
+        assert_eq!(counted.frees.load(Ordering::SeqCst), 1);
+
+        let defaulted: Box<BigStruct2, _> = default_box_in(&counted);
+        assert_eq!(defaulted.b, 42);
+        assert_eq!(counted.allocs.load(Ordering::SeqCst), 2);
+
+        // zero-sized types won't touch the allocator
+        let unit: Box<(), _> = default_box_in(&counted);
+        assert_eq!(counted.allocs.load(Ordering::SeqCst), 2);
+
+        drop(unit);
+        drop(defaulted);
+        assert_eq!(counted.frees.load(Ordering::SeqCst), 2);
     }
 
     #[test]
@@ -1,208 +1,106 @@
-#![allow(unused)]
-
-use crate::make_box;
 use crate::pool::ElemBuilder;
-use crate::utils::{check_len, cpu_relax, enter, make_elem};
-use std::mem;
-use std::ptr;
-use std::sync::atomic::{AtomicBool, AtomicU16, AtomicUsize, AtomicPtr, Ordering};
+use crate::utils::{check_len, cpu_relax, enter, enter_last, make_elem};
+use std::collections::HashSet;
+use std::mem::{self, MaybeUninit};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 /// Constants
 pub(crate) const SLOT_CAP: usize = 8;
-const TRIALS_COUNT: usize = 4;
-
-pub(crate) struct Bucket<T> {
-    /// the actual data store
-    slot: [Option<T>; SLOT_CAP],
-
-    /// the current ready-to-use slot index, always offset by 1 to the actual index
-    len: usize,
-
-    /// if the slot is currently being read/write to
-    access: AtomicBool,
-}
-
-impl<T> Bucket<T> {
-    pub(crate) fn new(filler: Option<fn() -> T>) -> Self {
-        // create the placeholder
-        let mut slice: [Option<T>; SLOT_CAP] = Default::default();
-
-        // fill the placeholder if required
-        if let Some(handle) = filler.as_ref() {
-            for item in slice.iter_mut() {
-                item.replace(handle());
-            }
-        }
-
-        // done
-        Bucket {
-            slot: slice,
-            len: SLOT_CAP,
-            access: AtomicBool::new(false),
-        }
-    }
-
-    pub(crate) fn size_hint(&self) -> usize {
-        self.len % (SLOT_CAP + 1)
-    }
-
-    pub(crate) fn access(&self, get: bool) -> bool {
-        // count down to lock timeout
-        let mut count = if get { 4 } else { 2 };
-
-        // check the access and wait if not available
-        while self
-            .access
-            .compare_exchange(false, true, Ordering::Acquire, Ordering::Acquire)
-            .is_err()
-        {
-            if count == 0 {
-                return false;
-            }
 
-            cpu_relax(2 * count);
-            count -= 1;
-        }
-
-        if (get && self.len == 0) || (!get && self.len == SLOT_CAP) {
-            // not actually locked
-            self.leave();
-
-            // read but empty, or write but full, all fail
-            return false;
-        }
-
-        true
-    }
+/// The most slots a bucket can hold, since each slot takes 2 bits of the 64-bit bitmap.
+pub(crate) const MAX_SLOT_CAP: usize = 32;
+pub(crate) const TRIALS_COUNT: usize = 4;
 
-    pub(crate) fn leave(&self) {
-        self.access.store(false, Ordering::Release);
-    }
+/// A snapshot of the internal states of a single bucket, see `SyncPool::bucket_debug`.
+#[cfg(any(test, feature = "invariants"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BucketDebug {
+    /// The raw bitmap of the slots, where the bit at (2 * pos) is set if the slot holds an element,
+    /// and the bit at (2 * pos + 1) is set if someone is operating at the slot.
+    pub bitmap: u64,
 
-    /// The function is safe because it's used internally, and each time it's guaranteed a access has
-    /// been acquired previously
-    pub(crate) fn checkout(&mut self) -> Result<T, ()> {
-        // need to loop over the slots to make sure we're getting the valid value, starting from
-        let i = self.len - 1;
-        let val = self.slot[i].take().ok_or(());
+    /// The speculative length hint, which is bumped before a visitor tries the bucket, and restored
+    /// if the visit fails. It may run ahead of the actual count while the bucket is being visited.
+    pub len: usize,
 
-        // update internal states if we're good
-        if val.is_ok() {
-            self.len = i;
-        }
-
-        // return the inner value
-        val
-    }
-
-    /// The function is safe because it's used internally, and each time it's guaranteed a access has
-    /// been acquired previously
-    pub(crate) fn release(&mut self, mut val: T, reset: Option<fn(&mut T)>) {
-        // need to loop over the slots to make sure we're getting the valid value
-        let i = self.len;
-        if i >= SLOT_CAP {
-            return;
-        }
-
-        if self.slot[i].is_none() {
-            // reset the struct before releasing it to the pool
-            if let Some(handle) = reset {
-                handle(&mut val);
-            }
-
-            // move the value in
-            self.slot[i].replace(val);
-
-            // update internal states
-            self.len = i + 1;
-
-            // done
-            return;
-        }
-
-        // if all slots are full, no need to fallback, the `val` will be dropped here
-        drop(val);
-    }
-
-    /*
-        fn swap_in(&mut self, index: usize, content: T) {
-            let src = &mut self.slot[index] as *mut Option<T>;
-            unsafe {
-                src.write(Some(content));
-            }
-        }
-
-        fn swap_out(&mut self, index: usize) -> T {
-            let src = &mut self.slot[index] as *mut Option<T>;
-
-            unsafe {
-                // save off the old values
-                let val = ptr::read(src).unwrap_or_default();
-
-                // swap values
-                src.write(None);
-
-                val
-            }
-        }
-    */
+    /// The number of elements committed in the bucket, counted from the element bits of the bitmap.
+    pub check_len: usize,
 }
 
 pub(crate) struct Bucket2<T> {
-    /// The actual data store. Data are stored in heap and not managed by the runtime, so we must
-    /// restore them and drop the data when the bucket is dropped.
-    slot: [*mut T; SLOT_CAP],
+    /// The actual data store. A slot is only initialized if its element bit is set in the `bitmap`,
+    /// and the element can only be moved in or out by the one holding the slot's lock bit, so we
+    /// must drop the initialized slots manually when the bucket is dropped.
+    slot: Box<[MaybeUninit<Box<T>>]>,
 
     /// the current ready-to-use slot count, always offset by 1 to the actual index. This may not be
     /// a real-time reflection of how many elements are actually in the bucket, especially if other
     /// threads are actively interact with the sync pool.
     len: AtomicUsize,
 
-    /// The bitmap of the slots. The implementation rely on the assumption that each bucket only contains
-    /// at most 8 elements, otherwise, we need to update the underlying atomic data structure.
+    /// The bitmap of the slots, which can track up to `MAX_SLOT_CAP` slots, though only the bits of
+    /// the first `width` slots are ever used.
     ///
     /// Each position's state are comprised with 2 consecutive bits at (2 * pos) and (2 * pos + 1),
     /// where the bit at (2 * pos) indicates if the slot contains an element (1 -> element; 0 -> empty);
     /// the bit at (2 * pos + 1) indicates if someone is operating at the slot, and hence everyone
     /// else shall avoid using the position, otherwise we may corrupt the underlying data structure.
-    bitmap: AtomicU16,
+    bitmap: AtomicU64,
 }
 
 impl<T> Bucket2<T> {
     /// Instantiate the bucket and set initial values. If we want to pre-fill the slots, we will also
     /// make sure the bitmap is updated as well.
-    pub(crate) fn new(filler: Option<&ElemBuilder<T>>) -> Self {
+    pub(crate) fn new(filler: Option<&ElemBuilder<T>>, width: usize) -> Self {
+        Self::with_count(filler, width, width)
+    }
+
+    /// Instantiate the bucket of `width` slots and only pre-fill the first `count` slots, the
+    /// remainder of the slots are left empty and can be filled by later `put`s.
+    pub(crate) fn with_count(filler: Option<&ElemBuilder<T>>, width: usize, count: usize) -> Self {
+        debug_assert!(width > 0 && width <= MAX_SLOT_CAP);
+
         // create the placeholder
-        let mut slice: [*mut T; SLOT_CAP] = [ptr::null_mut(); SLOT_CAP];
-        let mut bitmap: u16 = 0;
+        let mut slice: Box<[MaybeUninit<Box<T>>]> =
+            (0..width).map(|_| MaybeUninit::uninit()).collect();
+        let mut bitmap: u64 = 0;
+        let mut len = 0;
 
         // fill the slots and update the bitmap
         if let Some(handle) = filler {
-            for (i, item) in slice.iter_mut().enumerate() {
-                *item = Box::into_raw(make_elem(handle));
-                bitmap |= 1 << (2 * i as u16);
+            for (i, item) in slice.iter_mut().enumerate().take(count) {
+                item.write(make_elem(handle));
+                bitmap |= 1 << (2 * i);
+                len += 1;
             }
         }
 
         // done
         Bucket2 {
             slot: slice,
-            len: AtomicUsize::new(SLOT_CAP),
-            bitmap: AtomicU16::new(bitmap),
+            len: AtomicUsize::new(len),
+            bitmap: AtomicU64::new(bitmap),
         }
     }
 
-    /// Obtain the number of available elements in this bucket. The size is volatile if the API is
-    /// accessed concurrently with read/write, so the
-    pub(crate) fn size_hint(&self) -> usize {
-        self.len.load(Ordering::Acquire) % (SLOT_CAP + 1)
-        //        check_len(self.bitmap.load(Ordering::Acquire))
+    /// The number of slots of the bucket.
+    #[inline]
+    pub(crate) fn width(&self) -> usize {
+        self.slot.len()
+    }
+
+    /// Count the number of occupied slots from the element bits of the bitmap. Unlike the `len` hint,
+    /// the count is read from a single snapshot of the bitmap, though it's still volatile if the
+    /// bucket is being visited concurrently.
+    pub(crate) fn occupancy(&self) -> usize {
+        check_len(self.bitmap.load(Ordering::Acquire))
     }
 
     /// Try to locate a position where we can fulfil the request -- either grab an element from the
     /// bucket, or put an element back into the bucket. If such a request can't be done, we will
-    /// return error.
-    pub(crate) fn access(&self, get: bool) -> Result<usize, ()> {
+    /// return error. If `last` is set, we will look for the highest position instead of the lowest.
+    /// We will retry up to `trials` times if the bitmap is contended before giving up.
+    pub(crate) fn access(&self, get: bool, last: bool, trials: usize) -> Result<usize, ()> {
         // register intentions first, make sure the len is in post-action state so it can reject
         // future or concurrent attempts if it's unlikely to succeed in this slot.
         let curr_len = if get {
@@ -213,17 +111,24 @@ impl<T> Bucket2<T> {
 
         // oops, last op blew off the roof, back off mate. Note that (0 - 1 == MAX_USIZE) for stack
         // overflow, still way off the roof and a proof of not doing well.
-        if curr_len > SLOT_CAP || (get && curr_len == 0) {
+        if curr_len > self.width() || (get && curr_len == 0) {
             return self.access_failure(get);
         }
 
-        // try 2 times on this slot if the desired slot happens to be taken ...
-        let mut trials: usize = TRIALS_COUNT;
+        // try a few times on this slot if the desired slot happens to be taken ...
+        let mut trials = trials;
         while trials > 0 {
             trials -= 1;
 
             // init try
-            let (pos, mask) = match enter(self.bitmap.load(Ordering::Acquire), get) {
+            let bitmap = self.bitmap.load(Ordering::Acquire);
+            let found = if last {
+                enter_last(bitmap, self.width(), get)
+            } else {
+                enter(bitmap, self.width(), get)
+            };
+
+            let (pos, mask) = match found {
                 Ok(pos) => (pos, 0b10 << (2 * pos)),
                 Err(()) => continue,
             };
@@ -231,9 +136,15 @@ impl<T> Bucket2<T> {
             // main loop to try to update the bitmap
             let old = self.bitmap.fetch_or(mask, Ordering::AcqRel);
 
-            // if the lock bit we replaced was not yet marked at the atomic op, we're good
+            // if the lock bit we replaced was not yet marked at the atomic op, we're good, as long as
+            // the slot hasn't been taken or filled by someone else since we checked the bitmap;
+            // otherwise release the lock bit and try again.
             if old & mask == 0 {
-                return Ok(pos as usize);
+                if (old & (mask >> 1) != 0) == get {
+                    return Ok(pos);
+                }
+
+                self.bitmap.fetch_and(!mask, Ordering::AcqRel);
             }
 
             // otherwise, try again after some wait. The earliest registered gets some favor by
@@ -247,7 +158,7 @@ impl<T> Bucket2<T> {
     /// Update the bitmap to make sure: 1) the lock bit of the operated upon position is flipped back
     /// to free-to-use; 2) the marker bit of the operated upon position is properly updated. We should
     /// succeed at the first trial of the for-loop, otherwise we may in trouble.
-    pub(crate) fn leave(&self, pos: u16) {
+    pub(crate) fn leave(&self, pos: usize) {
         // the lock bit we want to toggle
         let lock_bit = 0b10 << (2 * pos);
 
@@ -268,42 +179,230 @@ impl<T> Bucket2<T> {
     /// access has been acquired previously.
     pub(crate) fn checkout(&mut self, pos: usize) -> Result<Box<T>, ()> {
         // check the boundary and underlying slot position before doing something with it.
-        if pos >= SLOT_CAP || self.slot[pos].is_null() {
+        if pos >= self.width() || !self.occupied(pos) {
             return Err(());
         }
 
-        // swap the pointer out of the slot, this is the raw pointer to the heap memory location of
-        // the underlying data. The swap operation is cheap, since *mut T is guaranteed to be 8-bytes
-        // in length and hence we'll run the "simplified" version of the mem swap which is cheaper
-        // to run.
-        let val = mem::replace(&mut self.slot[pos], ptr::null_mut());
-
-        // Restore to the box version, this won't allocate since the pointed to content already
-        // exist. This action is safe since all values we put behind the pointers are knocked out
-        // from its boxed version, guaranteed by the implementation of the `new` and `release` APIs.
-        Ok(unsafe { Box::from_raw(val) })
+        // Move the box out of the slot, this only copies the pointer to the heap memory location of
+        // the underlying data. This action is safe since the element bit guarantees the slot has been
+        // initialized by `new` or `release`, and the element bit will be cleared when we `leave`,
+        // such that the moved-out slot won't be read again.
+        Ok(unsafe { self.slot[pos].assume_init_read() })
     }
 
-    /// Release the element back into the pool. If a reset function has been previously provided, we
-    /// will call the function to reset the value before putting it back. The API will be no-op if
-    /// the desired operation can't be conducted, such as if the position is OOB, or the position
-    /// already contains an element.
+    /// Release the element back into the pool, the element shall have been reset by the pool before
+    /// being released. The API will be no-op if the desired operation can't be conducted, such as if
+    /// the position is OOB, or the position already contains an element.
     ///
     /// The function is safe because it's used internally, and each time it's guaranteed an exclusive
     /// access has been acquired previously
-    pub(crate) fn release(&mut self, pos: usize, mut val: Box<T>, reset: Option<fn(&mut T)>) {
+    pub(crate) fn release(&mut self, pos: usize, val: Box<T>) {
         // check if the slot has already been occupied (unlikely but still)
-        if pos >= SLOT_CAP || !self.slot[pos].is_null() {
+        if pos >= self.width() || self.occupied(pos) {
             return;
         }
 
-        // reset the struct before releasing it to the pool
-        if let Some(handle) = reset {
-            handle(&mut val);
+        // move the value in, the element bit will be set when we `leave`
+        self.slot[pos].write(val);
+    }
+
+    /// Invoke the handler on every element currently stored in the bucket, and return the number of
+    /// elements being visited.
+    ///
+    /// The function is safe because it's used internally, and each time it's guaranteed the write
+    /// barrier has been raised and all visitors have left the pool.
+    pub(crate) fn for_each_mut<F: FnMut(&mut T)>(&mut self, handle: &mut F) -> usize {
+        let bitmap = self.bitmap.load(Ordering::Acquire);
+        let mut count = 0;
+
+        for (i, item) in self.slot.iter_mut().enumerate() {
+            if bitmap & (1 << (2 * i)) == 0 {
+                continue;
+            }
+
+            handle(unsafe { item.assume_init_mut() });
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Same as `for_each_mut`, but the elements are only visited by their shared references.
+    ///
+    /// The function is safe because it's used internally, and each time it's guaranteed the write
+    /// barrier has been raised and all visitors have left the pool.
+    pub(crate) fn for_each<F: FnMut(&T)>(&self, handle: &mut F) -> usize {
+        let bitmap = self.bitmap.load(Ordering::Acquire);
+        let mut count = 0;
+
+        for (i, item) in self.slot.iter().enumerate() {
+            if bitmap & (1 << (2 * i)) == 0 {
+                continue;
+            }
+
+            handle(unsafe { item.assume_init_ref() });
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Take the elements matching the predicate out of the bucket and hand them over to the sink, the
+    /// vacated slots can be filled by later `put`s. Return the number of elements taken.
+    ///
+    /// The function is safe because it's used internally, and each time it's guaranteed the write
+    /// barrier has been raised and all visitors have left the pool.
+    pub(crate) fn take_if<P, S>(&mut self, pred: &mut P, sink: &mut S) -> usize
+    where
+        P: FnMut(&T) -> bool,
+        S: FnMut(Box<T>),
+    {
+        let mut bitmap = self.bitmap.load(Ordering::Acquire);
+        let mut count = 0;
+
+        for (i, item) in self.slot.iter_mut().enumerate() {
+            let mask = 1 << (2 * i);
+            if bitmap & mask == 0 || !pred(unsafe { item.assume_init_ref() }) {
+                continue;
+            }
+
+            // clear both the lock bit and the element bit of the position
+            bitmap &= !(0b11 << (2 * i));
+            count += 1;
+
+            sink(unsafe { item.assume_init_read() });
+        }
+
+        if count > 0 {
+            self.bitmap.store(bitmap, Ordering::Release);
+            self.len.fetch_sub(count, Ordering::AcqRel);
+        }
+
+        count
+    }
+
+    /// Move elements from `src` into the empty slots of the bucket until either the bucket is full or
+    /// the `src` is exhausted. Return the number of elements moved in.
+    ///
+    /// The function is safe because it's used internally, and each time it's guaranteed the write
+    /// barrier has been raised and all visitors have left the pool.
+    pub(crate) fn fill_from<I: Iterator<Item = Box<T>>>(&mut self, src: &mut I) -> usize {
+        let mut bitmap = self.bitmap.load(Ordering::Acquire);
+        let mut count = 0;
+
+        for (i, item) in self.slot.iter_mut().enumerate() {
+            let mask = 1 << (2 * i);
+            if bitmap & mask != 0 {
+                continue;
+            }
+
+            match src.next() {
+                Some(val) => {
+                    item.write(val);
+                    bitmap |= mask;
+                    count += 1;
+                }
+                None => break,
+            }
+        }
+
+        if count > 0 {
+            self.bitmap.store(bitmap, Ordering::Release);
+            self.len.fetch_add(count, Ordering::AcqRel);
         }
 
-        // move the value in
-        self.slot[pos] = Box::into_raw(val);
+        count
+    }
+
+    /// Take a snapshot of the bitmap and the length hint, which is volatile if the bucket is being
+    /// visited concurrently.
+    #[cfg(any(test, feature = "invariants"))]
+    pub(crate) fn debug_state(&self) -> BucketDebug {
+        let bitmap = self.bitmap.load(Ordering::Acquire);
+
+        BucketDebug {
+            bitmap,
+            len: self.len.load(Ordering::Acquire),
+            check_len: check_len(bitmap),
+        }
+    }
+
+    /// Panics if the internal states of the bucket are inconsistent: no lock bit shall be left set,
+    /// and the length hint shall match the number of elements marked by the element bits. Only
+    /// meaningful when no one else is visiting the bucket.
+    #[cfg(any(test, feature = "invariants"))]
+    pub(crate) fn assert_invariants(&self) {
+        let bitmap = self.bitmap.load(Ordering::Acquire);
+        let mut count = 0;
+
+        for i in 0..self.width() {
+            let pos = 2 * i;
+
+            assert_eq!(bitmap & (0b10 << pos), 0, "lock bit is left set at slot {}", i);
+
+            if bitmap & (1 << pos) != 0 {
+                count += 1;
+            }
+        }
+
+        assert_eq!(self.len.load(Ordering::Acquire), count, "length hint mismatches");
+    }
+
+    /// Check the internal states of the bucket at `idx`, and report the anomalies instead of panicking
+    /// on them: a lock bit left set, a length hint off the element bits, or an element whose address
+    /// has been seen in another slot already, as recorded in `seen`. Return the number of elements
+    /// held by the bucket. Only reliable when the write barrier has been raised and all visitors have
+    /// left the pool.
+    pub(crate) fn validate(
+        &self,
+        idx: usize,
+        seen: &mut HashSet<usize>,
+        anomalies: &mut Vec<String>,
+    ) -> usize {
+        let bitmap = self.bitmap.load(Ordering::Acquire);
+        let mut count = 0;
+
+        // all boxes of a zero-sized type share the same dangling address
+        let sized = mem::size_of::<T>() > 0;
+
+        for (i, item) in self.slot.iter().enumerate() {
+            let pos = 2 * i;
+
+            if bitmap & (0b10 << pos) != 0 {
+                anomalies.push(format!(
+                    "bucket {}: lock bit is left set at slot {}",
+                    idx, i
+                ));
+            }
+
+            if bitmap & (1 << pos) == 0 {
+                continue;
+            }
+
+            count += 1;
+
+            let addr = unsafe { &**item.assume_init_ref() } as *const T as usize;
+            if sized && !seen.insert(addr) {
+                anomalies.push(format!("bucket {}: element at slot {} is aliased", idx, i));
+            }
+        }
+
+        let len = self.len.load(Ordering::Acquire);
+        if len != count {
+            anomalies.push(format!(
+                "bucket {}: length hint {} mismatches {} elements",
+                idx, len, count
+            ));
+        }
+
+        count
+    }
+
+    /// Check if the slot at `pos` holds an element, which is only reliable to the one holding the
+    /// slot's lock bit, or when the write barrier has been raised.
+    #[inline]
+    fn occupied(&self, pos: usize) -> bool {
+        self.bitmap.load(Ordering::Acquire) & (1 << (2 * pos)) != 0
     }
 
     #[inline]
@@ -320,56 +419,23 @@ impl<T> Bucket2<T> {
 
 impl<T> Drop for Bucket2<T> {
     fn drop(&mut self) {
-        for item in self.slot.iter_mut() {
-            if item.is_null() {
+        let bitmap = *self.bitmap.get_mut();
+
+        for (i, item) in self.slot.iter_mut().enumerate() {
+            if bitmap & (1 << (2 * i)) == 0 {
                 continue;
             }
 
+            // drop both the element and its heap allocation
             unsafe {
-                ptr::drop_in_place(*item);
+                item.assume_init_drop();
             }
-            *item = ptr::null_mut();
         }
     }
 }
 
 unsafe impl<T: Send> Send for Bucket2<T> {}
 
-pub(crate) struct RingBucket<T> {
-    /// The actual data store. Data are stored in heap and not managed by the runtime, so we must
-    /// restore them and drop the data when the bucket is dropped.
-    slot: [AtomicPtr<T>; SLOT_CAP],
-
-    /// the current ready-to-use slot count, always offset by 1 to the actual index. This may not be
-    /// a real-time reflection of how many elements are actually in the bucket, especially if other
-    /// threads are actively interact with the sync pool.
-    len: AtomicUsize,
-
-    head: AtomicUsize,
-
-    tail: AtomicUsize,
-}
-
-impl<T> RingBucket<T> {
-    /// Instantiate the bucket and set initial values. If we want to pre-fill the slots, we will also
-    /// make sure the bitmap is updated as well.
-    pub(crate) fn new(filler: Option<&ElemBuilder<T>>) -> Self {
-        // create the placeholder
-        let mut slice: [AtomicPtr<T>; SLOT_CAP] = Default::default();
-
-        // fill the slots and update the bitmap
-        if let Some(handle) = filler {
-            for (_, item) in slice.iter_mut().enumerate() {
-                item.swap(Box::into_raw(make_elem(handle)), Ordering::SeqCst);
-            }
-        }
-
-        // done
-        RingBucket {
-            slot: slice,
-            len: AtomicUsize::new(SLOT_CAP),
-            head: AtomicUsize::new(0),
-            tail: AtomicUsize::new(SLOT_CAP),
-        }
-    }
-}
+/// The slots are only accessed after the slot's lock bit has been acquired, hence sharing the
+/// bucket is fine as long as the elements can be moved across threads.
+unsafe impl<T: Send> Sync for Bucket2<T> {}
@@ -0,0 +1,217 @@
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// The source of the unique ids of the thread caches, such that a thread can tell apart the elements
+/// cached for different pools.
+static CACHE_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The elements cached by the current thread, grouped by the pools they belong to.
+    static LOCAL_CACHES: RefCell<Vec<LocalCache>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The type-erased stack of elements cached by a thread for a single pool. The elements are stored
+/// as raw pointers since the thread local storage can't be generic over the element type, and the
+/// `drop_elem` handle is used to drop the elements left in the cache when the thread exits.
+struct LocalCache {
+    id: usize,
+    elems: Vec<*mut ()>,
+    cached: Arc<AtomicUsize>,
+    drop_elem: unsafe fn(*mut ()),
+}
+
+impl Drop for LocalCache {
+    fn drop(&mut self) {
+        self.cached.fetch_sub(self.elems.len(), Ordering::AcqRel);
+
+        for elem in self.elems.drain(..) {
+            unsafe { (self.drop_elem)(elem) };
+        }
+    }
+}
+
+unsafe fn drop_elem<T>(elem: *mut ()) {
+    drop(Box::from_raw(elem as *mut T));
+}
+
+/// The handle to the per-thread caches of a pool, where each thread can stash up to `cap` elements
+/// for itself without touching the shared buckets. The number of the cached elements of all threads
+/// is kept in `cached`, which is decremented as the elements leave the caches, or are dropped along
+/// with the caches of the exiting threads.
+pub(crate) struct ThreadCache<T> {
+    id: usize,
+    cap: usize,
+    cached: Arc<AtomicUsize>,
+    _marker: PhantomData<Box<T>>,
+}
+
+impl<T: 'static> ThreadCache<T> {
+    /// The elements may outlive the pool in the caches of other threads, and will only be dropped
+    /// when those threads exit, hence the elements can't borrow anything.
+    pub(crate) fn new(cap: usize, cached: Arc<AtomicUsize>) -> Self {
+        ThreadCache {
+            id: CACHE_ID.fetch_add(1, Ordering::Relaxed),
+            cap,
+            cached,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> ThreadCache<T> {
+    /// Make a handle to a new set of caches with the same capacity, e.g. for a cloned pool. The
+    /// element type has already been checked to be `'static` when this handle was made.
+    pub(crate) fn fork(&self, cached: Arc<AtomicUsize>) -> Self {
+        ThreadCache {
+            id: CACHE_ID.fetch_add(1, Ordering::Relaxed),
+            cap: self.cap,
+            cached,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Pop the most recently cached element of the current thread, if there's any.
+    pub(crate) fn pop(&self) -> Option<Box<T>> {
+        LOCAL_CACHES
+            .try_with(|caches| {
+                let mut caches = caches.try_borrow_mut().ok()?;
+                let cache = caches.iter_mut().find(|cache| cache.id == self.id)?;
+
+                let elem = cache.elems.pop()?;
+                self.cached.fetch_sub(1, Ordering::AcqRel);
+
+                Some(unsafe { Box::from_raw(elem as *mut T) })
+            })
+            .ok()
+            .flatten()
+    }
+
+    /// Stash the element in the current thread's cache, or hand it back if the cache is full.
+    pub(crate) fn push(&self, val: Box<T>) -> Result<(), Box<T>> {
+        let mut val = Some(val);
+
+        // the thread local storage may be unavailable if the thread is exiting
+        let _ = LOCAL_CACHES.try_with(|caches| {
+            let mut caches = match caches.try_borrow_mut() {
+                Ok(caches) => caches,
+                Err(_) => return,
+            };
+
+            let pos = match caches.iter().position(|cache| cache.id == self.id) {
+                Some(pos) => pos,
+                None => {
+                    caches.push(LocalCache {
+                        id: self.id,
+                        elems: Vec::with_capacity(self.cap),
+                        cached: Arc::clone(&self.cached),
+                        drop_elem: drop_elem::<T>,
+                    });
+
+                    caches.len() - 1
+                }
+            };
+
+            let cache = &mut caches[pos];
+            if cache.elems.len() < self.cap {
+                if let Some(elem) = val.take() {
+                    self.cached.fetch_add(1, Ordering::AcqRel);
+                    cache.elems.push(Box::into_raw(elem) as *mut ());
+                }
+            }
+        });
+
+        match val {
+            Some(val) => Err(val),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T> Drop for ThreadCache<T> {
+    fn drop(&mut self) {
+        // only the current thread's cache can be cleared, the elements cached by the other threads
+        // will be dropped when those threads exit.
+        let cache = LOCAL_CACHES
+            .try_with(|caches| {
+                let mut caches = caches.try_borrow_mut().ok()?;
+                let pos = caches.iter().position(|cache| cache.id == self.id)?;
+                Some(caches.swap_remove(pos))
+            })
+            .ok()
+            .flatten();
+
+        // drop the elements after releasing the borrow, in case they touch the caches on drop
+        drop(cache);
+    }
+}
+
+/// The number of pools whose last used bucket is remembered by each thread, the entries of the
+/// least recently registered pools are evicted first.
+const AFFINITY_CAP: usize = 16;
+
+thread_local! {
+    /// The last bucket visited by the current thread, paired with the id of the pool.
+    static LAST_BUCKETS: RefCell<Vec<(usize, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The handle to the bucket that each thread has visited the last time, such that the thread can
+/// start from a cache-warm bucket instead of the position shared by all threads.
+pub(crate) struct BucketAffinity {
+    id: usize,
+}
+
+impl BucketAffinity {
+    pub(crate) fn new() -> Self {
+        BucketAffinity {
+            id: CACHE_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// The bucket visited by the current thread the last time, if there's any.
+    pub(crate) fn last(&self) -> Option<usize> {
+        LAST_BUCKETS
+            .try_with(|buckets| {
+                let buckets = buckets.try_borrow().ok()?;
+                buckets
+                    .iter()
+                    .find(|(id, _)| *id == self.id)
+                    .map(|(_, pos)| *pos)
+            })
+            .ok()
+            .flatten()
+    }
+
+    /// Remember the bucket that the current thread has just visited.
+    pub(crate) fn set(&self, pos: usize) {
+        let _ = LAST_BUCKETS.try_with(|buckets| {
+            let mut buckets = match buckets.try_borrow_mut() {
+                Ok(buckets) => buckets,
+                Err(_) => return,
+            };
+
+            if let Some(entry) = buckets.iter_mut().find(|(id, _)| *id == self.id) {
+                entry.1 = pos;
+                return;
+            }
+
+            if buckets.len() >= AFFINITY_CAP {
+                buckets.remove(0);
+            }
+
+            buckets.push((self.id, pos));
+        });
+    }
+}
+
+impl Drop for BucketAffinity {
+    fn drop(&mut self) {
+        // the entries of the other threads will be evicted over time
+        let _ = LAST_BUCKETS.try_with(|buckets| {
+            if let Ok(mut buckets) = buckets.try_borrow_mut() {
+                buckets.retain(|(id, _)| *id != self.id);
+            }
+        });
+    }
+}
@@ -189,23 +189,77 @@
 //! You can find more complex (i.e. practical) use cases in the [examples](https://github.com/Chopinsky/byte_buffer/tree/master/sync_pool/examples)
 //! folder.
 //!
+//! ## `no_std` support
+//! The heap-direct boxing helpers (`raw_box_uninit`, `raw_box_zeroed`, `make_box` and `default_box`) only
+//! rely on the `alloc` crate, and remain available when the crate is built with
+//! `default-features = false`. The `SyncPool` itself requires the default `std` feature.
+//!
+//! ## Custom allocators
+//! On nightly, the `allocator_api` feature adds the `_in` variants of the boxing helpers
+//! (`raw_box_uninit_in`, `raw_box_zeroed_in`, `make_box_in` and `default_box_in`), which allocate
+//! the boxes with the given `Allocator` instead of the global one. The elements of a `SyncPool` are
+//! still allocated by the global allocator, i.e. the pool stores and hands out `Box<T>`.
+//!
+//! ## Metrics
+//! With the `metrics` feature enabled, the pool records the latency of every `get` and `put` call,
+//! which can be read via `SyncPool::timing_snapshot`. The instrumentation is compiled out entirely
+//! when the feature is off.
+//!
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+extern crate alloc;
 
 mod boxed;
+#[cfg(feature = "std")]
 mod bucket;
+#[cfg(feature = "std")]
+mod cache;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "std")]
+mod numa;
+#[cfg(feature = "std")]
 mod pool;
+#[cfg(feature = "std")]
 mod utils;
 
-pub use crate::{
-    boxed::{default_box, make_box, raw_box, raw_box_zeroed},
-    pool::{PoolManager, PoolState, SyncPool},
+#[allow(deprecated)]
+pub use crate::boxed::{default_box, make_box, raw_box, raw_box_uninit, raw_box_zeroed};
+
+#[cfg(feature = "allocator_api")]
+pub use crate::boxed::{default_box_in, make_box_in, raw_box_uninit_in, raw_box_zeroed_in};
+
+#[cfg(feature = "std")]
+pub use crate::pool::{
+    BatchGuard, BuilderKind, CheckoutError, CheckoutOutcome, ExpandError, PoolArc, PoolGuard,
+    PoolManager, PoolState, ReturnSink, ReusePolicy, SyncPool, ValidationReport,
 };
 
+#[cfg(all(feature = "std", any(test, feature = "invariants")))]
+pub use crate::bucket::BucketDebug;
+
+#[cfg(feature = "metrics")]
+pub use crate::metrics::{OpTiming, TimingStats};
+
 pub mod prelude {
     pub use crate::boxed::*;
-    pub use crate::{PoolManager, PoolState, SyncPool};
+
+    #[cfg(feature = "std")]
+    pub use crate::{
+        BatchGuard, BuilderKind, CheckoutError, CheckoutOutcome, ExpandError, PoolArc, PoolGuard,
+        PoolManager, PoolState, ReturnSink, ReusePolicy, SyncPool, ValidationReport,
+    };
+
+    #[cfg(all(feature = "std", any(test, feature = "invariants")))]
+    pub use crate::BucketDebug;
+
+    #[cfg(feature = "metrics")]
+    pub use crate::{OpTiming, TimingStats};
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
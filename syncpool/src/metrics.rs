@@ -0,0 +1,78 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// The accumulated latency of a single kind of pool operation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpTiming {
+    /// The number of calls being recorded.
+    pub count: u64,
+
+    /// The total time spent in all the recorded calls.
+    pub total: Duration,
+
+    /// The longest time spent in a single call.
+    pub max: Duration,
+}
+
+impl OpTiming {
+    /// The average time spent in a single call, or zero if no call has been recorded.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::from_nanos(0);
+        }
+
+        Duration::from_nanos((self.total.as_nanos() / u128::from(self.count)) as u64)
+    }
+}
+
+/// A snapshot of the latency of the `get` and `put` calls on a pool, including the time spent in
+/// spinning and backing off, see `SyncPool::timing_snapshot`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimingStats {
+    pub get: OpTiming,
+    pub put: OpTiming,
+}
+
+/// The atomic counters of the latency of a single kind of pool operation.
+#[derive(Default)]
+pub(crate) struct Timer {
+    count: AtomicU64,
+    total: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Timer {
+    /// Record the time elapsed since `start`.
+    #[inline]
+    pub(crate) fn record(&self, start: Instant) {
+        let elapsed = start.elapsed().as_nanos().min(u128::from(u64::MAX)) as u64;
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(elapsed, Ordering::Relaxed);
+        self.max.fetch_max(elapsed, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> OpTiming {
+        OpTiming {
+            count: self.count.load(Ordering::Relaxed),
+            total: Duration::from_nanos(self.total.load(Ordering::Relaxed)),
+            max: Duration::from_nanos(self.max.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// The timers of all the recorded pool operations.
+#[derive(Default)]
+pub(crate) struct Timings {
+    pub(crate) get: Timer,
+    pub(crate) put: Timer,
+}
+
+impl Timings {
+    pub(crate) fn snapshot(&self) -> TimingStats {
+        TimingStats {
+            get: self.get.snapshot(),
+            put: self.put.snapshot(),
+        }
+    }
+}
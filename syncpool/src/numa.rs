@@ -0,0 +1,133 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of lookups after which a thread queries its NUMA node again, since the thread may have
+/// been migrated to a CPU on another node in the meantime.
+const REFRESH_PERIOD: usize = 64;
+
+/// The cached number of NUMA nodes, 0 if it hasn't been queried yet.
+static NODES: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The NUMA node that the current thread has been running on the last time we've asked, and the
+    /// number of lookups left before we ask again.
+    static LAST_NODE: Cell<(Option<usize>, usize)> = const { Cell::new((None, 0)) };
+}
+
+/// The partition of the buckets by the NUMA nodes, where each node owns a contiguous range of the
+/// buckets, such that a thread starts looking for a bucket in the range owned by its own node.
+#[derive(Clone)]
+pub(crate) struct NumaAffinity {
+    nodes: usize,
+}
+
+impl NumaAffinity {
+    /// Query the NUMA topology, which is only done once for all pools. `None` will be returned if
+    /// there's only a single node, or if the topology can't be determined.
+    pub(crate) fn detect() -> Option<Self> {
+        let nodes = node_count();
+        if nodes > 1 {
+            Some(NumaAffinity { nodes })
+        } else {
+            None
+        }
+    }
+
+    /// A partition over `nodes` nodes regardless of the actual topology, e.g. to test the partition on
+    /// a single-node machine.
+    #[cfg(test)]
+    pub(crate) fn with_nodes(nodes: usize) -> Self {
+        NumaAffinity { nodes }
+    }
+
+    /// The range of the buckets owned by the current thread's node out of `slots` buckets, as the
+    /// `(start, len)` pair, or `None` if the node of the thread can't be determined.
+    pub(crate) fn range(&self, slots: usize) -> Option<(usize, usize)> {
+        let node = current_node().filter(|&node| node < self.nodes)?;
+        let start = node * slots / self.nodes;
+        let end = (node + 1) * slots / self.nodes;
+
+        // with fewer buckets than nodes, the nodes share the buckets
+        Some((start, (end - start).max(1)))
+    }
+}
+
+fn node_count() -> usize {
+    let nodes = NODES.load(Ordering::Relaxed);
+    if nodes > 0 {
+        return nodes;
+    }
+
+    let nodes = query_nodes().unwrap_or(1).max(1);
+    NODES.store(nodes, Ordering::Relaxed);
+    nodes
+}
+
+fn current_node() -> Option<usize> {
+    LAST_NODE
+        .try_with(|last| {
+            let (node, left) = last.get();
+            if left > 0 {
+                last.set((node, left - 1));
+                return node;
+            }
+
+            let node = query_node();
+            last.set((node, REFRESH_PERIOD));
+            node
+        })
+        .ok()
+        .flatten()
+}
+
+/// Pretend that the current thread is running on `node` from now on, such that the partition can be
+/// tested on a machine of any topology.
+#[cfg(test)]
+pub(crate) fn pin_current_node(node: usize) {
+    LAST_NODE.with(|last| last.set((Some(node), usize::MAX)));
+}
+
+/// The highest id of the online nodes plus 1, where the online nodes are listed as ranges, e.g.
+/// `0-1,3`, such that the node ids can be used as the indices directly.
+#[cfg(all(feature = "numa", target_os = "linux"))]
+fn query_nodes() -> Option<usize> {
+    let online = std::fs::read_to_string("/sys/devices/system/node/online").ok()?;
+
+    online
+        .trim()
+        .split(',')
+        .filter_map(|range| range.rsplit('-').next()?.parse::<usize>().ok())
+        .max()
+        .map(|node| node + 1)
+}
+
+#[cfg(not(all(feature = "numa", target_os = "linux")))]
+fn query_nodes() -> Option<usize> {
+    None
+}
+
+#[cfg(all(feature = "numa", target_os = "linux"))]
+fn query_node() -> Option<usize> {
+    let mut cpu: libc::c_uint = 0;
+    let mut node: libc::c_uint = 0;
+
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_getcpu,
+            &mut cpu as *mut libc::c_uint,
+            &mut node as *mut libc::c_uint,
+            std::ptr::null_mut::<libc::c_void>(),
+        )
+    };
+
+    if ret == 0 {
+        Some(node as usize)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(all(feature = "numa", target_os = "linux")))]
+fn query_node() -> Option<usize> {
+    None
+}
@@ -1,7 +1,24 @@
+use crate::boxed::raw_box_uninit;
 use crate::bucket::*;
-use crate::utils::{cpu_relax, make_elem};
-use std::ops::Add;
+use crate::cache::{BucketAffinity, ThreadCache};
+#[cfg(feature = "metrics")]
+use crate::metrics::{TimingStats, Timings};
+use crate::numa::NumaAffinity;
+use crate::utils::{cpu_relax, make_elem, try_make_elem};
+use crossbeam_queue::{ArrayQueue, SegQueue};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::any::Any;
+use std::collections::HashSet;
+use std::fmt;
+use std::iter;
+use std::mem::{self, MaybeUninit};
+use std::ops::{Add, Deref, DerefMut};
+use std::panic;
+use std::pin::Pin;
+use std::ptr;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
@@ -9,14 +26,287 @@ const POOL_SIZE: usize = 8;
 const EXPANSION_CAP: usize = 512;
 const SPIN_PERIOD: usize = 4;
 
+/// The longest time `get` will wait for an element to be returned in the strict capacity mode.
+const STRICT_WAIT: Duration = Duration::from_secs(5);
+
+/// The default time to wait for the write barrier before giving up on a reconfiguration, see
+/// `PoolManager::with_barrier_timeout`.
+const BARRIER_TIMEOUT: Duration = Duration::from_millis(16);
+
 /// Configuration flag (@ bit positions):
 /// 1 -> If the pool is allowed to expand when under pressure
+/// 2 -> If the most recently returned element shall be reused first
+/// 4 -> If the number of outstanding elements shall be capped by the pool's capacity
 const CONFIG_ALLOW_EXPANSION: usize = 1;
+const CONFIG_REUSE_LIFO: usize = 2;
+const CONFIG_STRICT_CAPACITY: usize = 4;
+
+/// The order in which the idle elements in the pool are handed out by `get`. Returned elements are
+/// always placed in the lowest empty position of a bucket, and the policy decides from which end of
+/// the bucket `get` looks for an element.
+///
+/// `Lifo` hands out the most recently returned element first, which is likely still hot in the CPU
+/// cache; while `Fifo` hands out the element that has been idle for the longest time first, which
+/// spreads the usage evenly over all elements at the cost of cache locality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReusePolicy {
+    Fifo,
+    Lifo,
+}
+
+/// The strategy that the pool uses to construct new elements, which is decided by the constructor
+/// that created the pool, or by the builder or packer set on the pool afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuilderKind {
+    /// The elements are created by `T::default`, see `SyncPool::new`.
+    Default,
+
+    /// The elements are created by the builder function, see `SyncPool::with_builder`.
+    Builder,
+
+    /// The elements are allocated first and then initialized by the packer function, see
+    /// `SyncPool::with_packer`.
+    Packer,
+
+    /// The elements are created by the fallible builder function, see `SyncPool::try_with_builder`.
+    TryBuilder,
+}
+
+/// The reasons that the pool is unable to offer an idle element, see `SyncPool::try_checkout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckoutError {
+    /// Someone has raised the write barrier and is likely modifying the pool.
+    Barrier,
+
+    /// No idle element was found in the pool during the sweep.
+    Empty,
+
+    /// The pool has been poisoned, see `SyncPool::poison`.
+    Poisoned,
+}
+
+/// Where an idle element has been taken from, such that it can be placed back to the same place.
+#[derive(Clone, Copy)]
+enum IdleSource {
+    Cache,
+    Slot,
+    Overflow,
+}
+
+/// How an element has been obtained by `SyncPool::checkout_timed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CheckoutOutcome {
+    /// The element was idle in the pool at the first sweep.
+    Immediate,
+
+    /// The element was returned to the pool while we were waiting.
+    WaitedThenPooled,
+
+    /// No element was returned to the pool before the deadline, so a new one has been created.
+    WaitedThenAllocated,
+}
+
+/// The reasons that we are unable to expand the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpandError {
+    /// The pool is not allowed to expand, see `PoolManager::allow_expansion`.
+    Disabled,
+
+    /// The pool has reached the maximum number of slots and can't be expanded any further.
+    CapReached,
+
+    /// Someone else has raised the write barrier and is likely modifying the pool.
+    Contended,
+
+    /// We've waited too long and still couldn't obtain an exclusive access to the pool.
+    Timeout,
+}
+
+/// The result of the self-check of the pool, see `SyncPool::validate`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The number of idle elements held by the buckets, counted from their bitmaps.
+    pub idle: usize,
+
+    /// The number of buckets being checked.
+    pub buckets: usize,
+
+    /// The human-readable descriptions of the inconsistencies found, empty if the pool is sound.
+    pub anomalies: Vec<String>,
+}
+
+impl ValidationReport {
+    /// If no inconsistency has been found.
+    pub fn is_ok(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+}
+
+/// The handle invoked on the elements returned to the pool, which either resets the element and
+/// always keeps it, or may reject the element such that it will be dropped instead of re-pooled.
+/// The dynamic handle may hold states, so it's called under a lock since `put` can be called from
+/// multiple threads.
+enum ResetHandle<T> {
+    Reset(fn(&mut T)),
+    Reject(fn(&mut T) -> bool),
+    Dyn(Mutex<DynHandle<T>>),
+}
+
+type DynHandle<T> = Box<dyn FnMut(&mut T) + Send>;
+
+impl<T> ResetHandle<T> {
+    /// Copy the handle, which is impossible for the dynamic one since the closure can't be cloned.
+    fn try_clone(&self) -> Option<Self> {
+        match self {
+            ResetHandle::Reset(handle) => Some(ResetHandle::Reset(*handle)),
+            ResetHandle::Reject(handle) => Some(ResetHandle::Reject(*handle)),
+            ResetHandle::Dyn(_) => None,
+        }
+    }
+
+    /// Invoke the handle, and return if the element shall be kept in the pool.
+    #[inline]
+    fn apply(&self, val: &mut T) -> bool {
+        match self {
+            ResetHandle::Reset(handle) => {
+                handle(val);
+                true
+            }
+            ResetHandle::Reject(handle) => handle(val),
+            ResetHandle::Dyn(handle) => {
+                // a handle that panicked before is still usable, it's up to the handle to recover
+                let mut handle = handle.lock().unwrap_or_else(|err| err.into_inner());
+                handle(val);
+                true
+            }
+        }
+    }
+}
 
 pub(crate) enum ElemBuilder<T> {
     Default(fn() -> Box<T>),
     Builder(fn() -> T),
     Packer(fn(Box<T>) -> Box<T>),
+    TryBuilder(TryBuilder<T>),
+}
+
+/// All builders are plain function pointers, so they can be copied regardless of the element type.
+impl<T> Clone for ElemBuilder<T> {
+    fn clone(&self) -> Self {
+        match self {
+            ElemBuilder::Default(builder) => ElemBuilder::Default(*builder),
+            ElemBuilder::Builder(builder) => ElemBuilder::Builder(*builder),
+            ElemBuilder::Packer(packer) => ElemBuilder::Packer(*packer),
+            ElemBuilder::TryBuilder(builder) => ElemBuilder::TryBuilder(TryBuilder {
+                builder: builder.builder,
+                call: builder.call,
+            }),
+        }
+    }
+}
+
+impl<T> ElemBuilder<T> {
+    fn kind(&self) -> BuilderKind {
+        match self {
+            ElemBuilder::Default(_) => BuilderKind::Default,
+            ElemBuilder::Builder(_) => BuilderKind::Builder,
+            ElemBuilder::Packer(_) => BuilderKind::Packer,
+            ElemBuilder::TryBuilder(_) => BuilderKind::TryBuilder,
+        }
+    }
+}
+
+/// The fallible builder with its error type erased, since the pool is not generic over the error
+/// type. The builder is kept as a raw pointer, and it can only be invoked via `call`, which is
+/// instantiated with the original error type and boxes the error up.
+pub(crate) struct TryBuilder<T> {
+    builder: *const (),
+    call: fn(*const ()) -> Result<T, Box<dyn Any + Send>>,
+}
+
+impl<T> TryBuilder<T> {
+    fn new<E: Send + 'static>(builder: fn() -> Result<T, E>) -> Self {
+        TryBuilder {
+            builder: builder as *const (),
+            call: call_erased::<T, E>,
+        }
+    }
+
+    /// Build an element, and box up the error if the builder fails.
+    pub(crate) fn build(&self) -> Result<T, Box<dyn Any + Send>> {
+        (self.call)(self.builder)
+    }
+}
+
+/// The raw pointer is a plain function pointer, which can be shared between threads.
+unsafe impl<T> Send for TryBuilder<T> {}
+unsafe impl<T> Sync for TryBuilder<T> {}
+
+fn call_erased<T, E: Send + 'static>(builder: *const ()) -> Result<T, Box<dyn Any + Send>> {
+    // the pointer is always made from a `fn() -> Result<T, E>` in `TryBuilder::new`
+    let builder: fn() -> Result<T, E> = unsafe { mem::transmute(builder) };
+    builder().map_err(|err| Box::new(err) as Box<dyn Any + Send>)
+}
+
+/// The position to start looking for a bucket from, which is either shared by all threads, or owned
+/// by each thread if the thread affinity is enabled, see `PoolManager::with_thread_affinity`. With
+/// the NUMA affinity, the shared position is confined to the buckets owned by the thread's node, see
+/// `PoolManager::with_numa_affinity`.
+struct Cursor<'a> {
+    shared: &'a AtomicUsize,
+    affinity: Option<&'a BucketAffinity>,
+    numa: Option<&'a NumaAffinity>,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(
+        shared: &'a AtomicUsize,
+        affinity: Option<&'a BucketAffinity>,
+        numa: Option<&'a NumaAffinity>,
+    ) -> Self {
+        Cursor {
+            shared,
+            affinity,
+            numa,
+        }
+    }
+
+    /// The position to start from out of `cap` buckets, i.e. where the current thread (or any
+    /// thread) has left.
+    #[inline]
+    fn start(&self, cap: usize) -> usize {
+        if let Some(pos) = self.affinity.and_then(BucketAffinity::last) {
+            return pos;
+        }
+
+        let shared = self.shared.load(Ordering::Acquire);
+        match self.numa.and_then(|numa| numa.range(cap)) {
+            Some((start, len)) => start + shared % len,
+            None => shared,
+        }
+    }
+
+    /// Remember the bucket that we've successfully visited.
+    #[inline]
+    fn settle(&self, pos: usize) {
+        match self.affinity {
+            Some(affinity) => affinity.set(pos),
+            None => self.shared.store(pos, Ordering::Release),
+        }
+    }
+
+    /// Move on to the next position, which is yet to be wrapped around the number of slots. The
+    /// shared cursor is moved forward for all threads, note that `fetch_add` returns the previous
+    /// value.
+    #[inline]
+    fn advance(&self, pos: usize) -> usize {
+        if self.affinity.is_some() || self.numa.is_some() {
+            return pos + 1;
+        }
+
+        self.shared.fetch_add(1, Ordering::AcqRel) + 1
+    }
 }
 
 struct VisitorGuard<'a>(&'a AtomicUsize);
@@ -25,28 +315,263 @@ impl<'a> VisitorGuard<'a> {
     fn register(base: &'a (AtomicUsize, AtomicBool), get: bool) -> Option<Self> {
         let mut count = 8;
 
-        // wait if the underlying storage is in protection mode
-        while base.1.load(Ordering::Relaxed) {
+        loop {
+            // wait if the underlying storage is in protection mode
+            while base.1.load(Ordering::SeqCst) {
+                if get {
+                    return None;
+                }
+
+                cpu_relax(count);
+
+                if count > 4 {
+                    count -= 1;
+                }
+            }
+
+            base.0.fetch_add(1, Ordering::SeqCst);
+
+            // the barrier may be raised right before we're counted, then leave and wait for it again
+            if !base.1.load(Ordering::SeqCst) {
+                return Some(VisitorGuard(&base.0));
+            }
+
+            base.0.fetch_sub(1, Ordering::SeqCst);
+
             if get {
                 return None;
             }
+        }
+    }
+}
+
+impl<'a> Drop for VisitorGuard<'a> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+struct BarrierGuard<'a>(&'a (AtomicUsize, AtomicBool));
 
-            cpu_relax(count);
+impl<'a> BarrierGuard<'a> {
+    /// Raise the write barrier and wait for all visitors to leave, for no longer than `timeout` if
+    /// it's given, or until they're all gone otherwise.
+    fn raise(
+        base: &'a (AtomicUsize, AtomicBool),
+        timeout: Option<Duration>,
+    ) -> Result<Self, ExpandError> {
+        // raise the write barrier now, if someone has already raised the flag to indicate the
+        // intention to write, let me go away.
+        if base
+            .1
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(ExpandError::Contended);
+        }
 
+        // busy waiting ... for all visitors to leave
+        let deadline = timeout.map(|timeout| Instant::now().add(timeout));
+        let mut count: usize = 8;
+        while base
+            .0
+            .compare_exchange(1, 0, Ordering::SeqCst, Ordering::Relaxed)
+            .is_err()
+        {
             if count > 4 {
+                cpu_relax(2);
                 count -= 1;
+            } else if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                // we don't own the storage yet, only lower the barrier we've raised
+                base.1.store(false, Ordering::Release);
+                return Err(ExpandError::Timeout);
+            } else {
+                thread::yield_now();
             }
         }
 
-        base.0.fetch_add(1, Ordering::AcqRel);
+        Ok(BarrierGuard(base))
+    }
+}
+
+impl<'a> Drop for BarrierGuard<'a> {
+    fn drop(&mut self) {
+        // restore the visitor count and lower the barrier, this also runs if we're unwinding; a
+        // visitor that is backing off from the barrier may still be counted, so don't overwrite it
+        (self.0).0.fetch_add(1, Ordering::SeqCst);
+        (self.0).1.store(false, Ordering::Release);
+    }
+}
+
+/// A batch of elements checked out from the `SyncPool` via `checkout_batch`, which derefs to a slice
+/// of the elements. All the elements will be returned to the pool together when the guard is dropped.
+pub struct BatchGuard<'a, T> {
+    pool: &'a mut SyncPool<T>,
+    vals: Vec<Box<T>>,
+}
+
+impl<'a, T> Deref for BatchGuard<'a, T> {
+    type Target = [Box<T>];
 
-        Some(VisitorGuard(&base.0))
+    fn deref(&self) -> &[Box<T>] {
+        &self.vals
     }
 }
 
-impl<'a> Drop for VisitorGuard<'a> {
+impl<'a, T> DerefMut for BatchGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut [Box<T>] {
+        &mut self.vals
+    }
+}
+
+impl<'a, T> Drop for BatchGuard<'a, T> {
     fn drop(&mut self) {
-        self.0.fetch_sub(1, Ordering::AcqRel);
+        let vals = mem::take(&mut self.vals);
+        self.pool.put_many(vals);
+    }
+}
+
+/// An element checked out from the `SyncPool` via `checkout`, which derefs to the element, and will
+/// be returned to the pool when the guard is dropped, including on an early return or unwinding.
+pub struct PoolGuard<'a, T> {
+    pool: &'a mut SyncPool<T>,
+    val: Option<Box<T>>,
+}
+
+impl<'a, T> PoolGuard<'a, T> {
+    /// Detach the element from the guard and take over its ownership, such that it won't be returned
+    /// to the pool when the guard is dropped. The element is still counted as outstanding until it's
+    /// returned via `put`, if ever.
+    pub fn into_inner(mut self) -> Box<T> {
+        // the value is only taken here or on drop, so it's always present
+        self.val
+            .take()
+            .expect("the element has been taken out of the guard")
+    }
+}
+
+impl<'a, T> Deref for PoolGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.val
+            .as_deref()
+            .expect("the element has been taken out of the guard")
+    }
+}
+
+impl<'a, T> DerefMut for PoolGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.val
+            .as_deref_mut()
+            .expect("the element has been taken out of the guard")
+    }
+}
+
+impl<'a, T> Drop for PoolGuard<'a, T> {
+    fn drop(&mut self) {
+        let val = match self.val.take() {
+            Some(val) => val,
+            None => return,
+        };
+
+        // don't wait on the pool being reconfigured, the element is simply discarded instead
+        if self.pool.visitor_counter.1.load(Ordering::Acquire) {
+            drop(val);
+            self.pool.settle(1);
+            return;
+        }
+
+        self.pool.put(val);
+    }
+}
+
+/// Return the element to the pool when the guard goes out of scope, including on unwinding.
+struct ReturnGuard<'a, T> {
+    pool: &'a mut SyncPool<T>,
+    val: Option<Box<T>>,
+}
+
+impl<'a, T> Drop for ReturnGuard<'a, T> {
+    fn drop(&mut self) {
+        if let Some(val) = self.val.take() {
+            self.pool.put(val);
+        }
+    }
+}
+
+/// A reference-counted element checked out from the `SyncPool` via `get_arc`, which can be cloned
+/// and shared among readers. The element will be returned to the originating pool once the last
+/// clone is dropped. Since the element is shared, it's only accessible as `&T` while outstanding.
+pub struct PoolArc<T>(Arc<PoolArcInner<T>>);
+
+struct PoolArcInner<T> {
+    /// The element, only taken out when the last reference is dropped
+    val: Option<Box<T>>,
+
+    /// The pool to return the element to
+    pool: *mut SyncPool<T>,
+}
+
+impl<T> Drop for PoolArcInner<T> {
+    fn drop(&mut self) {
+        if let Some(val) = self.val.take() {
+            // the caller of `get_arc` guarantees that the pool outlives the element
+            unsafe {
+                (*self.pool).put(val);
+            }
+        }
+    }
+}
+
+impl<T> Clone for PoolArc<T> {
+    fn clone(&self) -> Self {
+        PoolArc(Arc::clone(&self.0))
+    }
+}
+
+impl<T> Deref for PoolArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self.0.val {
+            Some(ref val) => val,
+            None => unreachable!("the element is only taken when the last reference is dropped"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PoolArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// The element is shared among threads as `&T`, and the last holder may return it to the pool from
+/// any thread, which is fine since the pool itself can be shared across threads.
+unsafe impl<T: Send + Sync> Send for PoolArc<T> {}
+unsafe impl<T: Send + Sync> Sync for PoolArc<T> {}
+
+/// The handle to send the elements back to the `SyncPool` from other threads without holding the
+/// pool, see `SyncPool::return_sink`. The handle is cheap to clone, and it can outlive the pool, in
+/// which case the elements sent afterwards are dropped along with the last clone of the handle.
+pub struct ReturnSink<T> {
+    queue: Arc<SegQueue<Box<T>>>,
+}
+
+impl<T> ReturnSink<T> {
+    /// Send the element back to the pool. The element will be placed into the pool the next time
+    /// the pool is looking for an idle element, or when `SyncPool::reclaim_returns` is called.
+    pub fn send(&self, val: Box<T>) {
+        self.queue.push(val);
+    }
+}
+
+impl<T> Clone for ReturnSink<T> {
+    fn clone(&self) -> Self {
+        ReturnSink {
+            queue: Arc::clone(&self.queue),
+        }
     }
 }
 
@@ -69,11 +594,67 @@ pub struct SyncPool<T> {
     /// the number of times we failed to find an in-store struct to offer
     miss_count: AtomicUsize,
 
+    /// the number of elements handed out by the pool that haven't been returned yet
+    outstanding: AtomicUsize,
+
+    /// the number of returned elements dropped for failing the `validate_on_put` check
+    invalid_count: AtomicUsize,
+
+    /// if the pool has been marked as poisoned, see `SyncPool::poison`
+    poisoned: AtomicBool,
+
     /// if we allow expansion of the pool
     configure: AtomicUsize,
 
     /// the handle to be invoked before putting the struct back
-    reset_handle: Option<fn(&mut T)>,
+    reset_handle: Option<ResetHandle<T>>,
+
+    /// The handle to be invoked on each idle element when the pool is dropped
+    drop_handle: Option<fn(&mut T)>,
+
+    /// The check that a returned element shall pass before it's placed back into the pool
+    validate_on_put: Option<fn(&T) -> bool>,
+
+    /// The bounded queue to hold the elements returned to the pool when all the slots are full, such
+    /// that the elements can still be recycled during a burst beyond the pool's capacity.
+    overflow: Option<ArrayQueue<Box<T>>>,
+
+    /// The elements sent back via the return sinks, which are yet to be placed into the pool.
+    returns: Arc<SegQueue<Box<T>>>,
+
+    /// The number of times to retry on a contended bucket before moving on to the next one.
+    access_trials: usize,
+
+    /// The longest time to wait for the write barrier when reconfiguring the pool.
+    barrier_timeout: Duration,
+
+    /// The number of elements requested at construction, before being rounded to whole buckets.
+    capacity_hint: usize,
+
+    /// The per-thread caches of the elements, which will be visited before the shared slots.
+    thread_cache: Option<ThreadCache<T>>,
+
+    /// The number of elements parked in the thread caches, including the ones left behind in the
+    /// caches replaced by `with_thread_cache`, which stay alive until their threads exit.
+    cached: Arc<AtomicUsize>,
+
+    /// The per-thread cursors to the buckets, which will be used instead of the shared `curr` if
+    /// the thread affinity is enabled.
+    affinity: Option<BucketAffinity>,
+
+    /// The partition of the buckets by the NUMA nodes, if the NUMA affinity is enabled.
+    numa: Option<NumaAffinity>,
+
+    /// The latency of the `get` and `put` calls
+    #[cfg(feature = "metrics")]
+    timings: Timings,
+
+    /// The mask to wrap the slot positions with, which is only available when the number of slots
+    /// is a power of 2, such that we can skip the (slower) modulo in the hot loops.
+    slots_mask: Option<usize>,
+
+    /// The number of slots of each bucket, see `SyncPool::with_slot_width`.
+    slot_cap: usize,
 
     /// The builder that will be tasked to create a new instance of the data when the pool is unable
     /// to render one.
@@ -94,7 +675,93 @@ impl<T: Default> SyncPool<T> {
             pool_size = 1
         }
 
-        Self::make_pool(pool_size, ElemBuilder::Default(Default::default))
+        let mut pool = Self::make_pool(pool_size, ElemBuilder::Default(Default::default));
+        pool.capacity_hint = size;
+        pool
+    }
+
+    /// Create a `SyncPool` with exactly `count` pre-defined elements. Unlike `with_size`, the size
+    /// will not be rounded to a multiple of 8: we will still allocate enough slots to hold all the
+    /// elements, but the remainder of the slots will be left empty, which can be filled by `put`
+    /// later. Hence `len` shall equal `count` right after the pool is created.
+    pub fn with_capacity_exact(count: usize) -> Self {
+        Self::make_pool_exact(count, ElemBuilder::Default(Default::default))
+    }
+
+    /// Create a `SyncPool` with pre-defined number of elements, which are held in the buckets of
+    /// `width` slots each, instead of 8. A large pool of wider buckets takes fewer hops across the
+    /// buckets to locate an element or an empty slot in `get` and `put`, at the cost of more visitors
+    /// contending on the bitmap of the same bucket. Just like `with_size`, the size is rounded to a
+    /// multiple of the `width`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `width` is 0, or more than 32.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<u64> = SyncPool::with_slot_width(1024, 32);
+    /// assert_eq!(pool.slot_cap(), 32);
+    /// assert_eq!(pool.bucket_count(), 32);
+    ///
+    /// let val = pool.get();
+    /// assert_eq!(pool.len(), 1023);
+    /// pool.put(val);
+    /// ```
+    pub fn with_slot_width(size: usize, width: usize) -> Self {
+        assert!(
+            width > 0 && width <= MAX_SLOT_CAP,
+            "the slot width shall be between 1 and {}, got {}",
+            MAX_SLOT_CAP,
+            width
+        );
+
+        let pool_size = (size / width).max(1);
+        let builder = ElemBuilder::Default(Default::default);
+
+        let mut pool = Self::make_pool_with_width(pool_size, width, builder);
+        pool.capacity_hint = size;
+        pool
+    }
+
+    /// Obtain an element from the pool just like `get`, but a recycled element will be overwritten
+    /// with `T::default()` in place before it's handed out, such that the caller always gets a clean
+    /// element without stale fields, even if no reset handle is set. A newly created element is built
+    /// by `T::default()` as well, instead of the pool's builder.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<(usize, String)> = SyncPool::with_size(8);
+    ///
+    /// let mut val = pool.get();
+    /// *val = (42, String::from("stale"));
+    /// pool.put(val);
+    ///
+    /// for _ in 0..8 {
+    ///     assert_eq!(*pool.get_init_default(), (0, String::new()));
+    /// }
+    /// ```
+    pub fn get_init_default(&mut self) -> Box<T> {
+        self.assert_not_poisoned();
+
+        let recycled = match self.get_idle(true) {
+            Some(val) => Some(val),
+            None => self.acquire_permit(),
+        };
+
+        match recycled {
+            Some(mut val) => {
+                *val = T::default();
+                val
+            }
+            None => Box::default(),
+        }
     }
 }
 
@@ -150,31 +817,77 @@ impl<T> SyncPool<T> {
             pool_size = 1
         }
 
-        Self::make_pool(pool_size, ElemBuilder::Builder(builder))
+        let mut pool = Self::make_pool(pool_size, ElemBuilder::Builder(builder));
+        pool.capacity_hint = size;
+        pool
     }
 
-    /// Create a pool with default size of 64 pre-allocated elements in it, which will use the `packer`
-    /// handler to initialize the element that's being provided by the pool.
+    /// Create a pool with the default size of 64 pre-allocated elements, which are created by a
+    /// `builder` that may fail, e.g. to allocate a huge buffer or to acquire an external resource.
+    /// The first error returned by the `builder` during the pre-allocation will be returned, and the
+    /// elements built so far will be dropped.
     ///
-    /// Note that the handler shall take a boxed instance of the element that only contains
-    /// placeholder fields, and it is the caller/handler's job to initialize the fields and pack it
-    /// with valid and meaningful values. If the struct is valid with all-zero values, the handler
-    /// can just return the input element.
+    /// The `builder` is also used when the pool is unable to offer an element later on. Use
+    /// `try_get_built` to receive the error in that case; `get` will panic if the `builder` fails.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use syncpool::*;
-    /// use std::vec;
+    /// use syncpool::prelude::*;
     ///
-    /// struct BigStruct {
-    ///     a: u32,
-    ///     b: u32,
-    ///     c: Vec<u8>,
-    /// }
+    /// let mut pool = SyncPool::try_with_builder(|| Ok::<_, String>(vec![0u8; 16])).unwrap();
+    /// assert_eq!(pool.len(), 64);
+    /// assert_eq!(pool.builder_kind(), BuilderKind::TryBuilder);
     ///
-    /// let mut pool = SyncPool::with_packer(|mut src: Box<BigStruct>| {
-    ///     src.a = 1;
+    /// let val = pool.try_get_built::<String>().unwrap();
+    /// assert_eq!(val.len(), 16);
+    ///
+    /// let failed = SyncPool::<Vec<u8>>::try_with_builder(|| Err("unavailable"));
+    /// assert_eq!(failed.err(), Some("unavailable"));
+    /// ```
+    pub fn try_with_builder<E: Send + 'static>(builder: fn() -> Result<T, E>) -> Result<Self, E> {
+        let count = POOL_SIZE * SLOT_CAP;
+        let mut elems = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            elems.push(Box::new(builder()?));
+        }
+
+        let mut pool = Self::make_pool(0, ElemBuilder::TryBuilder(TryBuilder::new(builder)));
+        Self::add_slots(&mut pool.slots, &pool.builder, SLOT_CAP, POOL_SIZE, false);
+        pool.slots_mask = mask_of(pool.slots.len());
+        pool.capacity_hint = count;
+
+        let mut src = elems.into_iter();
+        for slot in pool.slots.iter_mut() {
+            slot.fill_from(&mut src);
+        }
+
+        Ok(pool)
+    }
+
+    /// Create a pool with default size of 64 pre-allocated elements in it, which will use the `packer`
+    /// handler to initialize the element that's being provided by the pool.
+    ///
+    /// Note that the handler shall take a boxed instance of the element that only contains
+    /// placeholder fields, and it is the caller/handler's job to initialize the fields and pack it
+    /// with valid and meaningful values. If the struct is valid with all-zero values, the handler
+    /// can just return the input element.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::*;
+    /// use std::vec;
+    ///
+    /// struct BigStruct {
+    ///     a: u32,
+    ///     b: u32,
+    ///     c: Vec<u8>,
+    /// }
+    ///
+    /// let mut pool = SyncPool::with_packer(|mut src: Box<BigStruct>| {
+    ///     src.a = 1;
     ///     src.b = 42;
     ///     src.c = vec::from_elem(0u8, 0x1_000_000);
     ///     src
@@ -204,33 +917,411 @@ impl<T> SyncPool<T> {
             pool_size = 1
         }
 
-        Self::make_pool(pool_size, ElemBuilder::Packer(packer))
+        let mut pool = Self::make_pool(pool_size, ElemBuilder::Packer(packer));
+        pool.capacity_hint = size;
+        pool
     }
 
     /// Try to obtain a pre-allocated element from the pool. This method will always succeed even if
     /// the pool is empty or not available for anyone to access, and in this case, a new boxed-element
     /// will be created.
+    ///
+    /// In the strict capacity mode (see `PoolManager::with_strict_capacity`), a new element will only
+    /// be created if fewer than `capacity` elements are outstanding, otherwise we will wait for an
+    /// element to be returned to the pool.
+    ///
+    /// # Panics
+    ///
+    /// The call will panic if the pool has been poisoned, see `poison`.
+    ///
+    /// In the strict capacity mode, the call will panic if no element is returned to the pool within
+    /// 5 seconds, use `try_get` or `get_timeout_or` if the caller shall not block for that long.
     pub fn get(&mut self) -> Box<T> {
-        // update user count
-        let guard = VisitorGuard::register(&self.visitor_counter, true);
-        if guard.is_none() {
-            return make_elem(&self.builder);
+        self.get_tracked().0
+    }
+
+    /// Same as `get`, but also tell if the element has been obtained from the pool (`true`) or newly
+    /// created by the builder (`false`), such that the allocation can be attributed to the exact
+    /// call-site, which the shared `miss_count` can't do when multiple threads are visiting the pool.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+    /// let vals: Vec<(Box<usize>, bool)> = (0..9).map(|_| pool.get_tracked()).collect();
+    ///
+    /// assert!(vals[..8].iter().all(|(_, pooled)| *pooled));
+    /// assert!(!vals[8].1);
+    /// ```
+    pub fn get_tracked(&mut self) -> (Box<T>, bool) {
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
+
+        let result = self.obtain();
+
+        #[cfg(feature = "metrics")]
+        self.timings.get.record(start);
+
+        result
+    }
+
+    fn obtain(&mut self) -> (Box<T>, bool) {
+        self.assert_not_poisoned();
+
+        if let Some(val) = self.get_idle(true) {
+            return (val, true);
+        }
+
+        match self.acquire_permit() {
+            Some(val) => (val, true),
+            None => (make_elem(&self.builder), false),
+        }
+    }
+
+    /// Try to obtain a pre-allocated element from the pool just like `get`, but if the pool is unable
+    /// to offer one, the element will be created by `make` instead of the pool's builder. This is
+    /// useful when the fallback element for a specific call-site shall differ from the pooled ones.
+    pub fn get_or_new_with<F: FnOnce() -> Box<T>>(&mut self, make: F) -> Box<T> {
+        self.assert_not_poisoned();
+
+        if let Some(val) = self.get_idle(true) {
+            return val;
+        }
+
+        self.acquire_permit().unwrap_or_else(make)
+    }
+
+    /// Try to obtain a pre-allocated element from the pool just like `get`, but if the pool is unable
+    /// to offer one, the error of the fallible builder (see `try_with_builder`) will be returned
+    /// instead of panicking. The builders that can't fail will always succeed.
+    ///
+    /// # Panics
+    ///
+    /// The call will panic if the pool has been poisoned, see `poison`, or if `E` is not the error
+    /// type of the fallible builder when it fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// static BUILT: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// let mut pool = SyncPool::try_with_builder(|| {
+    ///     match BUILT.fetch_add(1, Ordering::SeqCst) {
+    ///         n if n < 64 => Ok(n),
+    ///         _ => Err("out of resources"),
+    ///     }
+    /// })
+    /// .unwrap();
+    ///
+    /// let vals: Vec<Box<usize>> = (0..64).map(|_| pool.get()).collect();
+    /// assert_eq!(pool.try_get_built::<&str>().err(), Some("out of resources"));
+    /// ```
+    pub fn try_get_built<E: 'static>(&mut self) -> Result<Box<T>, E> {
+        self.assert_not_poisoned();
+
+        if let Some(val) = self.get_idle(true) {
+            return Ok(val);
+        }
+
+        match self.acquire_permit() {
+            Some(val) => Ok(val),
+            None => try_make_elem(&self.builder),
+        }
+    }
+
+    /// Try to obtain a pre-allocated element from the pool, but unlike `get`, `None` will be returned
+    /// right away if the pool is unable to offer one, instead of creating a new element, such that the
+    /// caller can shed the load, back off or expand the pool on starvation. The call never allocates,
+    /// and never waits for the write barrier to be lowered. A miss will be recorded in that case,
+    /// whether the pool is empty or being reconfigured, since the miss counter is the only trace left
+    /// of the failure; use `try_checkout` to tell the two apart. `None` will be returned as well if
+    /// the pool has been poisoned, which is not counted as a miss.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+    /// pool.with_strict_capacity(true);
+    ///
+    /// let vals: Vec<Box<usize>> = (0..8).map(|_| pool.get()).collect();
+    /// assert_eq!(pool.outstanding(), 8);
+    /// assert!(pool.try_get().is_none());
+    ///
+    /// for val in vals {
+    ///     pool.put(val);
+    /// }
+    ///
+    /// assert_eq!(pool.available_permits(), 8);
+    /// assert!(pool.try_get().is_some());
+    /// ```
+    pub fn try_get(&mut self) -> Option<Box<T>> {
+        match self.checkout_idle(true) {
+            Ok(val) => Some(val),
+            Err(CheckoutError::Barrier) => {
+                self.miss_count.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Same as `try_get`, but return the reason that the pool is unable to offer an element, such
+    /// that the caller can tell a pool that is busy being reconfigured apart from an exhausted one,
+    /// e.g. to decide if it shall retry or apply backpressure. The call never allocates, and never
+    /// waits for the write barrier to be lowered. A miss will be recorded if the pool is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+    ///
+    /// let vals: Vec<Box<usize>> = (0..8).map(|_| pool.try_checkout().unwrap()).collect();
+    /// assert_eq!(pool.try_checkout().err(), Some(CheckoutError::Empty));
+    ///
+    /// pool.poison();
+    /// assert_eq!(pool.try_checkout().err(), Some(CheckoutError::Poisoned));
+    /// ```
+    pub fn try_checkout(&mut self) -> Result<Box<T>, CheckoutError> {
+        self.checkout_idle(true)
+    }
+
+    /// Try to obtain a pre-allocated element from the pool, and keep trying with backoff until `dur`
+    /// has elapsed if the pool is starving, in the hope that someone will return an element in the
+    /// meantime. If we still can't obtain one by the deadline, the element will be created by the
+    /// `fallback` instead of the pool's builder, e.g. a cheaper, degraded element under heavy load.
+    /// A miss will be recorded if the `fallback` is taken. In the strict capacity mode, the
+    /// `fallback` is only taken if the capacity allows one more element, otherwise we will keep
+    /// waiting for an element to be returned past the deadline, see `with_strict_capacity`.
+    pub fn get_timeout_or<F: FnOnce() -> Box<T>>(&mut self, dur: Duration, fallback: F) -> Box<T> {
+        self.assert_not_poisoned();
+
+        let deadline = Instant::now() + dur;
+        let mut count: usize = 0;
+
+        loop {
+            if let Some(val) = self.get_idle(false) {
+                return val;
+            }
+
+            if Instant::now() >= deadline {
+                break;
+            }
+
+            backoff(count);
+            count += 1;
+        }
+
+        // in the strict capacity mode, we may still be handed an element returned to the pool
+        if let Some(val) = self.acquire_permit() {
+            return val;
+        }
+
+        self.miss_count.fetch_add(1, Ordering::Relaxed);
+        fallback()
+    }
+
+    /// Obtain an element the same way as `get_timeout_or`, waiting up to `dur` for an element to be
+    /// returned if the pool is starving, but fall back to the pool's builder after the deadline, and
+    /// tell how the element has been obtained, e.g. to keep a histogram of the checkout latency per
+    /// outcome. The call always succeeds. A miss will be recorded if a new element is created.
+    ///
+    /// # Panics
+    ///
+    /// The call will panic if the pool has been poisoned, see `poison`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+    /// let dur = Duration::from_millis(1);
+    ///
+    /// let vals: Vec<(Box<usize>, CheckoutOutcome)> =
+    ///     (0..9).map(|_| pool.checkout_timed(dur)).collect();
+    ///
+    /// assert!(vals[..8].iter().all(|(_, outcome)| *outcome == CheckoutOutcome::Immediate));
+    /// assert_eq!(vals[8].1, CheckoutOutcome::WaitedThenAllocated);
+    /// ```
+    pub fn checkout_timed(&mut self, dur: Duration) -> (Box<T>, CheckoutOutcome) {
+        self.assert_not_poisoned();
+
+        if let Some(val) = self.get_idle(false) {
+            return (val, CheckoutOutcome::Immediate);
+        }
+
+        let deadline = Instant::now() + dur;
+        let mut count: usize = 0;
+
+        while Instant::now() < deadline {
+            backoff(count);
+            count += 1;
+
+            if let Some(val) = self.get_idle(false) {
+                return (val, CheckoutOutcome::WaitedThenPooled);
+            }
+        }
+
+        // in the strict capacity mode, we may still be handed an element returned to the pool
+        match self.acquire_permit() {
+            Some(val) => (val, CheckoutOutcome::WaitedThenPooled),
+            None => {
+                self.miss_count.fetch_add(1, Ordering::Relaxed);
+                let val = make_elem(&self.builder);
+                (val, CheckoutOutcome::WaitedThenAllocated)
+            }
+        }
+    }
+
+    /// Wait for an idle element from the pool, sweeping the buckets with backoff until someone
+    /// returns an element, or until `cancel` is raised, e.g. by a supervisor that signals all the
+    /// workers to stop acquiring during the shutdown. The flag is checked before each sweep, and
+    /// `None` will be returned promptly once it's raised, or if the pool has been poisoned. Unlike
+    /// `get`, the call never creates a new element.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+    /// let cancel = AtomicBool::new(false);
+    ///
+    /// let val = pool.get_cancellable(&cancel).unwrap();
+    /// assert_eq!(pool.len(), 7);
+    ///
+    /// cancel.store(true, Ordering::SeqCst);
+    /// assert!(pool.get_cancellable(&cancel).is_none());
+    /// ```
+    pub fn get_cancellable(&mut self, cancel: &AtomicBool) -> Option<Box<T>> {
+        let mut count: usize = 0;
+
+        loop {
+            if cancel.load(Ordering::Acquire) || self.is_poisoned() {
+                return None;
+            }
+
+            if let Some(val) = self.get_idle(false) {
+                return Some(val);
+            }
+
+            backoff(count);
+            count += 1;
+        }
+    }
+
+    /// Obtain the permit to create a new element when the pool has no idle element to offer, and the
+    /// new element will be counted as outstanding. In the strict capacity mode, we will wait until an
+    /// element is returned to the pool, which is handed over instead; or until the total number of
+    /// the outstanding and the idle elements drops below the capacity, e.g. a returned element has
+    /// been rejected.
+    fn acquire_permit(&mut self) -> Option<Box<T>> {
+        if !self.strict_capacity() {
+            self.outstanding.fetch_add(1, Ordering::AcqRel);
+            return None;
+        }
+
+        let deadline = Instant::now() + STRICT_WAIT;
+        let mut count: usize = 0;
+
+        loop {
+            if let Some(val) = self.get_idle(false) {
+                return Some(val);
+            }
+
+            // the idle elements shall be counted before the outstanding ones, since `put` settles
+            // an element after placing it into the pool. The elements parked in the thread caches
+            // are alive as well, even if they can't be handed to this thread.
+            let idle = self.len() + self.overflow_len() + self.cached.load(Ordering::Acquire);
+            let cap = self.capacity().saturating_sub(idle);
+            let permit =
+                self.outstanding
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |curr| {
+                        if curr < cap {
+                            Some(curr + 1)
+                        } else {
+                            None
+                        }
+                    });
+
+            if permit.is_ok() {
+                return None;
+            }
+
+            assert!(
+                Instant::now() < deadline,
+                "no element has been returned to the pool with strict capacity in time"
+            );
+
+            backoff(count);
+            count += 1;
+        }
+    }
+
+    /// Check out an idle element from the slots or the overflow queue, and `None` if there's none.
+    /// If `record_miss` is set, a miss will be recorded unless the pool is not accessible at the
+    /// moment.
+    fn get_idle(&mut self, record_miss: bool) -> Option<Box<T>> {
+        self.checkout_idle(record_miss).ok()
+    }
+
+    fn checkout_idle(&mut self, record_miss: bool) -> Result<Box<T>, CheckoutError> {
+        self.checkout_idle_from(record_miss).map(|(val, _)| val)
+    }
+
+    /// Same as `checkout_idle`, but also tell where the element has been taken from.
+    fn checkout_idle_from(
+        &mut self,
+        record_miss: bool,
+    ) -> Result<(Box<T>, IdleSource), CheckoutError> {
+        if self.is_poisoned() {
+            return Err(CheckoutError::Poisoned);
+        }
+
+        if !self.returns.is_empty() {
+            self.reclaim_returns();
+        }
+
+        // update user count, or bail out if the pool is not accessible
+        let guard = match VisitorGuard::register(&self.visitor_counter, true) {
+            Some(guard) => guard,
+            None => return Err(CheckoutError::Barrier),
+        };
+
+        // the thread's own cache is the cheapest place to look at
+        if let Some(val) = self.thread_cache.as_ref().and_then(ThreadCache::pop) {
+            self.outstanding.fetch_add(1, Ordering::AcqRel);
+            return Ok((val, IdleSource::Cache));
         }
 
         // start from where we're left
         let cap = self.slots.len();
+        let lifo = self.reuse_policy() == ReusePolicy::Lifo;
         let mut trials = cap;
-        let mut pos: usize = self.curr.0.load(Ordering::Acquire) % cap;
+        let mask = self.slots_mask;
+        let cursor = Cursor::new(&self.curr.0, self.affinity.as_ref(), self.numa.as_ref());
+        let mut pos: usize = wrap_pos(cursor.start(cap), cap, mask);
 
         loop {
             // check this slot
             let slot = &mut self.slots[pos];
 
             // try the access or move on
-            if let Ok(i) = slot.access(true) {
+            if let Ok(i) = slot.access(true, lifo, self.access_trials) {
                 // try to checkout one slot
                 let checkout = slot.checkout(i);
-                slot.leave(i as u16);
+                slot.leave(i);
 
                 /*            if slot.access(true) {
                 // try to checkout one slot
@@ -239,10 +1330,11 @@ impl<T> SyncPool<T> {
 
                 if let Ok(val) = checkout {
                     // now we're locked, get the val and update internal states
-                    self.curr.0.store(pos, Ordering::Release);
+                    cursor.settle(pos);
+                    self.outstanding.fetch_add(1, Ordering::AcqRel);
 
                     // done
-                    return val;
+                    return Ok((val, IdleSource::Slot));
                 }
 
                 // failed to checkout, break and let the remainder logic to handle the rest
@@ -252,8 +1344,8 @@ impl<T> SyncPool<T> {
             // hold off a bit to reduce contentions
             cpu_relax(SPIN_PERIOD);
 
-            // update to the next position now.
-            pos = self.curr.0.fetch_add(1, Ordering::AcqRel) % cap;
+            // update to the next position now
+            pos = wrap_pos(cursor.advance(pos), cap, mask);
             trials -= 1;
 
             // we've finished 1 loop but not finding a value to extract, quit
@@ -262,52 +1354,396 @@ impl<T> SyncPool<T> {
             }
         }
 
+        // try the overflow queue before we give up
+        if let Some(val) = self.overflow.as_ref().and_then(|queue| queue.pop()) {
+            self.outstanding.fetch_add(1, Ordering::AcqRel);
+            return Ok((val, IdleSource::Overflow));
+        }
+
         // make sure our guard has been returned if we want the correct visitor count
         drop(guard);
-        self.miss_count.fetch_add(1, Ordering::Relaxed);
 
-        // create a new object
-        make_elem(&self.builder)
+        if record_miss {
+            self.miss_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        Err(CheckoutError::Empty)
+    }
+
+    /// Mark the pool as poisoned, e.g. when a panic occurred while an element is checked out and the
+    /// invariant that the elements shall hold can't be trusted anymore. Once poisoned, `get` and its
+    /// variants will panic, and `try_get` will return `None`, such that the subsystem using the pool
+    /// fails fast instead of working on corrupted elements, similar to a poisoned `Mutex`. Elements
+    /// can still be returned to a poisoned pool.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    /// use std::panic::{self, AssertUnwindSafe};
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+    ///
+    /// let res = panic::catch_unwind(AssertUnwindSafe(|| {
+    ///     let _val = pool.get();
+    ///     panic!("failed while holding the element");
+    /// }));
+    ///
+    /// if res.is_err() {
+    ///     pool.poison();
+    /// }
+    ///
+    /// assert!(pool.is_poisoned());
+    /// assert!(pool.try_get().is_none());
+    ///
+    /// pool.clear_poison();
+    /// assert!(pool.try_get().is_some());
+    /// ```
+    pub fn poison(&self) {
+        self.poisoned.store(true, Ordering::Release);
+    }
+
+    /// Check if the pool has been marked as poisoned by `poison`.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Acquire)
+    }
+
+    /// Clear the poisoned mark, such that the elements can be obtained from the pool again.
+    pub fn clear_poison(&self) {
+        self.poisoned.store(false, Ordering::Release);
+    }
+
+    #[inline]
+    fn assert_not_poisoned(&self) {
+        assert!(
+            !self.is_poisoned(),
+            "the pool has been poisoned, call `clear_poison` to recover it"
+        );
     }
 
     /// Try to return an element to the `SyncPool`. If succeed, we will return `None` to indicate that
     /// the value has been placed in an empty slot; otherwise, we will return `Option<Box<T>>` such
     /// that the caller can decide if the element shall be just discarded, or try put it back again.
+    ///
+    /// If the element is rejected by the handle set via `PoolManager::reject_handle`, or fails the
+    /// check set via `PoolManager::validate_on_put`, it will be dropped and `None` will be returned
+    /// as well.
     pub fn put(&mut self, val: Box<T>) -> Option<Box<T>> {
-        // update user count
-        let _guard = VisitorGuard::register(&self.visitor_counter, false);
-
-        // start from where we're left
-        let cap = self.slots.len();
-        let mut trials = 2 * cap;
-        let mut pos: usize = self.curr.1.load(Ordering::Acquire) % cap;
-
-        loop {
-            // check this slot
-            let slot = &mut self.slots[pos];
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
 
-            // try the access or move on
-            if let Ok(i) = slot.access(false) {
-                // now we're locked, get the val and update internal states
-                self.curr.1.store(pos, Ordering::Release);
+        // settle the element after it's placed, such that the waiters in the strict capacity mode
+        // won't see a permit while the element is still on its way back.
+        let ret = self.put_idle(val);
+        self.settle(1);
 
-                // put the value back and reset
-                slot.release(i, val, self.reset_handle);
-                slot.leave(i as u16);
+        #[cfg(feature = "metrics")]
+        self.timings.put.record(start);
 
-                return None;
-            }
+        ret
+    }
 
-            /*            if slot.access(false) {
-                // now we're locked, get the val and update internal states
-                self.curr.1.store(pos, Ordering::Release);
+    /// Create a handle for the other threads to send the elements back to the pool without holding
+    /// the pool, e.g. the consumers of a producer/consumer pipeline. The elements sent via the
+    /// handle are placed into the pool the next time the pool is looking for an idle element, or when
+    /// `reclaim_returns` is called; until then, they're still counted as outstanding.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    /// use std::thread;
+    ///
+    /// let mut pool: SyncPool<Vec<u8>> = SyncPool::with_size(8);
+    /// let sink = pool.return_sink();
+    ///
+    /// let val = pool.get();
+    /// thread::spawn(move || {
+    ///     // consume the element, then send it back
+    ///     sink.send(val);
+    /// })
+    /// .join()
+    /// .unwrap();
+    ///
+    /// assert_eq!(pool.reclaim_returns(), 1);
+    /// assert_eq!(pool.len(), 8);
+    /// ```
+    pub fn return_sink(&self) -> ReturnSink<T> {
+        ReturnSink {
+            queue: Arc::clone(&self.returns),
+        }
+    }
 
-                // put the value back into the slot
-                slot.release(val, self.reset_handle.load(Ordering::Acquire));
-                slot.leave();
+    /// Place the elements sent back via the return sinks into the pool, as if they're returned via
+    /// `put`, and return the number of elements taken from the sinks. This is done implicitly when
+    /// the pool is looking for an idle element, e.g. in `get`, but a background reaper may call this
+    /// to keep the idle elements available for the other APIs, e.g. `len` or `drain_filter`.
+    pub fn reclaim_returns(&mut self) -> usize {
+        let mut count = 0;
+        while let Some(val) = self.returns.pop() {
+            self.put(val);
+            count += 1;
+        }
 
-                return true;
-            }*/
+        count
+    }
+
+    /// Check out an element into `slot`, e.g. an `Option<Box<T>>` field of a long-lived struct that
+    /// cycles the element through the pool, and return the element held in the `slot`. If the `slot`
+    /// is already holding an element, it's kept and nothing will be checked out, such that the same
+    /// holder can't check out a second element by accident. Return the element with `put_from`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<Vec<u8>> = SyncPool::with_size(8);
+    /// let mut slot = None;
+    ///
+    /// pool.get_into(&mut slot).push(1);
+    /// pool.get_into(&mut slot).push(2);
+    /// assert_eq!(slot.as_deref(), Some(&vec![1, 2]));
+    /// assert_eq!(pool.outstanding(), 1);
+    ///
+    /// pool.put_from(&mut slot);
+    /// assert!(slot.is_none());
+    /// assert_eq!(pool.outstanding(), 0);
+    /// ```
+    pub fn get_into<'a>(&mut self, slot: &'a mut Option<Box<T>>) -> &'a mut T {
+        slot.get_or_insert_with(|| self.get())
+    }
+
+    /// Take the element out of `slot` and return it to the pool, which leaves the `slot` empty. This
+    /// is a no-op if the `slot` is empty. Same as `put`, the element will be handed back if the pool
+    /// can't take it.
+    pub fn put_from(&mut self, slot: &mut Option<Box<T>>) -> Option<Box<T>> {
+        slot.take().and_then(|val| self.put(val))
+    }
+
+    /// Create a new element with the pool's builder, the same way the pool creates its own elements,
+    /// but without taking it from or placing it into the pool. This is useful to build replacement
+    /// elements to be `put` into the pool, e.g. when the pool is starving and `refill` has failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool = SyncPool::with_builder_and_size(8, || vec![0u8; 16]);
+    /// let vals: Vec<Box<Vec<u8>>> = (0..8).map(|_| pool.get()).collect();
+    ///
+    /// let val = pool.make_one();
+    /// assert_eq!(val.len(), 16);
+    ///
+    /// assert!(pool.put(val).is_none());
+    /// assert_eq!(pool.len(), 1);
+    /// ```
+    pub fn make_one(&self) -> Box<T> {
+        make_elem(&self.builder)
+    }
+
+    /// Reset the element with the pool's reset handle, then re-initialize it with `init` and hand the
+    /// same element back, as if it's returned to the pool and checked out again right away, but
+    /// without visiting the slots at all. This keeps the element hot in the cache for a loop that
+    /// keeps reusing it.
+    ///
+    /// If the element fails the check set via `PoolManager::validate_on_put`, or it's rejected by the
+    /// handle set via `PoolManager::reject_handle`, it will be dropped, and a replacement will be
+    /// obtained via `get` before being initialized by `init`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<Vec<u8>> = SyncPool::with_size(8);
+    /// pool.reset_handle(|val| val.clear());
+    ///
+    /// let mut val = pool.get();
+    /// val.extend_from_slice(b"done");
+    ///
+    /// let val = pool.recycle(val, |val| val.push(42));
+    /// assert_eq!(*val, vec![42]);
+    /// assert_eq!(pool.outstanding(), 1);
+    /// ```
+    pub fn recycle<F: FnOnce(&mut T)>(&mut self, mut val: Box<T>, init: F) -> Box<T> {
+        // validate the element before it's reset, the same way as `put` does
+        let valid = match self.validate_on_put {
+            Some(validate) if !validate(&val) => {
+                self.invalid_count.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+            _ => true,
+        };
+
+        let kept = {
+            // the reset handle can't be swapped while we're using it
+            let _guard = VisitorGuard::register(&self.visitor_counter, false);
+
+            valid
+                && self
+                    .reset_handle
+                    .as_ref()
+                    .is_none_or(|handle| handle.apply(&mut val))
+        };
+
+        if !kept {
+            // the invalid or rejected element is dropped as if it's returned to the pool
+            drop(val);
+            self.settle(1);
+            val = self.get();
+        }
+
+        init(&mut val);
+        val
+    }
+
+    /// Place the element into the pool, which is not counted as a returned outstanding element, e.g.
+    /// the element is newly created to refill the pool.
+    fn put_idle(&mut self, mut val: Box<T>) -> Option<Box<T>> {
+        // drop the element that doesn't fit into the pool before it's reset
+        if let Some(validate) = self.validate_on_put {
+            if !validate(&val) {
+                self.invalid_count.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+        }
+
+        // update user count
+        let _guard = VisitorGuard::register(&self.visitor_counter, false);
+
+        // reset the struct before releasing it to the pool, or drop it if it's rejected; the handle
+        // can't be swapped once we're counted as a visitor
+        if let Some(handle) = self.reset_handle.as_ref() {
+            if !handle.apply(&mut val) {
+                return None;
+            }
+        }
+
+        // stash the element in the thread's own cache if there's still room, unless the element may
+        // be awaited by other threads in the strict capacity mode
+        let cache = self
+            .thread_cache
+            .as_ref()
+            .filter(|_| !self.strict_capacity());
+
+        if let Some(cache) = cache {
+            val = match cache.push(val) {
+                Ok(()) => return None,
+                Err(val) => val,
+            };
+        }
+
+        Self::release(
+            &mut self.slots,
+            self.slots_mask,
+            Cursor::new(&self.curr.1, self.affinity.as_ref(), self.numa.as_ref()),
+            self.overflow.as_ref(),
+            self.access_trials,
+            val,
+        )
+    }
+
+    /// Return a batch of elements to the `SyncPool` under a single visitor registration, and return
+    /// the number of elements that we failed to place back into the pool, which will be dropped.
+    fn put_many(&mut self, vals: Vec<Box<T>>) -> usize {
+        let count = vals.len();
+
+        // update user count
+        let _guard = VisitorGuard::register(&self.visitor_counter, false);
+
+        let validate = self.validate_on_put;
+        let reset = self.reset_handle.as_ref();
+        let overflow = self.overflow.as_ref();
+        let mut failed = 0;
+
+        for mut val in vals {
+            if let Some(validate) = validate {
+                if !validate(&val) {
+                    self.invalid_count.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+            }
+
+            // rejected elements are dropped as designed, which don't count as failures
+            if let Some(handle) = reset {
+                if !handle.apply(&mut val) {
+                    continue;
+                }
+            }
+
+            let ret = Self::release(
+                &mut self.slots,
+                self.slots_mask,
+                Cursor::new(&self.curr.1, self.affinity.as_ref(), self.numa.as_ref()),
+                overflow,
+                self.access_trials,
+                val,
+            );
+
+            if ret.is_some() {
+                failed += 1;
+            }
+        }
+
+        self.settle(count);
+
+        failed
+    }
+
+    /// Count `count` outstanding elements as returned. Elements that are not handed out by the pool
+    /// may be put into the pool as well, so the count shall never go below 0.
+    fn settle(&self, count: usize) {
+        let _ = self
+            .outstanding
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |curr| {
+                Some(curr.saturating_sub(count))
+            });
+    }
+
+    /// Place the element into an empty slot starting from the `curr` cursor, or park it in the
+    /// `overflow` queue. The caller shall have reset the element and registered as a visitor of the
+    /// pool.
+    fn release(
+        slots: &mut [Bucket2<T>],
+        mask: Option<usize>,
+        cursor: Cursor,
+        overflow: Option<&ArrayQueue<Box<T>>>,
+        access_trials: usize,
+        val: Box<T>,
+    ) -> Option<Box<T>> {
+        // start from where we're left
+        let cap = slots.len();
+        let mut trials = 2 * cap;
+        let mut pos: usize = wrap_pos(cursor.start(cap), cap, mask);
+
+        loop {
+            // check this slot
+            let slot = &mut slots[pos];
+
+            // try the access or move on
+            if let Ok(i) = slot.access(false, false, access_trials) {
+                // now we're locked, get the val and update internal states
+                cursor.settle(pos);
+
+                // put the value back
+                slot.release(i, val);
+                slot.leave(i);
+
+                return None;
+            }
+
+            /*            if slot.access(false) {
+                // now we're locked, get the val and update internal states
+                self.curr.1.store(pos, Ordering::Release);
+
+                // put the value back into the slot
+                slot.release(val, self.reset_handle.load(Ordering::Acquire));
+                slot.leave();
+
+                return true;
+            }*/
 
             // hold off a bit to reduce contentions
             if trials < cap {
@@ -316,338 +1752,3454 @@ impl<T> SyncPool<T> {
                 thread::yield_now();
             }
 
-            // update states
-            pos = self.curr.1.fetch_add(1, Ordering::AcqRel) % cap;
-            trials -= 1;
+            // update states
+            pos = wrap_pos(cursor.advance(pos), cap, mask);
+            trials -= 1;
+
+            // we've finished 1 loop but not finding a value to extract, park the value in the
+            // overflow queue if there's any room, otherwise hand it back.
+            if trials == 0 {
+                return match overflow {
+                    Some(queue) => queue.push(val).err(),
+                    None => Some(val),
+                };
+            }
+        }
+    }
+
+    /// Obtain an element from the pool the same way as `get` does, but pin it in the heap before
+    /// handing it over to the caller. This is handy for self-referential structs, or elements that
+    /// will be driven by async state machines which require pinning.
+    ///
+    /// A pinned element can't be moved out of its `Pin` safely, so it can only be returned to the
+    /// pool via the `put_pinned` API if `T: Unpin`; otherwise the caller must guarantee that no one
+    /// is relying on the pinning anymore and call `put_pinned_unchecked`, or simply drop the element.
+    pub fn checkout_pinned(&mut self) -> Pin<Box<T>> {
+        Box::into_pin(self.get())
+    }
+
+    /// Return a pinned element to the `SyncPool`, where the pinning is released before putting the
+    /// element back. Same as `put`, if we fail to find an empty slot for the element, it will be
+    /// handed back to the caller.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that the pinned element is no longer relied upon to stay at its
+    /// memory location, e.g. all self-references in the element have been invalidated, since the
+    /// element will be reset and handed out again by the pool.
+    pub unsafe fn put_pinned_unchecked(&mut self, val: Pin<Box<T>>) -> Option<Pin<Box<T>>> {
+        self.put(Pin::into_inner_unchecked(val)).map(Box::into_pin)
+    }
+
+    /// Obtain a batch of `n` elements from the pool, which can be processed as a slice, and will be
+    /// returned to the pool together when the `BatchGuard` goes out of scope. This suits frame-based
+    /// workloads that grab a number of elements, process them together, and release them together.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+    ///
+    /// {
+    ///     let mut batch = pool.checkout_batch(4);
+    ///     batch.iter_mut().enumerate().for_each(|(i, val)| **val = i);
+    ///     assert_eq!(batch.len(), 4);
+    /// }
+    ///
+    /// assert_eq!(pool.len(), 8);
+    /// ```
+    pub fn checkout_batch(&mut self, n: usize) -> BatchGuard<'_, T> {
+        let vals = (0..n).map(|_| self.get()).collect();
+        BatchGuard { pool: self, vals }
+    }
+
+    /// Obtain an element from the pool just like `get`, but wrapped in a `PoolGuard` which returns
+    /// the element to the pool when it goes out of scope, such that the element won't leak on the
+    /// early returns, e.g. via `?`. Call `PoolGuard::into_inner` to keep the element instead. If the
+    /// write barrier is raised at the time the guard is dropped, the element will be dropped rather
+    /// than waiting for the barrier to be lowered.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<Vec<u8>> = SyncPool::with_size(8);
+    ///
+    /// {
+    ///     let mut val = pool.checkout();
+    ///     val.push(42);
+    /// }
+    ///
+    /// assert_eq!(pool.len(), 8);
+    ///
+    /// let val = pool.checkout().into_inner();
+    /// assert_eq!(pool.len(), 7);
+    /// ```
+    pub fn checkout(&mut self) -> PoolGuard<'_, T> {
+        let val = Some(self.get());
+        PoolGuard { pool: self, val }
+    }
+
+    /// Obtain exactly `n` idle elements from the pool, or none at all: if the pool can't offer `n`
+    /// elements without creating new ones, the elements taken so far will be placed back to where
+    /// they were taken from, i.e. the thread cache, the slots or the overflow queue, without being
+    /// reset or counted as missed, and `None` will be returned. This
+    /// suits the work that needs a guaranteed set of elements up front, where a partial batch would
+    /// only hold the elements while waiting for the rest. `None` will be returned as well if the pool
+    /// has been poisoned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+    ///
+    /// assert!(pool.try_get_exactly(9).is_none());
+    /// assert_eq!(pool.len(), 8);
+    ///
+    /// let vals = pool.try_get_exactly(8).unwrap();
+    /// assert_eq!(vals.len(), 8);
+    /// assert_eq!(pool.outstanding(), 8);
+    /// ```
+    pub fn try_get_exactly(&mut self, n: usize) -> Option<Vec<Box<T>>> {
+        let mut vals = Vec::with_capacity(n);
+        let mut sources = Vec::with_capacity(n);
+
+        while vals.len() < n {
+            match self.checkout_idle_from(false) {
+                Ok((val, source)) => {
+                    vals.push(val);
+                    sources.push(source);
+                }
+                Err(_) => break,
+            }
+        }
+
+        if vals.len() == n {
+            return Some(vals);
+        }
+
+        // undo the partial checkout in the reverse order, and place each element back to where it's
+        // taken from; the elements are still clean and shall bypass the handles
+        let count = vals.len();
+        let _guard = VisitorGuard::register(&self.visitor_counter, false);
+
+        for (val, source) in vals.into_iter().zip(sources).rev() {
+            let val = match source {
+                IdleSource::Cache => match self.thread_cache.as_ref() {
+                    Some(cache) => cache.push(val).err(),
+                    None => Some(val),
+                },
+                IdleSource::Overflow => match self.overflow.as_ref() {
+                    Some(queue) => queue.push(val).err(),
+                    None => Some(val),
+                },
+                IdleSource::Slot => Some(val),
+            };
+
+            let val = match val {
+                Some(val) => val,
+                None => continue,
+            };
+
+            // the slots we've just emptied can't be taken by others, since we hold `&mut self`
+            let left = Self::release(
+                &mut self.slots,
+                self.slots_mask,
+                Cursor::new(&self.curr.1, self.affinity.as_ref(), self.numa.as_ref()),
+                self.overflow.as_ref(),
+                self.access_trials,
+                val,
+            );
+
+            // never drop an idle element, it will be placed into the pool on the next checkout
+            if let Some(val) = left {
+                self.returns.push(val);
+            }
+        }
+
+        self.settle(count);
+        None
+    }
+
+    /// Obtain an element from the pool, run `f` with it, and put it back into the pool before returning
+    /// the result of `f`. The element is returned to the pool (and hence reset by the reset handle)
+    /// even if `f` panics, so this can't leak elements out of the pool for request-scoped usages.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<Vec<u8>> = SyncPool::with_size(8);
+    ///
+    /// let len = pool.with_checked_out(|buf| {
+    ///     buf.extend_from_slice(b"hello");
+    ///     buf.len()
+    /// });
+    ///
+    /// assert_eq!(len, 5);
+    /// assert_eq!(pool.len(), 8);
+    /// ```
+    pub fn with_checked_out<R, F: FnOnce(&mut T) -> R>(&mut self, f: F) -> R {
+        let val = self.get();
+        let mut guard = ReturnGuard {
+            pool: self,
+            val: Some(val),
+        };
+
+        match guard.val.as_deref_mut() {
+            Some(val) => f(val),
+            None => unreachable!("the element is only taken when the guard is dropped"),
+        }
+    }
+
+    /// Obtain an element from the pool just like `get`, but wrapped in a reference-counted `PoolArc`
+    /// that can be cloned and shared among multiple readers. The element will be returned to the pool
+    /// automatically when the last clone is dropped, and it shall be treated as immutable while
+    /// outstanding: only `&T` is accessible through the `PoolArc`.
+    ///
+    /// # Safety
+    ///
+    /// The `PoolArc` holds a raw handle back to the pool, hence the caller must guarantee that the
+    /// pool is neither dropped nor moved until all the clones of the `PoolArc` have been dropped,
+    /// e.g. by placing the pool in a `static`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(8);
+    ///
+    /// let shared = unsafe { pool.get_arc() };
+    /// let reader = shared.clone();
+    /// assert_eq!(reader.len(), 32);
+    ///
+    /// drop(shared);
+    /// drop(reader);
+    /// assert_eq!(pool.len(), 8);
+    /// ```
+    pub unsafe fn get_arc(&mut self) -> PoolArc<T> {
+        let val = self.get();
+
+        PoolArc(Arc::new(PoolArcInner {
+            val: Some(val),
+            pool: self as *mut SyncPool<T>,
+        }))
+    }
+
+    /// Replace the construction strategy of the pool with the `builder` handler, which shall create and
+    /// initialize the element the same way as the one provided to `with_builder`. The change is made
+    /// under the write barrier, and we will return `false` if someone else is holding the barrier.
+    ///
+    /// Only the elements created after the swap (i.e. when the pool is starving, or being refilled or
+    /// expanded) will be made by the new builder; the idle elements in the pool are left untouched.
+    /// As a result, elements made by the old and the new builders will coexist in the pool, and the
+    /// caller shall make sure that either shape is valid, or convert the old elements with a reset
+    /// handle, or evict them from the pool.
+    pub fn swap_builder(&mut self, builder: fn() -> T) -> bool {
+        self.replace_builder(ElemBuilder::Builder(builder))
+    }
+
+    /// Replace the construction strategy of the pool with the `packer` handler, which shall initialize
+    /// the element the same way as the one provided to `with_packer`. Same as `swap_builder`, only the
+    /// elements created after the swap will be made by the new packer.
+    pub fn swap_packer(&mut self, packer: fn(Box<T>) -> Box<T>) -> bool {
+        self.replace_builder(ElemBuilder::Packer(packer))
+    }
+
+    /// Set the handle to be invoked on each idle element when the pool is dropped, e.g. to log or to
+    /// flush a shared resource exactly once per element, which is the same as
+    /// `PoolManager::drop_handle`. Only the elements in the pool at the time are visited: the ones
+    /// checked out are dropped by their owners without the handle being invoked.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// static FLUSHED: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// let mut pool: SyncPool<Vec<u8>> = SyncPool::with_size(8);
+    /// pool.with_drop_handler(|_| {
+    ///     FLUSHED.fetch_add(1, Ordering::SeqCst);
+    /// });
+    ///
+    /// let val = pool.get();
+    /// drop(pool);
+    ///
+    /// assert_eq!(FLUSHED.load(Ordering::SeqCst), 7);
+    /// ```
+    pub fn with_drop_handler(&mut self, handle: fn(&mut T)) -> &mut Self {
+        self.drop_handle(handle)
+    }
+
+    /// Move all the idle elements in the pool into the `sink`, and return the number of elements being
+    /// moved. The pool will be left empty but structurally intact, such that it can be refilled by
+    /// later `put`s. Since the elements are appended to the caller-provided `sink`, the same vector
+    /// can be reused across calls without reallocating once it has grown large enough.
+    ///
+    /// The API will raise the write barrier and block until all visitors have left the pool.
+    pub fn take_all_into(&mut self, sink: &mut Vec<Box<T>>) -> usize {
+        sink.reserve(self.len());
+        self.take_idle(|_| true, |val| sink.push(val))
+    }
+
+    /// Build `count` elements with the pool's builder on `threads` worker threads, then move all of
+    /// them into the pool under a single write barrier. The empty slots are filled first, and the
+    /// pool will grow with new buckets for the remaining elements. Return the number of elements
+    /// being added. This cuts the startup time of a large pool of heavy elements, since building the
+    /// elements is done concurrently and off the critical section.
+    ///
+    /// Same as `PoolManager::try_expand`, the pool only grows if the expansion is enabled, and never
+    /// beyond the cap on the number of buckets, so fewer elements than `count` may be added.
+    ///
+    /// The API will raise the write barrier and block until all visitors have left the pool. If the
+    /// barrier is held by someone else, the elements will be dropped and 0 will be returned. If the
+    /// builder panics, the panic will be propagated to the caller.
+    pub fn warmup_parallel(&mut self, threads: usize, count: usize) -> usize
+    where
+        T: Send,
+    {
+        // don't bother building the elements that have no place to go
+        let mut room = self.capacity().saturating_sub(self.len());
+        if self.expansion_enabled() {
+            room += EXPANSION_CAP.saturating_sub(self.slots.len()) * self.slot_cap;
+        }
+
+        let count = count.min(room);
+        if count == 0 {
+            return 0;
+        }
+
+        let threads = threads.clamp(1, count);
+        let builder = &self.builder;
+
+        let elems: Vec<Box<T>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|i| {
+                    let share = count / threads + usize::from(i < count % threads);
+                    scope.spawn(move || (0..share).map(|_| make_elem(builder)).collect::<Vec<_>>())
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|err| panic::resume_unwind(err))
+                })
+                .collect()
+        });
+
+        let _guard = match BarrierGuard::raise(&self.visitor_counter, None) {
+            Ok(guard) => guard,
+            Err(_) => return 0,
+        };
+
+        let mut src = elems.into_iter();
+        let mut added = self
+            .slots
+            .iter_mut()
+            .fold(0, |sum, slot| sum + slot.fill_from(&mut src));
+
+        while src.len() > 0 && self.expansion_enabled() && self.slots.len() < EXPANSION_CAP {
+            let mut bucket = Bucket2::with_count(None, self.slot_cap, 0);
+            added += bucket.fill_from(&mut src);
+            self.slots.push(bucket);
+        }
+
+        self.slots_mask = mask_of(self.slots.len());
+
+        added
+    }
+
+    /// Same as `PoolManager::refill`, but the elements are built concurrently on the rayon thread pool,
+    /// then moved into the empty slots under a single write barrier, which speeds up the recovery of a
+    /// pool of heavy elements after it has been starved. The pool won't grow, i.e. at most as many
+    /// elements as the empty slots will be built. Return the number of elements being added.
+    ///
+    /// The API will raise the write barrier and block until all visitors have left the pool. If the
+    /// barrier is held by someone else, the elements will be dropped and 0 will be returned.
+    ///
+    /// The API is available with the `rayon` feature enabled, otherwise it falls back to the serial
+    /// `PoolManager::refill`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<Vec<u8>> = SyncPool::with_size(16);
+    /// let vals: Vec<Box<Vec<u8>>> = (0..16).map(|_| pool.get()).collect();
+    ///
+    /// // the checked out elements are lost, e.g. they've been moved elsewhere
+    /// drop(vals);
+    ///
+    /// assert_eq!(pool.par_refill(32), 16);
+    /// assert_eq!(pool.len(), 16);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_refill(&mut self, additional: usize) -> usize
+    where
+        T: Send,
+    {
+        let quota = additional.min(self.capacity().saturating_sub(self.len()));
+        if quota == 0 {
+            return 0;
+        }
+
+        let builder = &self.builder;
+        let elems: Vec<Box<T>> = (0..quota)
+            .into_par_iter()
+            .map(|_| make_elem(builder))
+            .collect();
+
+        let _guard = match BarrierGuard::raise(&self.visitor_counter, None) {
+            Ok(guard) => guard,
+            Err(_) => return 0,
+        };
+
+        // the elements that don't fit, if any, are dropped along with the iterator
+        let mut src = elems.into_iter();
+        self.slots
+            .iter_mut()
+            .fold(0, |sum, slot| sum + slot.fill_from(&mut src))
+    }
+
+    /// Same as `PoolManager::refill`, which the API falls back to without the `rayon` feature.
+    #[cfg(not(feature = "rayon"))]
+    pub fn par_refill(&mut self, additional: usize) -> usize
+    where
+        T: Send,
+    {
+        self.refill(additional)
+    }
+
+    /// Retain only the idle elements for which `f` returns `true`, and drop the rest, e.g. to evict
+    /// the elements holding expired cached data. The vacated slots stay available for later `put`s.
+    /// Return the number of elements being evicted. Elements currently checked out are not visited.
+    ///
+    /// The API will raise the write barrier and block until all visitors have left the pool.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) -> usize {
+        self.take_idle(|val| !f(val), drop)
+    }
+
+    /// Take the idle elements for which `f` returns `true` out of the pool, and hand them over to the
+    /// caller, e.g. to finalize the elements holding expired data outside the pool. The rest of the
+    /// elements are left in place, and the vacated slots stay available for later `put`s. Elements
+    /// currently checked out are not visited.
+    ///
+    /// The API will raise the write barrier and block until all visitors have left the pool.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+    /// let vals: Vec<Box<usize>> = (0..8).map(|_| pool.get()).collect();
+    ///
+    /// for (i, mut val) in vals.into_iter().enumerate() {
+    ///     *val = i;
+    ///     pool.put(val);
+    /// }
+    ///
+    /// let mut odd = pool.drain_filter(|val| val % 2 == 1);
+    /// odd.sort();
+    ///
+    /// assert_eq!(odd, [1, 3, 5, 7].iter().map(|&i| Box::new(i)).collect::<Vec<_>>());
+    /// assert_eq!(pool.len(), 4);
+    /// ```
+    pub fn drain_filter<F: FnMut(&T) -> bool>(&mut self, f: F) -> Vec<Box<T>> {
+        let mut drained = Vec::new();
+        self.take_idle(f, |val| drained.push(val));
+        drained
+    }
+
+    /// Consume the pool and hand over all of its idle elements, including the ones parked in the
+    /// overflow queue and in the current thread's cache, e.g. as the last step of the shutdown. The
+    /// elements checked out at the time, or cached by other threads, are not included. The drop
+    /// handle (see `PoolManager::drop_handle`) won't be invoked on the returned elements.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+    /// let val = pool.get();
+    ///
+    /// let elems = pool.into_elems();
+    /// assert_eq!(elems.len(), 7);
+    /// ```
+    pub fn into_elems(mut self) -> Vec<Box<T>> {
+        let mut elems = Vec::with_capacity(self.len() + self.overflow_len());
+
+        if let Some(cache) = self.thread_cache.as_ref() {
+            while let Some(val) = cache.pop() {
+                elems.push(val);
+            }
+        }
+
+        // the pool is owned, so no one else can be visiting it
+        for slot in self.slots.iter_mut() {
+            slot.take_if(&mut |_| true, &mut |val| elems.push(val));
+        }
+
+        if let Some(queue) = self.overflow.as_ref() {
+            while let Some(val) = queue.pop() {
+                elems.push(val);
+            }
+        }
+
+        while let Some(val) = self.returns.pop() {
+            elems.push(val);
+        }
+
+        // the pool is left empty, so dropping it frees nothing else
+        elems
+    }
+
+    /// Assert the internal consistency of the pool, and panic on any violation: every slot is either
+    /// empty with its element bit cleared, or holding an element with its element bit set; and no
+    /// lock bits are left set. The check is only meaningful when no one is visiting the pool, e.g.
+    /// after all the worker threads of a test have been joined. This helps to turn a silent
+    /// corruption of the pool into a test failure.
+    ///
+    /// The API is only available in tests, or with the `invariants` feature enabled.
+    #[cfg(any(test, feature = "invariants"))]
+    pub fn assert_invariants(&self) {
+        assert_eq!(
+            self.visitor_counter.0.load(Ordering::Acquire),
+            1,
+            "visitors are left in the pool"
+        );
+        assert!(
+            !self.visitor_counter.1.load(Ordering::Acquire),
+            "the write barrier is left raised"
+        );
+
+        for slot in self.slots.iter() {
+            slot.assert_invariants();
+        }
+    }
+
+    /// Inspect the raw states of the bucket at `idx`, i.e. its bitmap, its length hint and the number
+    /// of elements committed in it, without touching any other bucket. This is a read-only snapshot
+    /// for diagnosing a suspected corruption, e.g. when elements seem to be lost, which may be stale
+    /// if the pool is being visited concurrently.
+    ///
+    /// The API is only available in tests, or with the `invariants` feature enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is not less than `bucket_count`.
+    #[cfg(any(test, feature = "invariants"))]
+    pub fn bucket_debug(&self, idx: usize) -> BucketDebug {
+        assert!(
+            idx < self.slots.len(),
+            "bucket index {} out of range for {} buckets",
+            idx,
+            self.slots.len()
+        );
+
+        self.slots[idx].debug_state()
+    }
+
+    /// Build a histogram of how full the buckets are, where the `k`-th entry counts the buckets that
+    /// are currently holding `k` idle elements. The histogram is computed in a single pass over the
+    /// bucket bitmaps without blocking the visitors, so it's a snapshot that may be stale if the pool
+    /// is being visited concurrently. A lopsided histogram, i.e. elements piling up in a few buckets
+    /// while the rest are empty, means more contention on those few buckets. The histogram has
+    /// `slot_cap() + 1` entries, one for each possible occupancy of a bucket.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::*;
+    ///
+    /// let mut pool: SyncPool<u64> = SyncPool::with_size(16);
+    /// let val = pool.get();
+    ///
+    /// let histogram = pool.slot_occupancy_histogram();
+    /// assert_eq!(histogram.iter().sum::<usize>(), pool.capacity() / (histogram.len() - 1));
+    ///
+    /// pool.put(val);
+    /// ```
+    pub fn slot_occupancy_histogram(&self) -> Vec<usize> {
+        let mut histogram = vec![0; self.slot_cap + 1];
+
+        for slot in self.slots.iter() {
+            histogram[slot.occupancy()] += 1;
+        }
+
+        histogram
+    }
+
+    /// Take a snapshot of the number of idle elements held by each bucket, in the order of the
+    /// buckets. Unlike `slot_occupancy_histogram`, the entries keep the identity of the buckets, e.g.
+    /// to spot the buckets that are never visited. The snapshot is taken the same way as the
+    /// histogram, so it may be stale if the pool is being visited concurrently.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::*;
+    ///
+    /// let mut pool: SyncPool<u64> = SyncPool::with_size(16);
+    /// let val = pool.get();
+    ///
+    /// let lens = pool.len_per_bucket();
+    /// assert_eq!(lens.iter().sum::<usize>(), pool.len());
+    ///
+    /// pool.put(val);
+    /// ```
+    pub fn len_per_bucket(&self) -> Vec<usize> {
+        self.slots.iter().map(Bucket2::occupancy).collect()
+    }
+
+    /// Check that the pool hasn't lost or duplicated any element, and report the findings instead of
+    /// panicking on them, e.g. for a periodic self-check of a long-running service whose report can
+    /// be logged or alerted on. The idle elements are counted from the bucket bitmaps, and each of
+    /// them shall live at a distinct address; no lock bits shall be left set; and the length hint of
+    /// each bucket shall match its elements. This is the runtime counterpart of `assert_invariants`.
+    ///
+    /// The API will raise the write barrier and wait for all visitors to leave, for no longer than
+    /// the barrier timeout (see `PoolManager::with_barrier_timeout`). If the barrier can't be raised,
+    /// the check is skipped, and the report carries the reason as its only anomaly.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool: SyncPool<u64> = SyncPool::with_size(16);
+    /// let val = pool.get();
+    ///
+    /// let report = pool.validate();
+    /// assert!(report.is_ok());
+    /// assert_eq!(report.idle, pool.len());
+    ///
+    /// pool.put(val);
+    /// ```
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport {
+            idle: 0,
+            buckets: self.slots.len(),
+            anomalies: Vec::new(),
+        };
+
+        let _guard = match BarrierGuard::raise(&self.visitor_counter, Some(self.barrier_timeout)) {
+            Ok(guard) => guard,
+            Err(err) => {
+                report.anomalies.push(format!(
+                    "check skipped, unable to raise the write barrier: {:?}",
+                    err
+                ));
+
+                return report;
+            }
+        };
+
+        let mut seen = HashSet::with_capacity(self.capacity());
+        for (idx, slot) in self.slots.iter().enumerate() {
+            report.idle += slot.validate(idx, &mut seen, &mut report.anomalies);
+        }
+
+        report
+    }
+
+    /// Move the idle elements around such that they're packed into the fewest buckets, filling the
+    /// buckets from the first one, and leaving the trailing buckets fully empty. Return the number of
+    /// buckets that have become empty. This undoes the fragmentation after heavy churn, where a few
+    /// idle elements are spread thinly across many buckets, which hurts the locality of `get`.
+    ///
+    /// The API will raise the write barrier and block until all visitors have left the pool. If the
+    /// barrier is held by someone else, the pool is left untouched and 0 will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::*;
+    ///
+    /// let mut pool: SyncPool<usize> = SyncPool::with_size(32);
+    ///
+    /// // evict all but the last element of each bucket
+    /// let mut count = 0;
+    /// pool.retain(|_| {
+    ///     count += 1;
+    ///     count % 8 == 0
+    /// });
+    ///
+    /// assert_eq!(pool.len(), 4);
+    /// assert_eq!(pool.slot_occupancy_histogram()[1], 4);
+    ///
+    /// assert_eq!(pool.compact(), 3);
+    /// assert_eq!(pool.slot_occupancy_histogram()[4], 1);
+    /// assert_eq!(pool.len(), 4);
+    /// ```
+    pub fn compact(&mut self) -> usize {
+        let _guard = match BarrierGuard::raise(&self.visitor_counter, None) {
+            Ok(guard) => guard,
+            Err(_) => return 0,
+        };
+
+        let empty = self.empty_buckets();
+
+        let mut elems = Vec::with_capacity(self.len());
+        for slot in self.slots.iter_mut() {
+            slot.take_if(&mut |_| true, &mut |val| elems.push(val));
+        }
+
+        let mut src = elems.into_iter();
+        for slot in self.slots.iter_mut() {
+            if slot.fill_from(&mut src) == 0 {
+                break;
+            }
+        }
+
+        // start looking for the elements from the packed buckets
+        self.curr.0.store(0, Ordering::Release);
+
+        self.empty_buckets().saturating_sub(empty)
+    }
+
+    /// Walk through all the idle elements in the pool and invoke the `touch` handler on each of them,
+    /// returning the number of elements being touched. The API is a latency-tail mitigation: the
+    /// elements pre-allocated at construction time may be lazily paged by the OS, such that the first
+    /// `get` of each element still incurs page faults in the hot code path. The handler shall write
+    /// (or read) at least one byte per memory page of the element to force the OS to back the pages.
+    ///
+    /// The API will raise the write barrier and block until all visitors have left the pool, so it's
+    /// best to call it once right after the pool is created.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::*;
+    /// use std::vec;
+    ///
+    /// const PAGE: usize = 4096;
+    ///
+    /// let mut pool = SyncPool::with_builder(|| vec::from_elem(0u8, 4 * PAGE));
+    ///
+    /// let touched = pool.pre_touch(|buf: &mut Vec<u8>| {
+    ///     for i in (0..buf.len()).step_by(PAGE) {
+    ///         buf[i] = 0;
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(touched, pool.len());
+    /// ```
+    pub fn pre_touch<F: FnMut(&mut T)>(&mut self, touch: F) -> usize {
+        self.iter_mut_idle(touch)
+    }
+
+    /// Apply `f` to every idle element in the pool in place, e.g. to update a field of all the pooled
+    /// elements on a configuration reload, and return the number of elements being visited. Elements
+    /// currently checked out are not visited.
+    ///
+    /// The API will raise the write barrier and block until all visitors have left the pool, and the
+    /// pool will be accessible again once it returns, even if `f` panics.
+    pub fn iter_mut_idle<F: FnMut(&mut T)>(&mut self, mut f: F) -> usize {
+        let _guard = match BarrierGuard::raise(&self.visitor_counter, None) {
+            Ok(guard) => guard,
+            Err(_) => return 0,
+        };
+
+        self.slots
+            .iter_mut()
+            .fold(0, |sum, slot| sum + slot.for_each_mut(&mut f))
+    }
+
+    /// Take the idle elements matching the predicate out of the pool under the write barrier, and hand
+    /// them over to the sink. The pool is left structurally intact, such that the vacated slots can be
+    /// filled by later `put`s.
+    fn take_idle<P, S>(&mut self, mut pred: P, mut sink: S) -> usize
+    where
+        P: FnMut(&T) -> bool,
+        S: FnMut(Box<T>),
+    {
+        let _guard = match BarrierGuard::raise(&self.visitor_counter, None) {
+            Ok(guard) => guard,
+            Err(_) => return 0,
+        };
+
+        self.slots
+            .iter_mut()
+            .fold(0, |sum, slot| sum + slot.take_if(&mut pred, &mut sink))
+    }
+
+    #[inline]
+    fn empty_buckets(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.occupancy() == 0)
+            .count()
+    }
+
+    fn replace_builder(&mut self, builder: ElemBuilder<T>) -> bool {
+        let _guard = match BarrierGuard::raise(&self.visitor_counter, None) {
+            Ok(guard) => guard,
+            Err(_) => return false,
+        };
+
+        self.builder = builder;
+        true
+    }
+
+    fn make_pool(size: usize, builder: ElemBuilder<T>) -> Self {
+        Self::make_pool_with_width(size, SLOT_CAP, builder)
+    }
+
+    fn make_pool_with_width(size: usize, width: usize, builder: ElemBuilder<T>) -> Self {
+        let mut pool = SyncPool {
+            slots: Vec::with_capacity(size),
+            curr: (AtomicUsize::new(0), AtomicUsize::new(0)),
+            visitor_counter: (AtomicUsize::new(1), AtomicBool::new(false)),
+            miss_count: AtomicUsize::new(0),
+            outstanding: AtomicUsize::new(0),
+            invalid_count: AtomicUsize::new(0),
+            poisoned: AtomicBool::new(false),
+            configure: AtomicUsize::new(0),
+            reset_handle: None,
+            drop_handle: None,
+            validate_on_put: None,
+            overflow: None,
+            returns: Arc::new(SegQueue::new()),
+            access_trials: TRIALS_COUNT,
+            barrier_timeout: BARRIER_TIMEOUT,
+            capacity_hint: size * width,
+            thread_cache: None,
+            cached: Arc::new(AtomicUsize::new(0)),
+            affinity: None,
+            numa: None,
+            #[cfg(feature = "metrics")]
+            timings: Timings::default(),
+            slots_mask: None,
+            slot_cap: width,
+            builder,
+        };
+
+        Self::add_slots(&mut pool.slots, &pool.builder, width, size, true);
+        pool.slots_mask = mask_of(pool.slots.len());
+        pool
+    }
+
+    fn make_pool_exact(count: usize, builder: ElemBuilder<T>) -> Self {
+        // always keep at least 1 bucket around, such that we won't divide by 0 when looking for slots
+        let size = count.div_ceil(SLOT_CAP).max(1);
+        let mut pool = Self::make_pool(0, builder);
+        pool.capacity_hint = count;
+
+        let mut remainder = count;
+        for _ in 0..size {
+            let fill = remainder.min(SLOT_CAP);
+            pool.slots
+                .push(Bucket2::with_count(Some(&pool.builder), SLOT_CAP, fill));
+            remainder -= fill;
+        }
+
+        pool.slots_mask = mask_of(pool.slots.len());
+        pool
+    }
+
+    #[inline]
+    fn add_slots(
+        slots: &mut Vec<Bucket2<T>>,
+        builder: &ElemBuilder<T>,
+        width: usize,
+        count: usize,
+        fill: bool,
+    ) {
+        let filler = if fill { Some(builder) } else { None };
+
+        for _ in 0..count {
+            // self.slots.push(Bucket::new(fill));
+            slots.push(Bucket2::new(filler, width));
+        }
+    }
+
+    /// Replace the reset handle under the write barrier once all visitors have left, and give up if
+    /// the barrier is held by someone else, or the visitors don't leave within the barrier timeout.
+    fn update_reset(&mut self, handle: ResetHandle<T>) -> bool {
+        let _guard = match BarrierGuard::raise(&self.visitor_counter, Some(self.barrier_timeout)) {
+            Ok(guard) => guard,
+            Err(_) => return false,
+        };
+
+        self.reset_handle.replace(handle);
+        true
+    }
+
+    fn update_config(&mut self, mask: usize, target: bool) {
+        let mut config = self.configure.load(Ordering::SeqCst);
+
+        while let Err(old) = self.configure.compare_exchange(
+            config,
+            config ^ mask,
+            Ordering::SeqCst,
+            Ordering::Relaxed,
+        ) {
+            if !((old & mask > 0) ^ target) {
+                // the configure already matches, we're done
+                return;
+            }
+
+            config = old;
+        }
+    }
+}
+
+impl<T: 'static> SyncPool<T> {
+    /// Give each thread a small cache of up to `cap` elements of its own, such that most `get` and
+    /// `put` calls from the same thread can be served without touching the shared slots, similar to
+    /// the thread caches of memory allocators. `get` will pop from the thread's cache first, and `put`
+    /// will push to it first, and only fall back to the shared slots when the cache is empty or full.
+    /// Setting `cap` to 0 disables the thread caches.
+    ///
+    /// Note that the cached elements are not counted by `len`, and are invisible to the APIs that walk
+    /// through the idle elements, e.g. `retain` or `take_all_into`. The caches are skipped by `put` in
+    /// the strict capacity mode, see `PoolManager::with_strict_capacity`. Since a thread can only clear its
+    /// own cache, the elements cached by the other threads will outlive the pool, and won't be dropped
+    /// until those threads exit.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::*;
+    ///
+    /// let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(8);
+    /// pool.with_thread_cache(2);
+    ///
+    /// let val = pool.get();
+    /// assert_eq!(pool.len(), 7);
+    ///
+    /// // the element is kept in the thread's cache instead of the pool
+    /// pool.put(val);
+    /// assert_eq!(pool.len(), 7);
+    ///
+    /// // and handed out again from the cache
+    /// let val = pool.get();
+    /// assert_eq!(pool.len(), 7);
+    /// ```
+    pub fn with_thread_cache(&mut self, cap: usize) -> &mut Self {
+        let cache = if cap > 0 {
+            Some(ThreadCache::new(cap, Arc::clone(&self.cached)))
+        } else {
+            None
+        };
+
+        if let Ok(_guard) = BarrierGuard::raise(&self.visitor_counter, None) {
+            // dropping the old caches releases the current thread's cached elements
+            self.thread_cache = cache;
+        }
+
+        self
+    }
+}
+
+impl<T> SyncPool<MaybeUninit<T>> {
+    /// Create a pool with default size of 64 pre-allocated elements, where the elements are allocated
+    /// without being initialized (i.e. via `raw_box_uninit`), such that the cost of zeroing or
+    /// building a large `T` is skipped when the caller always initializes the element before use.
+    ///
+    /// The pool never drops the `T` inside the elements, including when the elements are returned
+    /// via `put_uninit` or when the pool is dropped, since it can't tell if an element has been
+    /// initialized. Return the initialized elements via `put_init` to have the `T` dropped. If a reset
+    /// handle is set, it will be invoked on the `MaybeUninit<T>`, which may well be uninitialized,
+    /// hence it must not read the element as a `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::*;
+    ///
+    /// let mut pool: SyncPool<std::mem::MaybeUninit<[u8; 0x10_000]>> = SyncPool::new_uninit();
+    ///
+    /// let val = pool.get_uninit();
+    /// let val = SyncPool::write_init(val, [42; 0x10_000]);
+    /// assert_eq!(val[4200], 42);
+    ///
+    /// pool.put_init(val);
+    /// ```
+    pub fn new_uninit() -> Self {
+        Self::make_pool(POOL_SIZE, ElemBuilder::Default(raw_box_uninit::<T>))
+    }
+
+    /// Same as `new_uninit`, but create the pool with the pre-defined number of uninitialized
+    /// elements, which is rounded up to a multiple of 8 just like `with_size`.
+    pub fn with_size_uninit(size: usize) -> Self {
+        let pool_size = (size / SLOT_CAP).max(1);
+
+        let mut pool = Self::make_pool(pool_size, ElemBuilder::Default(raw_box_uninit::<T>));
+        pool.capacity_hint = size;
+        pool
+    }
+
+    /// Obtain an element that may be uninitialized, which is the same as `get`. The element shall
+    /// be initialized before it's read, e.g. via `write_init`.
+    #[inline]
+    pub fn get_uninit(&mut self) -> Box<MaybeUninit<T>> {
+        self.get()
+    }
+
+    /// Return an element to the pool without dropping its content, which is the same as `put`. If the
+    /// element has been initialized with a `T` that holds resources (e.g. a `Vec`), use `put_init`
+    /// instead, otherwise the resources will be leaked.
+    #[inline]
+    pub fn put_uninit(&mut self, val: Box<MaybeUninit<T>>) -> Option<Box<MaybeUninit<T>>> {
+        self.put(val)
+    }
+
+    /// Initialize the element with `value` and hand it back as a `Box<T>`, reusing the allocation.
+    /// This is the safe counterpart of `Box::assume_init`, since the whole `T` is written.
+    pub fn write_init(mut val: Box<MaybeUninit<T>>, value: T) -> Box<T> {
+        val.write(value);
+
+        // the value has been initialized
+        unsafe { Box::from_raw(Box::into_raw(val) as *mut T) }
+    }
+
+    /// Drop the `T` in place and return its memory to the pool as an uninitialized element. Same as
+    /// `put`, the element will be handed back if the pool can't take it, where the `T` has been
+    /// dropped already.
+    pub fn put_init(&mut self, val: Box<T>) -> Option<Box<MaybeUninit<T>>> {
+        let raw = Box::into_raw(val);
+
+        let uninit = unsafe {
+            ptr::drop_in_place(raw);
+            Box::from_raw(raw as *mut MaybeUninit<T>)
+        };
+
+        self.put(uninit)
+    }
+}
+
+impl<T: Unpin> SyncPool<T> {
+    /// Return a pinned element obtained from `checkout_pinned` to the `SyncPool`. Since `T: Unpin`,
+    /// the pinning carries no guarantee and the element can be safely moved back into the pool.
+    pub fn put_pinned(&mut self, val: Pin<Box<T>>) -> Option<Pin<Box<T>>> {
+        self.put(Pin::into_inner(val)).map(Box::into_pin)
+    }
+}
+
+impl<T> Default for SyncPool<T>
+where
+    T: Default,
+{
+    fn default() -> Self {
+        SyncPool::new()
+    }
+}
+
+impl<T: Clone> Clone for SyncPool<T> {
+    /// Create an independent pool with the same configuration, where each idle element of this pool
+    /// is cloned into the same bucket of the new pool, e.g. to make a warm pool for each worker out
+    /// of a template pool. The elements checked out at the time, or cached by the threads, are absent
+    /// from the clone; so is the reset handle set via `set_reset_handle_dyn`, since the closure can't
+    /// be cloned. The statistics, e.g. `miss_count`, start from scratch.
+    ///
+    /// The write barrier is held while the elements are cloned, and we will wait for it if someone
+    /// else is holding it.
+    fn clone(&self) -> Self {
+        let mut pool = Self::make_pool(0, self.builder.clone());
+
+        pool.configure = AtomicUsize::new(self.configure.load(Ordering::Acquire));
+        pool.poisoned = AtomicBool::new(self.is_poisoned());
+        pool.reset_handle = self.reset_handle.as_ref().and_then(ResetHandle::try_clone);
+        pool.drop_handle = self.drop_handle;
+        pool.validate_on_put = self.validate_on_put;
+        pool.overflow = self
+            .overflow
+            .as_ref()
+            .map(|queue| ArrayQueue::new(queue.capacity()));
+        pool.access_trials = self.access_trials;
+        pool.barrier_timeout = self.barrier_timeout;
+        pool.capacity_hint = self.capacity_hint;
+        pool.thread_cache = self
+            .thread_cache
+            .as_ref()
+            .map(|cache| cache.fork(Arc::clone(&pool.cached)));
+        pool.affinity = self.affinity.as_ref().map(|_| BucketAffinity::new());
+        pool.numa = self.numa.clone();
+
+        // the slots may be swapped out by whoever holds the barrier, so we must own it to visit them
+        let _guard = loop {
+            match BarrierGuard::raise(&self.visitor_counter, None) {
+                Ok(guard) => break guard,
+                Err(_) => thread::yield_now(),
+            }
+        };
+
+        for slot in self.slots.iter() {
+            let mut elems = Vec::with_capacity(self.slot_cap);
+            slot.for_each(&mut |val: &T| elems.push(Box::new(val.clone())));
+
+            let mut bucket = Bucket2::with_count(None, self.slot_cap, 0);
+            bucket.fill_from(&mut elems.into_iter());
+            pool.slots.push(bucket);
+        }
+
+        pool.slots_mask = mask_of(pool.slots.len());
+        pool.slot_cap = self.slot_cap;
+
+        if let (Some(src), Some(dst)) = (self.overflow.as_ref(), pool.overflow.as_ref()) {
+            // the queue can't be iterated in place, so put the parked elements back in order
+            let parked: Vec<Box<T>> = iter::from_fn(|| src.pop()).collect();
+
+            for val in parked {
+                let _ = dst.push(Box::new(T::clone(&val)));
+                let _ = src.push(val);
+            }
+        }
+
+        pool
+    }
+}
+
+impl<T> Drop for SyncPool<T> {
+    fn drop(&mut self) {
+        if let Some(handle) = self.drop_handle {
+            let mut handle = |val: &mut T| handle(val);
+
+            if let Some(cache) = self.thread_cache.as_ref() {
+                while let Some(mut val) = cache.pop() {
+                    handle(&mut val);
+                }
+            }
+
+            for slot in self.slots.iter_mut() {
+                slot.for_each_mut(&mut handle);
+            }
+
+            if let Some(queue) = self.overflow.as_ref() {
+                while let Some(mut val) = queue.pop() {
+                    handle(&mut val);
+                }
+            }
+
+            while let Some(mut val) = self.returns.pop() {
+                handle(&mut val);
+            }
+        }
+
+        self.slots.clear();
+
+        // now drop the reset handle if it's not null
+        self.reset_handle.take();
+    }
+}
+
+/// The pool hands out and takes back the elements from any thread, so it's only safe to move or
+/// share the pool across threads if the elements themselves can be moved across threads.
+unsafe impl<T: Send> Send for SyncPool<T> {}
+unsafe impl<T: Send> Sync for SyncPool<T> {}
+
+pub trait PoolState {
+    fn expansion_enabled(&self) -> bool;
+
+    fn miss_count(&self) -> usize;
+
+    fn capacity(&self) -> usize;
+
+    fn len(&self) -> usize;
+
+    fn bucket_count(&self) -> usize;
+
+    fn slot_cap(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The fraction of the pool's capacity that is currently checked out, where `len` is the number
+    /// of idle elements parked in the pool, and `capacity` is the number of slots that can hold an
+    /// element. The result is within `[0.0, 1.0]`, and a pool without any capacity is considered to
+    /// be fully utilized. The value is a snapshot and may be stale if the pool is being visited
+    /// concurrently.
+    fn utilization(&self) -> f64 {
+        let cap = self.capacity();
+        if cap == 0 {
+            return 1.0;
+        }
+
+        let len = self.len().min(cap);
+        1.0 - (len as f64 / cap as f64)
+    }
+}
+
+impl<T> PoolState for SyncPool<T> {
+    fn expansion_enabled(&self) -> bool {
+        let configure = self.configure.load(Ordering::SeqCst);
+        configure & CONFIG_ALLOW_EXPANSION > 0
+    }
+
+    fn miss_count(&self) -> usize {
+        self.miss_count.load(Ordering::Acquire)
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots.len() * self.slot_cap
+    }
+
+    /// The number of buckets in the pool, the contention among the visitors is resolved per bucket.
+    fn bucket_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The number of elements that a single bucket can hold, such that `capacity` equals
+    /// `bucket_count * slot_cap`.
+    fn slot_cap(&self) -> usize {
+        self.slot_cap
+    }
+
+    /// The number of idle elements in the pool, counted from the element bits of the buckets, such
+    /// that it's never thrown off by the speculative counters updated while visiting the buckets.
+    /// The count is a snapshot that may be stale if the pool is being visited concurrently.
+    fn len(&self) -> usize {
+        self.slots
+            .iter()
+            .fold(0, |sum, item| sum + item.occupancy())
+    }
+}
+
+impl<T> SyncPool<T> {
+    /// The order in which the idle elements are handed out, see `PoolManager::with_reuse_policy`.
+    pub fn reuse_policy(&self) -> ReusePolicy {
+        if self.configure.load(Ordering::SeqCst) & CONFIG_REUSE_LIFO > 0 {
+            ReusePolicy::Lifo
+        } else {
+            ReusePolicy::Fifo
+        }
+    }
+
+    /// If the pool is in the strict capacity mode, see `PoolManager::with_strict_capacity`.
+    pub fn strict_capacity(&self) -> bool {
+        self.configure.load(Ordering::SeqCst) & CONFIG_STRICT_CAPACITY > 0
+    }
+
+    /// The number of returned elements that have been dropped for failing the check set via
+    /// `PoolManager::validate_on_put`.
+    pub fn invalid_count(&self) -> usize {
+        self.invalid_count.load(Ordering::Acquire)
+    }
+
+    /// The number of elements handed out by the pool, either pooled or newly created, that have not
+    /// been returned via `put` yet.
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.load(Ordering::Acquire)
+    }
+
+    /// The number of elements that can still be checked out before the pool is exhausted in the
+    /// strict capacity mode, see `PoolManager::with_strict_capacity`.
+    pub fn available_permits(&self) -> usize {
+        self.capacity().saturating_sub(self.outstanding())
+    }
+
+    /// The number of retries on a contended bucket, see `PoolManager::with_access_trials`.
+    pub fn access_trials(&self) -> usize {
+        self.access_trials
+    }
+
+    /// How long the write barrier waits for the visitors to leave, see
+    /// `PoolManager::with_barrier_timeout`.
+    pub fn barrier_timeout(&self) -> Duration {
+        self.barrier_timeout
+    }
+
+    /// If each thread starts from the bucket it has visited last, see
+    /// `PoolManager::with_thread_affinity`.
+    pub fn thread_affinity(&self) -> bool {
+        self.affinity.is_some()
+    }
+
+    /// If the buckets are partitioned by the NUMA nodes, which is `false` if the NUMA affinity is
+    /// disabled, or if the NUMA topology couldn't be determined, see `with_numa_affinity`.
+    pub fn numa_affinity(&self) -> bool {
+        self.numa.is_some()
+    }
+
+    /// The number of elements parked in the overflow queue, see `PoolManager::with_overflow`. These
+    /// elements are not counted in `len`.
+    pub fn overflow_len(&self) -> usize {
+        self.overflow.as_ref().map_or(0, |queue| queue.len())
+    }
+
+    /// The strategy that the pool uses to construct new elements, which is useful to confirm how a
+    /// pool has been set up when it's constructed elsewhere.
+    pub fn builder_kind(&self) -> BuilderKind {
+        self.builder.kind()
+    }
+
+    /// The number of elements requested when the pool was constructed, e.g. the `size` given to
+    /// `with_size`, before it's rounded to whole buckets. Unlike `capacity`, the hint won't change
+    /// when the pool is expanded.
+    pub fn capacity_hint(&self) -> usize {
+        self.capacity_hint
+    }
+
+    /// The latency of all the `get` and `put` calls on the pool so far, including the time spent in
+    /// spinning and backing off. The counters are updated independently, so the snapshot may be
+    /// slightly inconsistent if the pool is being visited concurrently.
+    #[cfg(feature = "metrics")]
+    pub fn timing_snapshot(&self) -> TimingStats {
+        self.timings.snapshot()
+    }
+}
+
+pub trait PoolManager<T> {
+    fn reset_handle(&mut self, handle: fn(&mut T)) -> bool;
+    fn set_reset_handle_dyn(&mut self, handle: Box<dyn FnMut(&mut T) + Send>) -> bool;
+    fn reject_handle(&mut self, handle: fn(&mut T) -> bool) -> bool;
+    fn drop_handle(&mut self, handle: fn(&mut T)) -> &mut Self;
+    fn validate_on_put(&mut self, handle: fn(&T) -> bool) -> &mut Self;
+    fn allow_expansion(&mut self, allow: bool) -> &mut Self;
+    fn with_reuse_policy(&mut self, policy: ReusePolicy) -> &mut Self;
+    fn with_strict_capacity(&mut self, strict: bool) -> &mut Self;
+    fn with_access_trials(&mut self, trials: usize) -> &mut Self;
+    fn with_barrier_timeout(&mut self, timeout: Duration) -> &mut Self;
+    fn with_thread_affinity(&mut self, enable: bool) -> &mut Self;
+    fn with_numa_affinity(&mut self, enable: bool) -> &mut Self;
+    fn with_overflow(&mut self, cap: usize) -> &mut Self;
+    fn expand(&mut self, additional: usize, block: bool) -> bool;
+    fn try_expand(&mut self, additional: usize, block: bool) -> Result<usize, ExpandError>;
+    fn refill(&mut self, count: usize) -> usize;
+}
+
+/// The pool manager that provide many useful utilities to keep the SyncPool close to the needs of
+/// the caller program.
+impl<T> PoolManager<T> for SyncPool<T> {
+    /// Set or update the reset handle. If set, the reset handle will be invoked every time an element
+    /// has been returned back to the pool (i.e. calling the `put` method), regardless of if the element
+    /// is created by the pool or not.
+    ///
+    /// The handle is replaced under the write barrier once the threads using the pool have left, and
+    /// we will return `false` and keep the old handle if that doesn't happen within the barrier
+    /// timeout (see `with_barrier_timeout`), or someone else is holding the barrier.
+    fn reset_handle(&mut self, handle: fn(&mut T)) -> bool {
+        self.update_reset(ResetHandle::Reset(handle))
+    }
+
+    /// Set or update the reset handle with a boxed closure, e.g. one of the reset strategies picked
+    /// at runtime, or a closure holding its own states. The handle is invoked the same way as the one
+    /// set via `reset_handle`, but the calls are serialized by a lock, since the handle may mutate
+    /// its states, which makes it more costly than a plain `fn` under contention. This replaces any
+    /// reset handle set previously, and returns `false` if it can't, same as `reset_handle`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let mut pool: SyncPool<Vec<u8>> = SyncPool::with_size(8);
+    ///
+    /// let resets = Arc::new(AtomicUsize::new(0));
+    /// let counter = Arc::clone(&resets);
+    ///
+    /// assert!(pool.set_reset_handle_dyn(Box::new(move |val: &mut Vec<u8>| {
+    ///     val.clear();
+    ///     counter.fetch_add(1, Ordering::SeqCst);
+    /// })));
+    ///
+    /// let mut val = pool.get();
+    /// val.extend_from_slice(b"hello");
+    /// pool.put(val);
+    ///
+    /// assert_eq!(resets.load(Ordering::SeqCst), 1);
+    /// ```
+    fn set_reset_handle_dyn(&mut self, handle: Box<dyn FnMut(&mut T) + Send>) -> bool {
+        self.update_reset(ResetHandle::Dyn(Mutex::new(handle)))
+    }
+
+    /// Set or update the reset handle with one that may reject the returned element. The handle is
+    /// invoked the same way as the one set via `reset_handle`, but if it returns `false`, the element
+    /// will be dropped instead of being placed back into the pool, e.g. to evict a wrapped connection
+    /// that has errored out. This replaces any reset handle set previously, and returns `false` if it
+    /// can't, same as `reset_handle`.
+    fn reject_handle(&mut self, handle: fn(&mut T) -> bool) -> bool {
+        self.update_reset(ResetHandle::Reject(handle))
+    }
+
+    /// Set or update the handle to be invoked on each idle element when the pool is dropped, e.g. to
+    /// hand an OS resource back before the element goes away. The handle runs before the element's
+    /// own `Drop`. It only covers the elements parked in the pool, including the overflow queue and
+    /// the current thread's cache; the elements checked out at the time are dropped by their owners,
+    /// and the ones cached by other threads are dropped when those threads exit, both without the
+    /// handle being invoked.
+    fn drop_handle(&mut self, handle: fn(&mut T)) -> &mut Self {
+        self.drop_handle = Some(handle);
+        self
+    }
+
+    /// Set or update the check that a returned element shall pass before it's placed back into the
+    /// pool, otherwise the element will be dropped and counted in `SyncPool::invalid_count`. The
+    /// check runs before the reset handle. This guards the pools whose elements may differ in size
+    /// or shape, e.g. a `SyncPool<Box<[u8]>>`, against handing out a mismatched element later.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use syncpool::prelude::*;
+    ///
+    /// let mut pool = SyncPool::with_builder_and_size(8, || vec![0u8; 16].into_boxed_slice());
+    /// pool.validate_on_put(|val| val.len() == 16);
+    ///
+    /// let val = pool.get();
+    /// assert!(pool.put(Box::new(vec![0u8; 32].into_boxed_slice())).is_none());
+    /// assert_eq!(pool.invalid_count(), 1);
+    ///
+    /// pool.put(val);
+    /// assert!((0..8).all(|_| pool.get().len() == 16));
+    /// ```
+    fn validate_on_put(&mut self, handle: fn(&T) -> bool) -> &mut Self {
+        self.validate_on_put = Some(handle);
+        self
+    }
+
+    /// Set or update the settings that if we will allow the `SyncPool` to be expanded.
+    fn allow_expansion(&mut self, allow: bool) -> &mut Self {
+        if !(self.expansion_enabled() ^ allow) {
+            // not flipping the configuration, return
+            return self;
+        }
+
+        self.update_config(CONFIG_ALLOW_EXPANSION, allow);
+        self
+    }
+
+    /// Set the order in which the idle elements are handed out, see `ReusePolicy` for the tradeoff.
+    /// The pool uses `ReusePolicy::Fifo` by default.
+    fn with_reuse_policy(&mut self, policy: ReusePolicy) -> &mut Self {
+        if self.reuse_policy() != policy {
+            self.update_config(CONFIG_REUSE_LIFO, policy == ReusePolicy::Lifo);
+        }
+
+        self
+    }
+
+    /// Set if the pool shall act as a semaphore that bounds the number of outstanding elements by its
+    /// capacity. In the strict capacity mode, `get` will wait for an element to be returned instead
+    /// of creating a new one past the capacity, and `try_get` will return `None` right away. This is
+    /// useful when the elements stand for limited resources, e.g. connections.
+    ///
+    /// The returned elements skip the thread caches (see `SyncPool::with_thread_cache`) in the strict
+    /// capacity mode, such that they can be handed to any waiting thread. The elements cached before
+    /// the mode is turned on still count against the capacity, until they're checked out again by
+    /// their threads, or dropped when those threads exit.
+    fn with_strict_capacity(&mut self, strict: bool) -> &mut Self {
+        if self.strict_capacity() != strict {
+            self.update_config(CONFIG_STRICT_CAPACITY, strict);
+        }
+
+        self
+    }
+
+    /// Set the number of times to retry on a contended bucket before moving on to the next bucket,
+    /// which is 4 by default. More trials on the same bucket may reduce the wasted bucket-hopping on
+    /// machines with many cores. The value will be at least 1.
+    fn with_access_trials(&mut self, trials: usize) -> &mut Self {
+        self.access_trials = trials.max(1);
+        self
+    }
+
+    /// Set the longest time to wait for the write barrier when reconfiguring the pool, i.e. in
+    /// `reset_handle`, `reject_handle` and the non-blocking `expand`, which is 16ms by default. A
+    /// busy pool may never leave the barrier free for that long, and the reconfiguration will be
+    /// given up; a longer timeout makes the reconfiguration more likely to go through, at the cost
+    /// of a longer stall of the caller.
+    fn with_barrier_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.barrier_timeout = timeout;
+        self
+    }
+
+    /// Set if each thread shall start looking for a bucket from the one it has visited the last
+    /// time, instead of the position shared by all threads. This avoids the contention on the shared
+    /// position, and improves the chance that a thread reuses a bucket still hot in its CPU cache.
+    /// The thread affinity is disabled by default.
+    fn with_thread_affinity(&mut self, enable: bool) -> &mut Self {
+        if self.thread_affinity() == enable {
+            return self;
+        }
+
+        let affinity = if enable {
+            Some(BucketAffinity::new())
+        } else {
+            None
+        };
+
+        if let Ok(_guard) = BarrierGuard::raise(&self.visitor_counter, None) {
+            self.affinity = affinity;
+        }
+
+        self
+    }
+
+    /// Set if the buckets shall be partitioned by the NUMA nodes of a multi-socket machine, such
+    /// that each thread starts looking for a bucket in the range owned by the node it's running on,
+    /// and prefers the elements last touched on the same node. The threads still move on to the
+    /// buckets of other nodes when the ones of their own node are exhausted. The thread affinity
+    /// (see `with_thread_affinity`) takes precedence if both are enabled.
+    ///
+    /// The NUMA topology is only available on Linux with the `numa` feature. Otherwise, or if the
+    /// machine has a single node, the pool sticks to the position shared by all threads, and
+    /// `SyncPool::numa_affinity` will report `false`. The NUMA affinity is disabled by default.
+    fn with_numa_affinity(&mut self, enable: bool) -> &mut Self {
+        let numa = if enable { NumaAffinity::detect() } else { None };
+
+        if let Ok(_guard) = BarrierGuard::raise(&self.visitor_counter, None) {
+            self.numa = numa;
+        }
+
+        self
+    }
+
+    /// Set up a bounded overflow queue that can hold at most `cap` elements. When all the slots in the
+    /// pool are full (e.g. the pool can't be expanded any further), the elements returned by `put`
+    /// will be parked in the overflow queue, and `get` will drain the queue before creating new
+    /// elements. This caps the total retained memory, while elements can still be recycled during a
+    /// burst beyond the pool's capacity.
+    ///
+    /// Setting the `cap` to 0 removes the overflow queue, and all elements in the old overflow queue
+    /// will be dropped when the queue is replaced.
+    fn with_overflow(&mut self, cap: usize) -> &mut Self {
+        let queue = if cap > 0 {
+            Some(ArrayQueue::new(cap))
+        } else {
+            None
+        };
+
+        if let Ok(_guard) = BarrierGuard::raise(&self.visitor_counter, None) {
+            self.overflow = queue;
+        }
+
+        self
+    }
+
+    /// Try to expand the `SyncPool` and add more elements to it. Usually invoke this API only when
+    /// the caller is certain that the pool is under pressure, and that a short block to the access
+    /// of the pool won't cause serious issues, since the function will block the current caller's
+    /// thread until it's finished (i.e. get the opportunity to raise the writer's barrier and wait
+    /// everyone to leave).
+    ///
+    /// If we're unable to expand the pool, it's due to one of the following reasons: 1) someone has
+    /// already raised the writer's barrier and is likely modifying the pool, we will leave immediately,
+    /// and it's up to the caller if they want to try again; 2) we've waited too long but still couldn't
+    /// obtain an exclusive access to the pool, and similar to reason 1), we will quit now. If `block`
+    /// is `false`, we will wait no longer than the barrier timeout, see `with_barrier_timeout`;
+    /// otherwise we will wait until all visitors have left.
+    fn expand(&mut self, additional: usize, block: bool) -> bool {
+        self.try_expand(additional, block).is_ok()
+    }
+
+    /// Same as `expand`, but return the number of slots added to the pool if the expansion succeeds,
+    /// or the reason that we are unable to expand the pool, such that the caller can decide to retry,
+    /// give up, or enable the expansion first.
+    fn try_expand(&mut self, additional: usize, block: bool) -> Result<usize, ExpandError> {
+        // if the pool isn't allowed to expand, just return
+        if !self.expansion_enabled() {
+            return Err(ExpandError::Disabled);
+        }
+
+        // if exceeding the upper limit, quit
+        if self.slots.len() > EXPANSION_CAP {
+            return Err(ExpandError::CapReached);
+        }
+
+        // raise the write barrier and wait for all visitors to leave, the barrier will be lowered
+        // when the guard goes out of scope.
+        let timeout = if block {
+            None
+        } else {
+            Some(self.barrier_timeout)
+        };
+
+        let _guard = BarrierGuard::raise(&self.visitor_counter, timeout)?;
+
+        // update the slots by pushing `additional` slots
+        Self::add_slots(
+            &mut self.slots,
+            &self.builder,
+            self.slot_cap,
+            additional,
+            true,
+        );
+        self.slots_mask = mask_of(self.slots.len());
+        self.miss_count.store(0, Ordering::Release);
+
+        Ok(additional)
+    }
+
+    /// Due to contentious access to the pool, sometimes the `put` action could not finish and return
+    /// the element to the pool successfully. Overtime, this could cause the number of elements in the
+    /// pool to dwell. This would only happen slowly if we're running a very contentious multithreading
+    /// program, but it surely could happen. If the caller detects such situation, they can invoke the
+    /// `refill` API and try to refill the pool with elements.
+    ///
+    /// We will try to refill as many elements as requested
+    fn refill(&mut self, additional: usize) -> usize {
+        let cap = self.capacity();
+        let empty_slots = cap - self.len();
+
+        if empty_slots == 0 {
+            return 0;
+        }
+
+        let quota = if additional > empty_slots {
+            empty_slots
+        } else {
+            additional
+        };
+
+        let mut count = 0;
+        let timeout = Instant::now().add(Duration::from_millis(16));
+
+        // try to put `quota` number of elements into the pool
+        while count < quota {
+            let mut val = make_elem(&self.builder);
+            let mut runs = 0;
+
+            // retry to put the allocated element into the pool.
+            while let Some(ret) = self.put_idle(val) {
+                val = ret;
+                runs += 1;
+
+                // timeout
+                if Instant::now() > timeout {
+                    return count;
+                }
+
+                // check the pool length for every 4 failed attempts to put the element into the pool.
+                if runs % 4 == 0 && self.len() == cap {
+                    return count;
+                }
+
+                // relax a bit
+                if runs > 8 {
+                    thread::yield_now();
+                } else {
+                    cpu_relax(runs / 2);
+                }
+            }
+
+            count += 1;
+        }
+
+        count
+    }
+}
+
+/// The mask for wrapping the slot positions, which is only available if the number of slots is a
+/// power of 2. Must be recomputed whenever the number of slots has changed.
+#[inline]
+fn mask_of(cap: usize) -> Option<usize> {
+    if cap.is_power_of_two() {
+        Some(cap - 1)
+    } else {
+        None
+    }
+}
+
+/// Wrap the position around the number of slots, using the mask if it's available.
+#[inline(always)]
+fn wrap_pos(pos: usize, cap: usize, mask: Option<usize>) -> usize {
+    match mask {
+        Some(mask) => pos & mask,
+        None => pos % cap,
+    }
+}
+
+/// Spin a few rounds before yielding the thread to the ones that may return elements.
+#[inline]
+fn backoff(count: usize) {
+    if count < SPIN_PERIOD {
+        cpu_relax(count + 1);
+    } else {
+        thread::yield_now();
+    }
+}
+
+#[cfg(test)]
+mod pool_tests {
+    use super::*;
+    use crate::numa::pin_current_node;
+    use std::vec;
+
+    struct BigStruct {
+        a: u32,
+        b: u32,
+        c: Vec<u8>,
+    }
+
+    impl BigStruct {
+        fn new() -> Self {
+            BigStruct {
+                a: 1,
+                b: 42,
+                c: vec::from_elem(0u8, 0x1_000_000),
+            }
+        }
+
+        fn initializer(mut self: Box<Self>) -> Box<Self> {
+            self.a = 1;
+            self.b = 42;
+            self.c = vec::from_elem(0u8, 0x1_000_000);
+
+            self
+        }
+    }
+
+    #[test]
+    fn use_packer() {
+        let mut pool = SyncPool::with_packer(BigStruct::initializer);
+
+        let big_box = pool.get();
+
+        assert_eq!(big_box.a, 1);
+        assert_eq!(big_box.b, 42);
+        assert_eq!(big_box.c.len(), 0x1_000_000);
+    }
+
+    #[test]
+    fn pre_touch() {
+        let mut pool = SyncPool::with_builder(BigStruct::new);
+
+        let touched = pool.pre_touch(|val| {
+            for i in (0..val.c.len()).step_by(4096) {
+                val.c[i] = 1;
+            }
+        });
+
+        assert_eq!(touched, pool.len());
+
+        let big_box = pool.get();
+        assert_eq!(big_box.c[4096], 1);
+    }
+
+    #[test]
+    fn utilization() {
+        let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(16);
+        assert_eq!(pool.utilization(), 0.0);
+
+        let first = pool.get();
+        let second = pool.get();
+        assert_eq!(pool.utilization(), 2.0 / 16.0);
+
+        pool.put(first);
+        pool.put(second);
+        assert_eq!(pool.utilization(), 0.0);
+    }
+
+    #[test]
+    fn pinned() {
+        let mut pool = SyncPool::with_builder(BigStruct::new);
+        let len = pool.len();
+
+        let mut pinned = pool.checkout_pinned();
+        pinned.as_mut().get_mut().a = 2;
+        assert_eq!(pool.len(), len - 1);
+
+        assert!(pool.put_pinned(pinned).is_none());
+        assert_eq!(pool.len(), len);
+    }
+
+    #[test]
+    fn probe_next_bucket() {
+        let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(16);
+
+        // drain the first bucket, then the next `get` must move on to the second bucket rather
+        // than probing the drained bucket again.
+        let mut elems: Vec<Box<[u8; 32]>> = (0..SLOT_CAP).map(|_| pool.get()).collect();
+        assert_eq!(pool.miss_count(), 0);
+
+        elems.push(pool.get());
+        assert_eq!(pool.miss_count(), 0);
+        assert_eq!(pool.len(), 16 - SLOT_CAP - 1);
+    }
+
+    #[test]
+    fn capacity_exact() {
+        let mut pool: SyncPool<[u8; 32]> = SyncPool::with_capacity_exact(10);
+        assert_eq!(pool.len(), 10);
+        assert_eq!(pool.capacity(), 16);
+
+        let elems: Vec<Box<[u8; 32]>> = (0..12).map(|_| pool.get()).collect();
+        assert_eq!(pool.len(), 0);
+
+        for elem in elems {
+            assert!(pool.put(elem).is_none());
+        }
+
+        assert_eq!(pool.len(), 12);
+    }
+
+    #[test]
+    fn swap_builder() {
+        let mut pool = SyncPool::with_builder(BigStruct::new);
+        let taken: Vec<Box<BigStruct>> = (0..pool.capacity()).map(|_| pool.get()).collect();
+
+        assert!(pool.swap_builder(|| BigStruct {
+            a: 2,
+            b: 42,
+            c: Vec::new(),
+        }));
+
+        let fresh = pool.get();
+        assert_eq!(fresh.a, 2);
+        assert!(fresh.c.is_empty());
+
+        assert!(taken.iter().all(|val| val.a == 1));
+    }
+
+    #[test]
+    fn try_expand() {
+        let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(16);
+        assert_eq!(pool.try_expand(2, true), Err(ExpandError::Disabled));
+
+        pool.allow_expansion(true);
+        assert_eq!(pool.try_expand(2, true), Ok(2));
+        assert_eq!(pool.capacity(), 32);
+    }
+
+    #[test]
+    fn take_all_into() {
+        let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(16);
+        let mut sink = Vec::new();
+
+        let val = pool.get();
+        assert_eq!(pool.take_all_into(&mut sink), 15);
+        assert_eq!(sink.len(), 15);
+        assert_eq!(pool.len(), 0);
+
+        assert!(pool.put(val).is_none());
+        assert_eq!(pool.len(), 1);
+
+        sink.clear();
+        assert_eq!(pool.take_all_into(&mut sink), 1);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn iter_mut_idle() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(16);
+
+        let held = pool.get();
+        assert_eq!(pool.iter_mut_idle(|val| *val = 42), 15);
+        assert_eq!(*held, 0);
+
+        (0..15).for_each(|_| assert_eq!(*pool.get(), 42));
+
+        // the pool stays accessible even if the handler panics
+        assert!(pool.put(held).is_none());
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            pool.iter_mut_idle(|_| panic!("handler failure"));
+        }));
+
+        assert!(res.is_err());
+        pool.assert_invariants();
+        assert_eq!(*pool.get(), 0);
+    }
+
+    #[test]
+    fn get_arc() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+
+        let shared = unsafe { pool.get_arc() };
+        let readers: Vec<PoolArc<usize>> = (0..4).map(|_| shared.clone()).collect();
+        assert_eq!(pool.len(), 7);
+
+        drop(shared);
+        assert!(readers.iter().all(|val| **val == 0));
+        assert_eq!(pool.len(), 7);
+
+        // the element is returned once the last reader is gone
+        drop(readers);
+        assert_eq!(pool.len(), 8);
+        pool.assert_invariants();
+    }
+
+    #[test]
+    fn bucket_debug() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(16);
+        let val = pool.get();
+
+        let states: Vec<BucketDebug> = (0..pool.bucket_count())
+            .map(|idx| pool.bucket_debug(idx))
+            .collect();
+
+        let count: usize = states.iter().map(|state| state.check_len).sum();
+        assert_eq!(count, 15);
+
+        // no one is visiting, hence no lock bits, and the length hints are settled
+        assert!(states.iter().all(|state| state.len == state.check_len));
+        assert!(states.iter().all(|state| state.bitmap & 0xaaaa == 0));
+
+        // the one element is missing from a bucket that's otherwise full
+        let partial = states.iter().find(|state| state.check_len == 7).unwrap();
+        assert_eq!(partial.bitmap.count_ones(), 7);
+
+        pool.put(val);
+    }
+
+    #[test]
+    fn get_into_slot() {
+        #[derive(Default)]
+        struct Frame(Vec<u8>);
+
+        struct Worker {
+            frame: Option<Box<Frame>>,
+            done: usize,
+        }
+
+        impl Worker {
+            fn work(&mut self, pool: &mut SyncPool<Frame>, input: &[u8]) {
+                let frame = pool.get_into(&mut self.frame);
+                frame.0.extend_from_slice(input);
+
+                if frame.0.len() >= 4 {
+                    self.done += 1;
+                    pool.put_from(&mut self.frame);
+                }
+            }
+        }
+
+        let mut pool: SyncPool<Frame> = SyncPool::with_size(8);
+        pool.reset_handle(|val| val.0.clear());
+
+        let mut worker = Worker {
+            frame: None,
+            done: 0,
+        };
+
+        for _ in 0..10 {
+            worker.work(&mut pool, b"ab");
+
+            // the worker never holds more than one element at a time
+            assert!(pool.outstanding() <= 1);
+        }
+
+        assert_eq!(worker.done, 5);
+        assert!(worker.frame.is_none());
+        assert_eq!(pool.outstanding(), 0);
+        assert_eq!(pool.len(), 8);
+
+        // returning from an empty slot is a no-op
+        assert!(pool.put_from(&mut worker.frame).is_none());
+        assert_eq!(pool.len(), 8);
+    }
+
+    #[test]
+    fn try_get_exactly() {
+        static RESETS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut pool: SyncPool<usize> = SyncPool::with_size(16);
+        pool.reset_handle(|_| {
+            RESETS.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let vals: Vec<Box<usize>> = (0..12).map(|_| pool.get()).collect();
+        let misses = pool.miss_count();
+
+        // the partial batch is placed back as is, without running through the reset handle
+        assert!(pool.try_get_exactly(5).is_none());
+        assert_eq!(pool.len(), 4);
+        assert_eq!(pool.outstanding(), 12);
+        assert_eq!(pool.miss_count(), misses);
+        assert_eq!(RESETS.load(Ordering::SeqCst), 0);
+        pool.assert_invariants();
+
+        let batch = pool.try_get_exactly(4).unwrap();
+        assert_eq!(batch.len(), 4);
+        assert_eq!(pool.len(), 0);
+        assert_eq!(pool.outstanding(), 16);
+
+        drop(vals);
+    }
+
+    #[test]
+    fn validate_on_put() {
+        let mut pool = SyncPool::with_builder_and_size(8, || vec![0u8; 16].into_boxed_slice());
+        pool.validate_on_put(|val| val.len() == 16);
+
+        let vals: Vec<Box<Box<[u8]>>> = (0..8).map(|_| pool.get()).collect();
+        assert_eq!(pool.len(), 0);
+
+        // the mismatched elements are dropped instead of being pooled
+        for (i, val) in vals.into_iter().enumerate() {
+            let val = if i % 2 == 0 {
+                val
+            } else {
+                Box::new(vec![0u8; 32].into_boxed_slice())
+            };
+
+            assert!(pool.put(val).is_none());
+        }
+
+        assert_eq!(pool.len(), 4);
+        assert_eq!(pool.invalid_count(), 4);
+
+        // and the batch returns are validated as well
+        let mut batch = pool.checkout_batch(4);
+        *batch[0] = vec![0u8; 8].into_boxed_slice();
+        drop(batch);
+
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.invalid_count(), 5);
+        assert!((0..8).all(|_| pool.get().len() == 16));
+        pool.assert_invariants();
+    }
+
+    #[test]
+    fn recycle_validates() {
+        let mut pool = SyncPool::with_builder_and_size(8, || vec![0u8; 16]);
+        pool.validate_on_put(|val| val.len() == 16);
+        pool.reset_handle(|val| val.fill(0));
+
+        let mut val = pool.get();
+        val[0] = 1;
+        let val = pool.recycle(val, |val| val[1] = 2);
+        assert_eq!(val[..2], [0, 2]);
+        assert_eq!(pool.len(), 7);
+
+        // the mismatched element is replaced by a pooled one
+        let val = pool.recycle(Box::new(vec![0u8; 32]), |val| val[1] = 3);
+        assert_eq!(val.len(), 16);
+        assert_eq!(val[1], 3);
+        assert_eq!(pool.invalid_count(), 1);
+        assert_eq!(pool.len(), 6);
+    }
+
+    #[test]
+    fn reject_handle() {
+        let mut pool: SyncPool<(bool, usize)> = SyncPool::with_size(8);
+        pool.reject_handle(|val| {
+            val.1 = 0;
+            !val.0
+        });
+
+        let mut vals: Vec<Box<(bool, usize)>> = (0..8).map(|_| pool.get()).collect();
+        assert_eq!(pool.len(), 0);
+
+        vals.iter_mut().enumerate().for_each(|(i, val)| {
+            **val = (i % 4 == 0, i);
+        });
+
+        vals.into_iter().for_each(|val| {
+            assert!(pool.put(val).is_none());
+        });
+
+        // the poisoned elements are dropped on return
+        assert_eq!(pool.len(), 6);
+        pool.assert_invariants();
+
+        let mut sink = Vec::new();
+        pool.take_all_into(&mut sink);
+        assert!(sink.iter().all(|val| **val == (false, 0)));
+    }
+
+    #[test]
+    fn get_timeout_or() {
+        let mut pool: SyncPool<Vec<u8>> = SyncPool::with_builder_and_size(8, || vec![0u8; 64]);
+        let mut taken: Vec<Box<Vec<u8>>> = (0..8).map(|_| pool.get()).collect();
+        assert_eq!(pool.miss_count(), 0);
+
+        let degraded = pool.get_timeout_or(Duration::from_millis(1), || Box::new(Vec::new()));
+        assert!(degraded.is_empty());
+        assert_eq!(pool.miss_count(), 1);
+
+        assert!(pool.put(taken.pop().unwrap()).is_none());
+
+        let pooled = pool.get_timeout_or(Duration::from_millis(1), || Box::new(Vec::new()));
+        assert_eq!(pooled.len(), 64);
+        assert_eq!(pool.miss_count(), 1);
+    }
+
+    #[test]
+    fn get_timeout_or_strict() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        pool.with_strict_capacity(true);
+
+        let mut vals: Vec<Box<usize>> = (0..8).map(|_| pool.get()).collect();
+        assert_eq!(pool.outstanding(), 8);
+
+        // the fallback is not taken past the capacity, the element returned after the deadline is
+        // handed over instead
+        let val = vals.pop().unwrap();
+        let shared = SharedPool(&mut pool as *mut SyncPool<usize>);
+        let handle = thread::spawn(move || {
+            let shared = shared;
+            let pool = unsafe { &mut *shared.0 };
+
+            thread::sleep(Duration::from_millis(20));
+            pool.put(val);
+        });
+
+        let val = pool.get_timeout_or(Duration::from_millis(1), || Box::new(usize::MAX));
+        handle.join().unwrap();
+
+        assert_eq!(*val, 0);
+        assert_eq!(pool.outstanding(), 8);
+        assert_eq!(pool.miss_count(), 0);
+    }
+
+    #[test]
+    fn warmup_parallel() {
+        let mut pool: SyncPool<Vec<u8>> = SyncPool::with_builder_and_size(8, || vec![1u8; 64]);
+        let taken: Vec<Box<Vec<u8>>> = (0..2).map(|_| pool.get()).collect();
+        assert_eq!(pool.len(), 6);
+
+        // the pool can't grow, only the 2 empty slots are filled
+        assert_eq!(pool.warmup_parallel(4, 18), 2);
+        assert_eq!(pool.len(), 8);
+        assert_eq!(pool.capacity(), 8);
+
+        // grow by 2 buckets
+        pool.allow_expansion(true);
+        assert_eq!(pool.warmup_parallel(4, 16), 16);
+        assert_eq!(pool.len(), 24);
+        assert_eq!(pool.capacity(), 24);
+        pool.assert_invariants();
+
+        let mut sink = Vec::new();
+        pool.take_all_into(&mut sink);
+        assert!(sink.iter().all(|val| **val == vec![1u8; 64]));
+        drop(taken);
+
+        // the growth stops at the bucket cap
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        pool.allow_expansion(true);
+        assert_eq!(
+            pool.warmup_parallel(2, EXPANSION_CAP * SLOT_CAP),
+            (EXPANSION_CAP - 1) * SLOT_CAP
+        );
+        assert_eq!(pool.capacity(), EXPANSION_CAP * SLOT_CAP);
+    }
+
+    #[test]
+    fn with_checked_out() {
+        let mut pool: SyncPool<Vec<u8>> = SyncPool::with_size(8);
+        pool.reset_handle(|val| val.clear());
+
+        let len = pool.with_checked_out(|val| {
+            val.extend_from_slice(b"hello");
+            val.len()
+        });
+
+        assert_eq!(len, 5);
+        assert_eq!(pool.len(), 8);
+
+        // the element is returned and reset even if the handler panics
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            pool.with_checked_out(|val| {
+                val.push(1);
+                panic!("handler failure");
+            })
+        }));
+
+        assert!(res.is_err());
+        assert_eq!(pool.len(), 8);
+        pool.assert_invariants();
+
+        let mut sink = Vec::new();
+        pool.take_all_into(&mut sink);
+        assert!(sink.iter().all(|val| val.is_empty()));
+    }
+
+    #[test]
+    fn reuse_policy() {
+        for &(policy, expected) in &[(ReusePolicy::Fifo, 1), (ReusePolicy::Lifo, 3)] {
+            let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+            pool.with_reuse_policy(policy);
+            assert_eq!(pool.reuse_policy(), policy);
+
+            let mut vals: Vec<Box<usize>> = (0..8).map(|_| pool.get()).collect();
+            assert_eq!(pool.len(), 0);
+
+            // return the elements in the order of 1, 2, 3
+            for i in 1..=3 {
+                let mut val = vals.pop().unwrap();
+                *val = i;
+                assert!(pool.put(val).is_none());
+            }
+
+            assert_eq!(*pool.get(), expected);
+        }
+    }
+
+    #[test]
+    fn slot_width() {
+        for &width in [1, 16, 32].iter() {
+            let mut pool: SyncPool<usize> = SyncPool::with_slot_width(4 * width, width);
+            assert_eq!(pool.slot_cap(), width);
+            assert_eq!(pool.bucket_count(), 4);
+            assert_eq!(pool.capacity(), 4 * width);
+            assert_eq!(pool.len_per_bucket(), vec![width; 4]);
+
+            let vals: Vec<Box<usize>> = (0..4 * width).map(|_| pool.get()).collect();
+            assert_eq!(pool.len(), 0);
+            assert_eq!(pool.slot_occupancy_histogram()[0], 4);
+
+            for val in vals {
+                pool.put(val);
+            }
+
+            assert_eq!(pool.len(), 4 * width);
+            assert_eq!(pool.slot_occupancy_histogram()[width], 4);
+            assert!(pool.validate().is_ok());
+            pool.assert_invariants();
+        }
+    }
+
+    #[test]
+    fn slot_width_contended() {
+        // a single bucket, such that all threads contend on the same bitmap of 32 slots
+        let mut pool: SyncPool<usize> = SyncPool::with_slot_width(32, 32);
+        pool.with_reuse_policy(ReusePolicy::Lifo);
+        assert_eq!(pool.bucket_count(), 1);
+        assert_eq!(pool.bucket_debug(0).bitmap, 0x5555_5555_5555_5555);
+
+        // the full bucket hands out the top slot at the highest bits first, and takes it back
+        let val = pool.get();
+        assert_eq!(pool.bucket_debug(0).bitmap, 0x1555_5555_5555_5555);
+        pool.put(val);
+        assert_eq!(pool.bucket_debug(0).bitmap, 0x5555_5555_5555_5555);
+
+        // tag each pooled element with a unique id, newly created elements will be 0
+        let mut id = 0;
+        pool.iter_mut_idle(|val| {
+            id += 1;
+            *val = id;
+        });
+
+        // with the LIFO reuse, the threads keep fighting over the highest occupied slot, which is
+        // the top slot whenever the bucket is full, while the batches of up to 8 elements from the 4
+        // threads drain the whole bitmap from time to time
+        let evicted = contend(&mut pool, 4, |_, pool| {
+            let mut evicted = Vec::new();
+
+            for i in 0..1000 {
+                let vals: Vec<Box<usize>> = (0..i % 8 + 1).map(|_| pool.get()).collect();
+                for val in vals {
+                    if let Some(val) = pool.put(val) {
+                        evicted.push(*val);
+                    }
+                }
+            }
+
+            evicted
+        });
+
+        assert_eq!(pool.outstanding(), 0);
+        assert!(pool.validate().is_ok());
+        pool.assert_invariants();
+
+        // every tagged element is either in the pool or evicted, exactly once
+        let mut ids: Vec<usize> = evicted.into_iter().flatten().collect();
+        let mut sink = Vec::new();
+        pool.take_all_into(&mut sink);
+        ids.extend(sink.into_iter().map(|val| *val));
+
+        ids.retain(|&id| id > 0);
+        ids.sort_unstable();
+        assert_eq!(ids, (1..=32).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    #[should_panic]
+    fn slot_width_overflow() {
+        let _pool: SyncPool<usize> = SyncPool::with_slot_width(64, MAX_SLOT_CAP + 1);
+    }
+
+    #[test]
+    fn slot_occupancy_histogram() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(16);
+
+        let mut expected = [0; SLOT_CAP + 1];
+        expected[SLOT_CAP] = 2;
+        assert_eq!(pool.slot_occupancy_histogram(), expected);
+
+        let vals: Vec<Box<usize>> = (0..3).map(|_| pool.get()).collect();
+        let histogram = pool.slot_occupancy_histogram();
+        assert_eq!(histogram.iter().sum::<usize>(), 2);
+        assert_eq!(
+            histogram
+                .iter()
+                .enumerate()
+                .map(|(k, n)| k * n)
+                .sum::<usize>(),
+            pool.len()
+        );
+
+        for val in vals {
+            pool.put(val);
+        }
+
+        assert_eq!(pool.slot_occupancy_histogram(), expected);
+    }
+
+    #[test]
+    fn len_per_bucket() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(16);
+        assert_eq!(pool.len_per_bucket(), vec![SLOT_CAP; 2]);
+
+        let mut count = 0;
+        pool.retain(|_| {
+            count += 1;
+            count > SLOT_CAP
+        });
+
+        assert_eq!(pool.len_per_bucket(), vec![0, SLOT_CAP]);
+        assert_eq!(pool.compact(), 0);
+
+        pool.refill(3);
+        let lens = pool.len_per_bucket();
+        assert_eq!(lens.len(), pool.capacity() / SLOT_CAP);
+        assert_eq!(lens.iter().sum::<usize>(), pool.len());
+    }
+
+    #[test]
+    fn validate() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(16);
+        let vals: Vec<Box<usize>> = (0..5).map(|_| pool.get()).collect();
+
+        let report = pool.validate();
+        assert!(report.is_ok(), "{:?}", report.anomalies);
+        assert_eq!(report.idle, pool.len());
+        assert_eq!(report.buckets, pool.capacity() / SLOT_CAP);
+
+        for val in vals {
+            pool.put(val);
+        }
+
+        assert_eq!(pool.validate().idle, 16);
+
+        // the boxes of a zero-sized type all share the same address, which is not an alias
+        let unit: SyncPool<()> = SyncPool::with_size(8);
+        assert!(unit.validate().is_ok());
+
+        // someone else is holding the write barrier
+        pool.visitor_counter.1.store(true, Ordering::Release);
+        let report = pool.validate();
+        assert_eq!(report.idle, 0);
+        assert_eq!(report.anomalies.len(), 1);
+
+        pool.visitor_counter.1.store(false, Ordering::Release);
+        pool.assert_invariants();
+    }
+
+    #[test]
+    fn clone() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(16);
+        pool.allow_expansion(false).with_overflow(4);
+
+        let mut vals: Vec<Box<usize>> = (0..16).map(|_| pool.get()).collect();
+        for (i, val) in vals.iter_mut().enumerate() {
+            **val = i;
+        }
+
+        // 2 elements stay checked out, and the 2 extra elements beyond the slots are parked in the
+        // overflow queue
+        let kept = vals.split_off(14);
+        let extra = (100..104).map(Box::new);
+        vals.into_iter().chain(extra).for_each(|val| {
+            pool.put(val);
+        });
+
+        assert_eq!(pool.len(), 16);
+        assert_eq!(pool.overflow_len(), 2);
+
+        let copy = pool.clone();
+        assert_eq!(copy.len(), 16);
+        assert_eq!(copy.overflow_len(), 2);
+        assert_eq!(copy.len_per_bucket(), pool.len_per_bucket());
+        assert!(!copy.expansion_enabled());
+        copy.assert_invariants();
+
+        // the source keeps its idle elements as they were
+        assert_eq!(pool.len(), 16);
+        assert_eq!(pool.overflow_len(), 2);
+        pool.assert_invariants();
+
+        let mut cloned: Vec<usize> = copy.into_elems().into_iter().map(|val| *val).collect();
+        cloned.sort();
+        assert_eq!(cloned, (0..14).chain(100..104).collect::<Vec<_>>());
+
+        drop(kept);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn timing_snapshot() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        assert_eq!(pool.timing_snapshot(), TimingStats::default());
+
+        for _ in 0..4 {
+            let val = pool.get();
+            pool.put(val);
+        }
+
+        let stats = pool.timing_snapshot();
+        assert_eq!(stats.get.count, 4);
+        assert_eq!(stats.put.count, 4);
+        assert!(stats.get.max <= stats.get.total);
+        assert!(stats.get.mean() <= stats.get.max);
+    }
+
+    #[test]
+    fn try_get() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        let vals: Vec<Box<usize>> = (0..8).map(|_| pool.try_get().unwrap()).collect();
+        assert_eq!(pool.miss_count(), 0);
+
+        // the starving pool won't allocate
+        assert!(pool.try_get().is_none());
+        assert_eq!(pool.outstanding(), 8);
+        assert_eq!(pool.miss_count(), 1);
+
+        for val in vals {
+            pool.put(val);
+        }
+
+        // nor wait for the write barrier
+        pool.visitor_counter.1.store(true, Ordering::SeqCst);
+        assert!(pool.try_get().is_none());
+        assert_eq!(pool.miss_count(), 2);
+
+        pool.visitor_counter.1.store(false, Ordering::SeqCst);
+        assert!(pool.try_get().is_some());
+        assert_eq!(pool.miss_count(), 2);
+    }
+
+    #[test]
+    fn try_checkout() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        let val = pool.try_checkout().unwrap();
+        assert_eq!(pool.outstanding(), 1);
+
+        pool.visitor_counter.1.store(true, Ordering::SeqCst);
+        assert_eq!(pool.try_checkout().err(), Some(CheckoutError::Barrier));
+        pool.visitor_counter.1.store(false, Ordering::SeqCst);
+
+        pool.put(val);
+        assert_eq!(pool.miss_count(), 0);
+
+        let vals: Vec<_> = (0..8).map(|_| pool.try_checkout().unwrap()).collect();
+        assert_eq!(pool.try_checkout().err(), Some(CheckoutError::Empty));
+        assert_eq!(pool.miss_count(), 1);
+        drop(vals);
+    }
+
+    #[test]
+    fn compact() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(4 * SLOT_CAP);
+        assert_eq!(pool.compact(), 0);
+        assert_eq!(pool.len(), pool.capacity());
+
+        // leave a single element in each bucket
+        let mut count = 0;
+        pool.retain(|_| {
+            count += 1;
+            count % SLOT_CAP == 1
+        });
+
+        assert_eq!(pool.slot_occupancy_histogram()[1], 4);
+        assert_eq!(pool.compact(), 3);
+
+        let histogram = pool.slot_occupancy_histogram();
+        assert_eq!(histogram[0], 3);
+        assert_eq!(histogram[4], 1);
+        assert_eq!(pool.len(), 4);
+        pool.assert_invariants();
+
+        let vals: Vec<Box<usize>> = (0..4).map(|_| pool.get()).collect();
+        assert_eq!(pool.len(), 0);
+        assert_eq!(pool.miss_count(), 0);
+
+        vals.into_iter()
+            .for_each(|val| assert!(pool.put(val).is_none()));
+        pool.assert_invariants();
+    }
+
+    #[test]
+    fn poison() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        assert!(!pool.is_poisoned());
+
+        let val = pool.get();
+        pool.poison();
+        assert!(pool.is_poisoned());
+        assert!(pool.try_get().is_none());
+        assert_eq!(pool.miss_count(), 0);
+
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| pool.get()));
+        assert!(res.is_err());
+
+        // elements can still be returned while poisoned
+        assert!(pool.put(val).is_none());
+        assert_eq!(pool.len(), 8);
+
+        pool.clear_poison();
+        assert!(pool.try_get().is_some());
+    }
+
+    #[test]
+    fn uninit_elements() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Tracked(Vec<u8>);
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut pool: SyncPool<MaybeUninit<Tracked>> = SyncPool::with_size_uninit(8);
+        assert_eq!(pool.len(), 8);
+
+        let val = pool.get_uninit();
+        let val = SyncPool::write_init(val, Tracked(vec![1, 2, 3]));
+        assert_eq!(val.0, vec![1, 2, 3]);
+
+        // the value is dropped when it's returned, and the memory is kept
+        assert!(pool.put_init(val).is_none());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.len(), 8);
+
+        let val = pool.get_uninit();
+        assert!(pool.put_uninit(val).is_none());
+
+        drop(pool);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn drop_idle_slots_only() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Default)]
+        struct Tracked;
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut pool: SyncPool<Tracked> = SyncPool::with_size(8);
+        let vals: Vec<Box<Tracked>> = (0..3).map(|_| pool.get()).collect();
+
+        // the emptied slots are uninitialized, only the elements still in the buckets are dropped
+        drop(pool);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 5);
+
+        drop(vals);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 8);
+    }
+
+    #[test]
+    fn barrier_timeout() {
+        let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(4);
+        pool.allow_expansion(true);
+        assert_eq!(pool.barrier_timeout(), Duration::from_millis(16));
+
+        // pretend a visitor is stuck in the pool, such that the barrier can't be won
+        pool.visitor_counter.0.fetch_add(1, Ordering::SeqCst);
+        pool.with_barrier_timeout(Duration::from_millis(30));
+
+        let start = Instant::now();
+        assert_eq!(pool.try_expand(1, false), Err(ExpandError::Timeout));
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert!(!pool.visitor_counter.1.load(Ordering::SeqCst));
+
+        pool.visitor_counter.0.fetch_sub(1, Ordering::SeqCst);
+        assert_eq!(pool.try_expand(1, false), Ok(1));
+    }
+
+    #[test]
+    fn access_trials() {
+        for &trials in &[1, 4, 32] {
+            let mut pool: SyncPool<usize> = SyncPool::with_size(32);
+            pool.with_access_trials(trials);
+            assert_eq!(pool.access_trials(), trials);
+
+            // tag each pooled element with a unique id, newly created elements will be 0
+            let mut id = 0;
+            pool.iter_mut_idle(|val| {
+                id += 1;
+                *val = id;
+            });
+
+            let evicted = contend(&mut pool, 4, |_, pool| {
+                let mut evicted = Vec::new();
+
+                for _ in 0..1000 {
+                    let val = pool.get();
+                    if let Some(val) = pool.put(val) {
+                        evicted.push(*val);
+                    }
+                }
+
+                evicted
+            });
+
+            let mut ids: Vec<usize> = evicted.into_iter().flatten().collect();
+
+            pool.assert_invariants();
+
+            let mut sink = Vec::new();
+            pool.take_all_into(&mut sink);
+            ids.extend(sink.into_iter().map(|val| *val));
+
+            // every tagged element is either in the pool or evicted, exactly once
+            ids.retain(|&id| id > 0);
+            ids.sort_unstable();
+            assert_eq!(ids, (1..=32).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    fn drop_handle() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        pool.with_overflow(2).drop_handle(|val| {
+            assert_eq!(*val, 0);
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let vals: Vec<_> = (0..8).map(|_| pool.get()).collect();
+        let extra = vec![Box::new(0usize), Box::new(0usize)];
+        let checked_out = pool.get();
+
+        vals.into_iter().chain(extra).for_each(|val| {
+            assert!(pool.put(val).is_none());
+        });
+
+        assert_eq!(pool.len(), 8);
+        assert_eq!(pool.overflow_len(), 2);
+
+        drop(pool);
+        drop(checked_out);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn with_drop_handler() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let mut pool: SyncPool<usize> = SyncPool::with_size(16);
+        pool.with_drop_handler(|_| {
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let vals: Vec<Box<usize>> = (0..5).map(|_| pool.get()).collect();
+        let idle = pool.len();
+        assert_eq!(idle, 11);
+
+        drop(pool);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), idle);
+
+        drop(vals);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), idle);
+    }
+
+    #[test]
+    fn capacity_hint() {
+        let pool: SyncPool<usize> = SyncPool::new();
+        assert_eq!(pool.capacity_hint(), pool.capacity());
+
+        let mut pool: SyncPool<usize> = SyncPool::with_size(20);
+        assert_eq!(pool.capacity_hint(), 20);
+        assert_eq!(pool.capacity(), 16);
+
+        pool.allow_expansion(true);
+        assert!(pool.expand(1, true));
+        assert_eq!(pool.capacity_hint(), 20);
+
+        let pool: SyncPool<usize> = SyncPool::with_capacity_exact(13);
+        assert_eq!(pool.capacity_hint(), 13);
+        assert_eq!(pool.capacity(), 16);
+        assert_eq!(pool.bucket_count(), 2);
+        assert_eq!(pool.capacity(), pool.bucket_count() * pool.slot_cap());
+
+        let pool = SyncPool::with_builder_and_size(3, || 42usize);
+        assert_eq!(pool.capacity_hint(), 3);
+        assert_eq!(pool.capacity(), SLOT_CAP);
+    }
+
+    #[test]
+    fn builder_kind() {
+        let mut pool: SyncPool<usize> = SyncPool::new();
+        assert_eq!(pool.builder_kind(), BuilderKind::Default);
+
+        assert!(pool.swap_builder(|| 42));
+        assert_eq!(pool.builder_kind(), BuilderKind::Builder);
+
+        let pool = SyncPool::with_packer(|mut val: Box<usize>| {
+            *val = 42;
+            val
+        });
+        assert_eq!(pool.builder_kind(), BuilderKind::Packer);
+    }
+
+    #[test]
+    fn get_cancellable() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        let cancel = AtomicBool::new(false);
+
+        let vals: Vec<Box<usize>> = (0..8)
+            .map(|_| pool.get_cancellable(&cancel).unwrap())
+            .collect();
+        assert_eq!(pool.len(), 0);
+
+        // the waiting get bails out once the flag is raised by another thread
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(20));
+                cancel.store(true, Ordering::SeqCst);
+            });
+
+            let start = Instant::now();
+            assert!(pool.get_cancellable(&cancel).is_none());
+            assert!(start.elapsed() >= Duration::from_millis(20));
+        });
+
+        // no element has been created in the meantime
+        vals.into_iter().for_each(|val| {
+            pool.put(val);
+        });
+        assert!(pool.get_cancellable(&cancel).is_none());
+
+        cancel.store(false, Ordering::SeqCst);
+        assert!(pool.get_cancellable(&cancel).is_some());
+        assert_eq!(pool.len(), 7);
+    }
+
+    #[test]
+    fn checkout_timed() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        let dur = Duration::from_millis(5);
+
+        let (val, outcome) = pool.checkout_timed(dur);
+        assert_eq!(outcome, CheckoutOutcome::Immediate);
+
+        let mut vals: Vec<Box<usize>> = (0..7).map(|_| pool.get()).collect();
+        vals.push(val);
+
+        // an element returned by another thread while we're waiting
+        let sink = pool.return_sink();
+        let val = vals.pop().unwrap();
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                thread::sleep(Duration::from_millis(1));
+                sink.send(val);
+            });
+
+            let (val, outcome) = pool.checkout_timed(Duration::from_secs(5));
+            assert_eq!(outcome, CheckoutOutcome::WaitedThenPooled);
+            vals.push(val);
+        });
+
+        let misses = pool.miss_count();
+        let (val, outcome) = pool.checkout_timed(dur);
+        assert_eq!(outcome, CheckoutOutcome::WaitedThenAllocated);
+        assert_eq!(pool.miss_count(), misses + 1);
+        assert_eq!(pool.outstanding(), 9);
+
+        vals.push(val);
+        vals.into_iter().for_each(|val| {
+            pool.put(val);
+        });
+        assert_eq!(pool.outstanding(), 0);
+    }
+
+    #[test]
+    fn return_sink() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(16);
+        let mut vals: Vec<Box<usize>> = (0..16).map(|_| pool.get()).collect();
+        assert_eq!(pool.outstanding(), 16);
+
+        let sink = pool.return_sink();
+        thread::scope(|scope| {
+            for i in 0..4 {
+                let sink = sink.clone();
+                let chunk: Vec<Box<usize>> = vals.drain(..4).collect();
+
+                scope.spawn(move || {
+                    for mut val in chunk {
+                        *val = i + 1;
+                        sink.send(val);
+                    }
+                });
+            }
+        });
+
+        // the returned elements are only counted once they're reclaimed
+        assert_eq!(pool.len(), 0);
+        assert_eq!(pool.outstanding(), 16);
+
+        // looking for an idle element reclaims the returned ones
+        assert!(*pool.try_get().unwrap() > 0);
+        assert_eq!(pool.len(), 15);
+
+        sink.send(Box::new(42));
+        assert_eq!(pool.reclaim_returns(), 1);
+        assert_eq!(pool.len(), 16);
+
+        sink.send(Box::new(42));
+        drop(pool);
+        sink.send(Box::new(42));
+    }
+
+    #[test]
+    fn into_elems() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let mut pool: SyncPool<usize> = SyncPool::with_size(16);
+        pool.with_overflow(2).drop_handle(|_| {
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut vals: Vec<Box<usize>> = (0..16).map(|_| pool.get()).collect();
+        vals.extend((0..2).map(|_| Box::new(0)));
+
+        let checked_out = vals.pop().unwrap();
+        for (i, mut val) in vals.into_iter().enumerate() {
+            *val = i;
+            assert!(pool.put(val).is_none());
+        }
+
+        assert_eq!(pool.overflow_len(), 1);
+
+        let mut elems: Vec<usize> = pool.into_elems().into_iter().map(|val| *val).collect();
+        elems.sort_unstable();
+
+        assert_eq!(elems, (0..17).collect::<Vec<usize>>());
+        assert_eq!(*checked_out, 0);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn try_with_builder() {
+        static BUILT: AtomicUsize = AtomicUsize::new(0);
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Conn;
+
+        impl Drop for Conn {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        fn connect() -> Result<Conn, usize> {
+            match BUILT.fetch_add(1, Ordering::SeqCst) {
+                n if n % 100 == 99 => Err(n),
+                _ => Ok(Conn),
+            }
+        }
+
+        // the 100th build fails during the pre-allocation of the second pool
+        let mut pool = SyncPool::try_with_builder(connect).ok().unwrap();
+        assert_eq!(pool.len(), 64);
+        assert_eq!(pool.capacity_hint(), 64);
+
+        assert_eq!(SyncPool::try_with_builder(connect).err(), Some(99));
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 35);
+
+        let vals: Vec<Box<Conn>> = (0..64).map(|_| pool.get()).collect();
+        assert!(pool.try_get_built::<usize>().is_ok());
+        assert_eq!(BUILT.load(Ordering::SeqCst), 101);
+
+        vals.into_iter().for_each(|val| {
+            pool.put(val);
+        });
+        assert!(pool.try_get_built::<usize>().is_ok());
+        assert_eq!(BUILT.load(Ordering::SeqCst), 101);
+    }
+
+    #[test]
+    fn slots_mask() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(4 * SLOT_CAP);
+        assert_eq!(pool.slots_mask, Some(3));
+
+        pool.allow_expansion(true);
+        assert!(pool.expand(2, true));
+        assert_eq!(pool.slots.len(), 6);
+        assert_eq!(pool.slots_mask, None);
+
+        // the positions wrap around the same way with or without the mask
+        for pos in 0..64 {
+            assert_eq!(wrap_pos(pos, 4, Some(3)), wrap_pos(pos, 4, None));
+        }
+
+        let vals: Vec<_> = (0..pool.capacity()).map(|_| pool.get()).collect();
+        vals.into_iter()
+            .for_each(|val| assert!(pool.put(val).is_none()));
+        assert_eq!(pool.len(), pool.capacity());
+
+        assert!(pool.expand(2, true));
+        assert_eq!(pool.slots_mask, Some(7));
+    }
+
+    #[test]
+    fn strict_capacity() {
+        let mut pool: SyncPool<usize> = SyncPool::with_capacity_exact(4);
+        pool.with_strict_capacity(true);
+        assert!(pool.strict_capacity());
+
+        // the empty slots can still be filled by new elements
+        let mut vals: Vec<Box<usize>> = (0..8).map(|_| pool.get()).collect();
+        assert_eq!(pool.outstanding(), 8);
+        assert_eq!(pool.available_permits(), 0);
+        assert!(pool.try_get().is_none());
+
+        // the blocked `get` is served by the element returned from another thread
+        let val = vals.pop().unwrap();
+        let shared = SharedPool(&mut pool as *mut SyncPool<usize>);
+        let handle = thread::spawn(move || {
+            let shared = shared;
+            let pool = unsafe { &mut *shared.0 };
+
+            thread::sleep(Duration::from_millis(20));
+            pool.put(val);
+        });
+
+        vals.push(pool.get());
+        handle.join().unwrap();
+        assert_eq!(pool.outstanding(), 8);
+
+        for val in vals {
+            assert!(pool.put(val).is_none());
+        }
+
+        assert_eq!(pool.outstanding(), 0);
+        assert_eq!(pool.len(), 8);
+    }
+
+    #[test]
+    fn thread_cache() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        pool.with_thread_cache(2);
+
+        let mut vals: Vec<Box<usize>> = (0..8).map(|_| pool.get()).collect();
+        assert_eq!(pool.len(), 0);
+
+        // the first 2 elements stay in the cache, the 3rd one goes back to the pool
+        for i in 1..=3 {
+            let mut val = vals.pop().unwrap();
+            *val = i;
+            assert!(pool.put(val).is_none());
+        }
+
+        assert_eq!(pool.len(), 1);
+
+        // the cache hands out the most recently returned element first
+        assert_eq!(*pool.get(), 2);
+        assert_eq!(*pool.get(), 1);
+        assert_eq!(*pool.get(), 3);
+        assert_eq!(pool.len(), 0);
+
+        // elements cached by another thread are invisible to this thread
+        let shared = SharedPool(&mut pool as *mut SyncPool<usize>);
+        thread::spawn(move || {
+            let shared = shared;
+            let pool = unsafe { &mut *shared.0 };
+            pool.put(Box::new(4));
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(pool.len(), 0);
+        pool.put(Box::new(5));
+        assert_eq!(*pool.get(), 5);
+    }
+
+    #[test]
+    fn strict_capacity_thread_cache() {
+        use std::sync::mpsc;
+
+        static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+        struct Tracked;
+
+        impl Default for Tracked {
+            fn default() -> Self {
+                LIVE.fetch_add(1, Ordering::SeqCst);
+                Tracked
+            }
+        }
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                LIVE.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut pool: SyncPool<Tracked> = SyncPool::with_size(8);
+        pool.with_thread_cache(4);
+
+        // a long-lived thread returns all the elements before the strict capacity is turned on, and
+        // keeps half of them in its own cache
+        let vals: Vec<Box<Tracked>> = (0..8).map(|_| pool.get()).collect();
+        let (done_tx, done_rx) = mpsc::channel();
+        let (quit_tx, quit_rx) = mpsc::channel::<()>();
+
+        let shared = SharedPool(&mut pool as *mut SyncPool<Tracked>);
+        let owner = thread::spawn(move || {
+            let shared = shared;
+            let pool = unsafe { &mut *shared.0 };
+
+            for val in vals {
+                pool.put(val);
+            }
+
+            done_tx.send(()).unwrap();
+            quit_rx.recv().unwrap();
+        });
+
+        done_rx.recv().unwrap();
+        pool.with_strict_capacity(true);
+        assert_eq!(pool.len(), 4);
+
+        let mut vals: Vec<Box<Tracked>> = (0..4).map(|_| pool.get()).collect();
+        assert!(pool.try_get().is_none());
+
+        // the cached elements count against the capacity, so `get` waits instead of creating one
+        let shared = SharedPool(&mut pool as *mut SyncPool<Tracked>);
+        let waiter = thread::spawn(move || {
+            let shared = shared;
+            let pool = unsafe { &mut *shared.0 };
+            pool.get()
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        assert_eq!(LIVE.load(Ordering::SeqCst), 8);
+
+        // the cached elements are dropped with the exiting thread, which frees up their permits
+        quit_tx.send(()).unwrap();
+        owner.join().unwrap();
+
+        vals.push(waiter.join().unwrap());
+        assert_eq!(LIVE.load(Ordering::SeqCst), 5);
 
-            // we've finished 1 loop but not finding a value to extract, quit
-            if trials == 0 {
-                return Some(val);
-            }
-        }
-    }
+        // the elements returned in the strict capacity mode skip the thread caches
+        let shared = SharedPool(&mut pool as *mut SyncPool<Tracked>);
+        thread::spawn(move || {
+            let shared = shared;
+            let pool = unsafe { &mut *shared.0 };
 
-    fn make_pool(size: usize, builder: ElemBuilder<T>) -> Self {
-        let mut pool = SyncPool {
-            slots: Vec::with_capacity(size),
-            curr: (AtomicUsize::new(0), AtomicUsize::new(0)),
-            visitor_counter: (AtomicUsize::new(1), AtomicBool::new(false)),
-            miss_count: AtomicUsize::new(0),
-            configure: AtomicUsize::new(0),
-            reset_handle: None,
-            builder,
-        };
+            for val in vals {
+                pool.put(val);
+            }
+        })
+        .join()
+        .unwrap();
 
-        pool.add_slots(size, true);
-        pool
+        assert_eq!(pool.len(), 5);
+        assert_eq!(pool.outstanding(), 0);
+        assert_eq!(LIVE.load(Ordering::SeqCst), 5);
     }
 
-    #[inline]
-    fn add_slots(&mut self, count: usize, fill: bool) {
-        let filler = if fill { Some(&self.builder) } else { None };
+    #[test]
+    fn try_get_exactly_restores() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
+        pool.with_thread_cache(2).with_overflow(4);
 
-        for _ in 0..count {
-            // self.slots.push(Bucket::new(fill));
-            self.slots.push(Bucket2::new(filler));
+        // park the elements in the thread cache, the slots and the overflow queue
+        let vals: Vec<Box<usize>> = (0..8).map(|_| pool.get()).collect();
+        for (i, mut val) in vals.into_iter().enumerate() {
+            *val = i + 1;
+            pool.put(val);
         }
+
+        for i in 9..=12 {
+            pool.put(Box::new(i));
+        }
+
+        assert_eq!(pool.len(), 8);
+        assert_eq!(pool.overflow_len(), 2);
+
+        assert!(pool.try_get_exactly(20).is_none());
+        assert_eq!(pool.len(), 8);
+        assert_eq!(pool.overflow_len(), 2);
+        assert_eq!(pool.outstanding(), 0);
+
+        // the cached elements are still handed out first, in the same order
+        assert_eq!(*pool.try_get().unwrap(), 2);
+        assert_eq!(*pool.try_get().unwrap(), 1);
+
+        let mut vals: Vec<usize> = iter::from_fn(|| pool.try_get()).map(|val| *val).collect();
+        vals.sort_unstable();
+        assert_eq!(vals, (3..=12).collect::<Vec<usize>>());
     }
 
-    fn update_config(&mut self, mask: usize, target: bool) {
-        let mut config = self.configure.load(Ordering::SeqCst);
+    #[test]
+    fn checkout_batch() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
 
-        while let Err(old) = self.configure.compare_exchange(
-            config,
-            config ^ mask,
-            Ordering::SeqCst,
-            Ordering::Relaxed,
-        ) {
-            if !((old & mask > 0) ^ target) {
-                // the configure already matches, we're done
-                return;
-            }
+        {
+            let mut batch = pool.checkout_batch(4);
+            assert_eq!(batch.len(), 4);
 
-            config = old;
+            for val in batch.iter_mut() {
+                **val = 7;
+            }
         }
-    }
-}
 
-impl<T> Default for SyncPool<T>
-where
-    T: Default,
-{
-    fn default() -> Self {
-        SyncPool::new()
+        assert_eq!(pool.len(), 8);
+        pool.assert_invariants();
+
+        let vals: Vec<Box<usize>> = (0..8).map(|_| pool.get()).collect();
+        assert_eq!(vals.iter().filter(|val| ***val == 7).count(), 4);
     }
-}
 
-impl<T> Drop for SyncPool<T> {
-    fn drop(&mut self) {
-        self.slots.clear();
+    #[test]
+    fn checkout() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
 
-        // now drop the reset handle if it's not null
-        self.reset_handle.take();
+        {
+            let mut val = pool.checkout();
+            *val = 7;
+            assert_eq!(val.pool.outstanding(), 1);
+        }
+
+        assert_eq!(pool.len(), 8);
+        assert_eq!(pool.outstanding(), 0);
+
+        let val = pool.checkout().into_inner();
+        assert_eq!(pool.len(), 7);
+        assert_eq!(pool.outstanding(), 1);
+        pool.put(val);
+
+        // the element is dropped rather than returned while the write barrier is raised
+        let guard = pool.checkout();
+        guard.pool.visitor_counter.1.store(true, Ordering::Release);
+        drop(guard);
+
+        pool.visitor_counter.1.store(false, Ordering::Release);
+        assert_eq!(pool.len(), 7);
+        assert_eq!(pool.outstanding(), 0);
+        pool.assert_invariants();
     }
-}
 
-pub trait PoolState {
-    fn expansion_enabled(&self) -> bool;
+    #[test]
+    fn retain() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(8);
 
-    fn miss_count(&self) -> usize;
+        let vals: Vec<Box<usize>> = (0..8)
+            .map(|i| {
+                let mut val = pool.get();
+                *val = i;
+                val
+            })
+            .collect();
 
-    fn capacity(&self) -> usize;
+        vals.into_iter().for_each(|val| {
+            assert!(pool.put(val).is_none());
+        });
 
-    fn len(&self) -> usize;
+        assert_eq!(pool.retain(|val| val % 2 == 0), 4);
+        assert_eq!(pool.len(), 4);
+        pool.assert_invariants();
 
-    fn is_empty(&self) -> bool {
-        self.len() == 0
-    }
-}
+        let drained = pool.drain_filter(|&val| val < 4);
+        assert_eq!(drained.len(), 2);
+        assert!(drained.iter().all(|val| **val < 4));
+        assert_eq!(pool.len(), 2);
+        assert!(pool.drain_filter(|&val| val < 4).is_empty());
+        pool.assert_invariants();
 
-impl<T> PoolState for SyncPool<T> {
-    fn expansion_enabled(&self) -> bool {
-        let configure = self.configure.load(Ordering::SeqCst);
-        configure & CONFIG_ALLOW_EXPANSION > 0
-    }
+        let mut sink = Vec::new();
+        pool.take_all_into(&mut sink);
+        assert!(sink.iter().all(|val| **val % 2 == 0));
 
-    fn miss_count(&self) -> usize {
-        self.miss_count.load(Ordering::Acquire)
+        // the vacated slots can be refilled
+        (0..8).for_each(|i| {
+            assert!(pool.put(Box::new(i)).is_none());
+        });
+        assert_eq!(pool.len(), 8);
     }
 
-    fn capacity(&self) -> usize {
-        self.slots.len() * SLOT_CAP
-    }
+    #[test]
+    fn get_or_new_with() {
+        let mut pool: SyncPool<Vec<u8>> = SyncPool::with_builder_and_size(8, || vec![0u8; 64]);
 
-    fn len(&self) -> usize {
-        self.slots
-            .iter()
-            .fold(0, |sum, item| sum + item.size_hint())
-    }
-}
+        let warmed: Vec<Box<Vec<u8>>> = (0..8)
+            .map(|_| pool.get_or_new_with(|| Box::new(Vec::new())))
+            .collect();
 
-pub trait PoolManager<T> {
-    fn reset_handle(&mut self, handle: fn(&mut T)) -> &mut Self;
-    fn allow_expansion(&mut self, allow: bool) -> &mut Self;
-    fn expand(&mut self, additional: usize, block: bool) -> bool;
-    fn refill(&mut self, count: usize) -> usize;
-}
+        assert!(warmed.iter().all(|val| val.len() == 64));
+        assert_eq!(pool.miss_count(), 0);
 
-/// The pool manager that provide many useful utilities to keep the SyncPool close to the needs of
-/// the caller program.
-impl<T> PoolManager<T> for SyncPool<T> {
-    /// Set or update the reset handle. If set, the reset handle will be invoked every time an element
-    /// has been returned back to the pool (i.e. calling the `put` method), regardless of if the element
-    /// is created by the pool or not.
-    fn reset_handle(&mut self, handle: fn(&mut T)) -> &mut Self {
-        // busy waiting ... for the first chance a barrier owned by someone else is lowered
-        let mut count: usize = 8;
-        let timeout = Instant::now().add(Duration::from_millis(16));
+        let fallback = pool.get_or_new_with(|| Box::new(vec![1u8; 4]));
+        assert_eq!(*fallback, vec![1u8; 4]);
+        assert_eq!(pool.miss_count(), 1);
+    }
 
-        loop {
-            match self.visitor_counter.1.compare_exchange(
-                false,
-                true,
-                Ordering::SeqCst,
-                Ordering::Relaxed,
-            ) {
-                Ok(_) => break,
-                Err(_) => {
-                    cpu_relax(count);
-
-                    // update the counter (and the busy wait period)
-                    count -= 1;
+    #[test]
+    fn overflow() {
+        let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(8);
+        pool.with_overflow(2);
 
-                    if count < 4 {
-                        // yield the thread for later try
-                        thread::yield_now();
-                    } else if Instant::now() > timeout {
-                        // don't block for more than 16ms
-                        return self;
-                    }
-                }
+        let extra: Vec<Box<[u8; 32]>> = (0..3).map(|_| Box::new([0u8; 32])).collect();
+        let mut returned = 0;
+
+        for val in extra {
+            if pool.put(val).is_some() {
+                returned += 1;
             }
         }
 
-        self.reset_handle.replace(handle);
+        assert_eq!(returned, 1);
+        assert_eq!(pool.overflow_len(), 2);
+        assert_eq!(pool.len(), 8);
 
-        self.visitor_counter.1.store(false, Ordering::SeqCst);
-        self
+        let taken: Vec<Box<[u8; 32]>> = (0..10).map(|_| pool.get()).collect();
+        assert_eq!(taken.len(), 10);
+        assert_eq!(pool.overflow_len(), 0);
+        assert_eq!(pool.miss_count(), 0);
     }
 
-    /// Set or update the settings that if we will allow the `SyncPool` to be expanded.
-    fn allow_expansion(&mut self, allow: bool) -> &mut Self {
-        if !(self.expansion_enabled() ^ allow) {
-            // not flipping the configuration, return
-            return self;
-        }
+    struct SharedPool<T>(*mut SyncPool<T>);
 
-        self.update_config(CONFIG_ALLOW_EXPANSION, allow);
-        self
+    unsafe impl<T> Send for SharedPool<T> {}
+
+    /// Run `f` on `threads` threads visiting the same pool at the same time, just like a `static mut`
+    /// pool would be, and collect the results in the order of the thread indices passed to `f`.
+    fn contend<T, R, F>(pool: &mut SyncPool<T>, threads: usize, f: F) -> Vec<R>
+    where
+        R: Send,
+        F: Fn(usize, &mut SyncPool<T>) -> R + Sync,
+    {
+        let f = &f;
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|i| {
+                    let shared = SharedPool(&mut *pool as *mut SyncPool<T>);
+
+                    scope.spawn(move || {
+                        let shared = shared;
+                        f(i, unsafe { &mut *shared.0 })
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        })
     }
 
-    /// Try to expand the `SyncPool` and add more elements to it. Usually invoke this API only when
-    /// the caller is certain that the pool is under pressure, and that a short block to the access
-    /// of the pool won't cause serious issues, since the function will block the current caller's
-    /// thread until it's finished (i.e. get the opportunity to raise the writer's barrier and wait
-    /// everyone to leave).
-    ///
-    /// If we're unable to expand the pool, it's due to one of the following reasons: 1) someone has
-    /// already raised the writer's barrier and is likely modifying the pool, we will leave immediately,
-    /// and it's up to the caller if they want to try again; 2) we've waited too long but still couldn't
-    /// obtain an exclusive access to the pool, and similar to reason 1), we will quit now.
-    fn expand(&mut self, additional: usize, block: bool) -> bool {
-        // if the pool isn't allowed to expand, just return
-        if !self.expansion_enabled() {
-            return false;
-        }
+    #[test]
+    fn concurrent_get_put() {
+        let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(32);
 
-        // if exceeding the upper limit, quit
-        if self.slots.len() > EXPANSION_CAP {
-            return false;
-        }
+        contend(&mut pool, 4, |_, pool| {
+            for _ in 0..1000 {
+                let val = pool.get();
+                pool.put(val);
+            }
+        });
 
-        // raise the write barrier now, if someone has already raised the flag to indicate the
-        // intention to write, let me go away.
-        if self
-            .visitor_counter
-            .1
-            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Acquire)
-            .is_err()
-        {
-            return false;
+        pool.assert_invariants();
+        assert!(pool.len() <= pool.capacity());
+    }
+
+    #[test]
+    fn concurrent_reset_swap() {
+        let mut pool: SyncPool<Vec<u8>> = SyncPool::with_size(32);
+        pool.with_barrier_timeout(Duration::from_secs(5));
+
+        fn counting(resets: &Arc<AtomicUsize>) -> impl FnMut(&mut Vec<u8>) + Send {
+            let resets = Arc::clone(resets);
+            move |val: &mut Vec<u8>| {
+                val.clear();
+                resets.fetch_add(1, Ordering::SeqCst);
+            }
         }
 
-        // busy waiting ... for all visitors to leave
-        let mut count: usize = 8;
-        let safe = loop {
-            match self
-                .visitor_counter
-                .0
-                .compare_exchange(1, 0, Ordering::SeqCst, Ordering::Relaxed)
-            {
-                Ok(_) => break true,
-                Err(_) => {
-                    cpu_relax(2);
-                    count -= 1;
+        let resets = Arc::new(AtomicUsize::new(0));
+        assert!(pool.set_reset_handle_dyn(Box::new(counting(&resets))));
 
-                    if count < 4 {
-                        thread::yield_now();
-                    } else if !block {
-                        break false;
-                    }
-                }
+        // the first thread keeps swapping the handle while the others are putting the elements back
+        let swaps = contend(&mut pool, 4, |i, pool| {
+            if i == 0 {
+                return (0..50)
+                    .filter(|_| pool.set_reset_handle_dyn(Box::new(counting(&resets))))
+                    .count();
             }
-        };
 
-        if safe {
-            // update the slots by pushing `additional` slots
-            self.add_slots(additional, true);
-            self.miss_count.store(0, Ordering::Release);
-        }
+            for _ in 0..1000 {
+                let mut val = pool.get();
+                assert!(val.is_empty());
 
-        // update the internal states
-        self.visitor_counter.0.store(1, Ordering::SeqCst);
-        self.visitor_counter.1.store(false, Ordering::Release);
+                val.push(1);
+                pool.put(val);
+            }
+
+            0
+        });
 
-        safe
+        // every swap went through, and every returned element has been reset exactly once
+        assert_eq!(swaps[0], 50);
+        assert_eq!(resets.load(Ordering::SeqCst), 3000);
+        pool.assert_invariants();
     }
 
-    /// Due to contentious access to the pool, sometimes the `put` action could not finish and return
-    /// the element to the pool successfully. Overtime, this could cause the number of elements in the
-    /// pool to dwell. This would only happen slowly if we're running a very contentious multithreading
-    /// program, but it surely could happen. If the caller detects such situation, they can invoke the
-    /// `refill` API and try to refill the pool with elements.
-    ///
-    /// We will try to refill as many elements as requested
-    fn refill(&mut self, additional: usize) -> usize {
-        let cap = self.capacity();
-        let empty_slots = cap - self.len();
+    #[test]
+    fn concurrent_len() {
+        let mut pool: SyncPool<[u8; 32]> = SyncPool::with_size(32);
+        let done = Arc::new(AtomicBool::new(false));
 
-        if empty_slots == 0 {
-            return 0;
-        }
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = SharedPool(&mut pool as *mut SyncPool<[u8; 32]>);
 
-        let quota = if additional > empty_slots {
-            empty_slots
-        } else {
-            additional
-        };
+                thread::spawn(move || {
+                    let shared = shared;
+                    let pool = unsafe { &mut *shared.0 };
 
-        let mut count = 0;
-        let timeout = Instant::now().add(Duration::from_millis(16));
+                    for _ in 0..2000 {
+                        let val = pool.get();
+                        pool.put(val);
+                    }
+                })
+            })
+            .collect();
 
-        // try to put `quota` number of elements into the pool
-        while count < quota {
-            let mut val = make_elem(&self.builder);
-            let mut runs = 0;
+        let checker = {
+            let shared = SharedPool(&mut pool as *mut SyncPool<[u8; 32]>);
+            let done = Arc::clone(&done);
 
-            // retry to put the allocated element into the pool.
-            while let Some(ret) = self.put(val) {
-                val = ret;
-                runs += 1;
+            thread::spawn(move || {
+                let shared = shared;
+                let pool = unsafe { &*shared.0 };
 
-                // timeout
-                if Instant::now() > timeout {
-                    return count;
+                while !done.load(Ordering::Acquire) {
+                    assert!(pool.len() <= pool.capacity());
                 }
+            })
+        };
 
-                // check the pool length for every 4 failed attempts to put the element into the pool.
-                if runs % 4 == 0 && self.len() == cap {
-                    return count;
-                }
+        for handle in handles {
+            handle.join().unwrap();
+        }
 
-                // relax a bit
-                if runs > 8 {
-                    thread::yield_now();
-                } else {
-                    cpu_relax(runs / 2);
-                }
-            }
+        done.store(true, Ordering::Release);
+        checker.join().unwrap();
 
-            count += 1;
+        pool.assert_invariants();
+    }
+
+    #[test]
+    fn numa_affinity_range() {
+        let numa = NumaAffinity::with_nodes(4);
+
+        // each node owns a contiguous quarter of the buckets
+        for node in 0..4 {
+            pin_current_node(node);
+            assert_eq!(numa.range(16), Some((4 * node, 4)));
         }
 
-        count
-    }
-}
+        // the ranges cover all the buckets even if they can't be split evenly
+        let mut next = 0;
+        for node in 0..4 {
+            pin_current_node(node);
+            let (start, len) = numa.range(10).unwrap();
+            assert_eq!(start, next);
+            next += len;
+        }
 
-#[cfg(test)]
-mod pool_tests {
-    use super::*;
-    use std::vec;
+        assert_eq!(next, 10);
 
-    struct BigStruct {
-        a: u32,
-        b: u32,
-        c: Vec<u8>,
+        // with fewer buckets than nodes, the nodes share the buckets
+        pin_current_node(0);
+        assert_eq!(numa.range(2), Some((0, 1)));
+        pin_current_node(3);
+        assert_eq!(numa.range(2), Some((1, 1)));
+
+        // a node beyond the partition falls back to the shared position
+        pin_current_node(4);
+        assert_eq!(numa.range(16), None);
     }
 
-    impl BigStruct {
-        fn new() -> Self {
-            BigStruct {
-                a: 1,
-                b: 42,
-                c: vec::from_elem(0u8, 0x1_000_000),
-            }
+    #[test]
+    fn numa_affinity_cursor() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(128);
+        assert_eq!(pool.bucket_count(), 16);
+
+        // the topology is only available on Linux with the feature
+        if !cfg!(all(feature = "numa", target_os = "linux")) {
+            pool.with_numa_affinity(true);
+            assert!(!pool.numa_affinity());
         }
 
-        fn initializer(mut self: Box<Self>) -> Box<Self> {
-            self.a = 1;
-            self.b = 42;
-            self.c = vec::from_elem(0u8, 0x1_000_000);
+        // inject a topology of 4 nodes, where the current thread runs on the 3rd one
+        pool.numa = Some(NumaAffinity::with_nodes(4));
+        pin_current_node(2);
+        pool.curr.0.store(5, Ordering::Release);
 
-            self
-        }
+        // the shared position is confined to the buckets owned by the node, and moving on doesn't
+        // touch the shared position
+        let cursor = Cursor::new(&pool.curr.0, None, pool.numa.as_ref());
+        assert_eq!(cursor.start(16), 9);
+        assert_eq!(cursor.advance(9), 10);
+        assert_eq!(pool.curr.0.load(Ordering::Acquire), 5);
+
+        // so `get` takes the element from the node's bucket, and settles the cursor there
+        let val = pool.get();
+        assert_eq!(pool.len_per_bucket()[9], 7);
+        assert_eq!(pool.curr.0.load(Ordering::Acquire), 9);
+
+        pool.put(val);
+        pool.assert_invariants();
+
+        pool.with_numa_affinity(false);
+        assert!(!pool.numa_affinity());
     }
 
     #[test]
-    fn use_packer() {
-        let mut pool = SyncPool::with_packer(BigStruct::initializer);
+    fn thread_affinity() {
+        let mut pool: SyncPool<usize> = SyncPool::with_size(32);
+        pool.with_thread_affinity(true);
+        assert!(pool.thread_affinity());
 
-        let big_box = pool.get();
+        contend(&mut pool, 4, |i, pool| {
+            for _ in 0..1000 {
+                let mut vals: Vec<_> = (0..4).map(|_| pool.get()).collect();
+                vals.iter_mut().for_each(|val| **val = i);
+                vals.into_iter().for_each(|val| {
+                    pool.put(val);
+                });
+            }
+        });
 
-        assert_eq!(big_box.a, 1);
-        assert_eq!(big_box.b, 42);
-        assert_eq!(big_box.c.len(), 0x1_000_000);
+        pool.assert_invariants();
+        assert!(pool.len() <= pool.capacity());
+        assert_eq!(pool.outstanding(), 0);
+
+        pool.with_thread_affinity(false);
+        assert!(!pool.thread_affinity());
+
+        let vals: Vec<_> = (0..pool.len()).map(|_| pool.get()).collect();
+        assert_eq!(pool.len(), 0);
+        drop(vals);
     }
 
     #[test]
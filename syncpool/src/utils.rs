@@ -3,11 +3,21 @@
 use crate::boxed::make_box;
 use crate::bucket::SLOT_CAP;
 use crate::pool::ElemBuilder;
-use std::sync::atomic;
+use std::hint;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
-const GET_MASK: u16 = 0b1010_1010_1010_1010;
-const PUT_MASK: u16 = 0b1111_1111_1111_1111;
-const FULL_FLAG: u16 = 0b0101_0101_0101_0101;
+/// The masks of the bitmap of a bucket, which are cut down to the width of the bucket, see
+/// `width_mask`.
+const GET_MASK: u64 = 0xAAAA_AAAA_AAAA_AAAA;
+const PUT_MASK: u64 = u64::MAX;
+const FULL_FLAG: u64 = 0x5555_5555_5555_5555;
+
+/// The maximum exponent of the spin count in `cpu_relax`, i.e. we will spin at most 64 times.
+const RELAX_CAP: usize = 6;
+
+/// The cached number of CPUs available to the program, 0 if it hasn't been queried yet.
+static PARALLELISM: AtomicUsize = AtomicUsize::new(0);
 
 pub(crate) fn make_elem<T>(builder: &ElemBuilder<T>) -> Box<T> {
     match builder {
@@ -17,67 +27,97 @@ pub(crate) fn make_elem<T>(builder: &ElemBuilder<T>) -> Box<T> {
             let boxed: Box<T> = make_box(f);
             boxed
         }
+        ElemBuilder::TryBuilder(f) => match f.build() {
+            Ok(val) => Box::new(val),
+            Err(_) => panic!("the builder has failed to create the element"),
+        },
+    }
+}
+
+/// Same as `make_elem`, but hand the error of the fallible builder back, which must be of type `E`.
+pub(crate) fn try_make_elem<T, E: 'static>(builder: &ElemBuilder<T>) -> Result<Box<T>, E> {
+    match builder {
+        ElemBuilder::TryBuilder(f) => f.build().map(Box::new).map_err(|err| match err.downcast() {
+            Ok(err) => *err,
+            Err(_) => panic!("the error type doesn't match the one of the builder"),
+        }),
+        _ => Ok(make_elem(builder)),
     }
 }
 
+/// Back off for `2 ^ count` spins, where the exponent is capped at `RELAX_CAP`. On a uniprocessor
+/// (or a container limited to a single CPU), spinning only burns the time slice of the thread that
+/// we're waiting for, so we yield the thread instead.
 #[inline(always)]
 pub(crate) fn cpu_relax(count: usize) {
-    for _ in 0..(1 << count) {
-        atomic::spin_loop_hint()
+    if parallelism() == 1 {
+        thread::yield_now();
+        return;
+    }
+
+    for _ in 0..(1 << count.min(RELAX_CAP)) {
+        hint::spin_loop()
     }
 }
 
-pub(crate) fn check_len(src: u16) -> usize {
-    match src & FULL_FLAG {
-        0 => 0,
-        FULL_FLAG => 8,
-        mut base => {
-            let mut count = 0;
+#[inline]
+fn parallelism() -> usize {
+    let count = PARALLELISM.load(Ordering::Relaxed);
+    if count > 0 {
+        return count;
+    }
 
-            while base > 0 {
-                if base & 1 == 1 {
-                    count += 1;
-                }
+    let count = thread::available_parallelism().map_or(1, |n| n.get());
+    PARALLELISM.store(count, Ordering::Relaxed);
 
-                base >>= 2;
-            }
+    count
+}
 
-            count
-        }
-    }
+/// The mask covering the bits of the first `width` slots, i.e. both the element bit and the lock
+/// bit of each slot.
+#[inline(always)]
+pub(crate) fn width_mask(width: usize) -> u64 {
+    PUT_MASK >> (64 - 2 * width)
+}
+
+pub(crate) fn check_len(src: u64) -> usize {
+    (src & FULL_FLAG).count_ones() as usize
 }
 
-/// Assuming we have 8 elements per slot, otherwise must update the assumption.
-pub(crate) fn enter(src: u16, get: bool) -> Result<u16, ()> {
+/// Locate the lowest position out of the first `width` slots that can fulfil the request.
+pub(crate) fn enter(src: u64, width: usize, get: bool) -> Result<usize, ()> {
+    let mask = width_mask(width);
+    let src = src & mask;
+
     // get the base bits to check on. If we're not going to meet the needs, terminate early.
     let mut base = if get {
         if src == 0 {
             return Err(());
         }
 
-        src ^ GET_MASK
+        src ^ (GET_MASK & mask)
     } else {
-        if src == FULL_FLAG {
+        if src == FULL_FLAG & mask {
             return Err(());
         }
 
-        src ^ PUT_MASK
+        src ^ mask
     };
 
     // find the starting position for the spot check
-    let mut pos: u16 = {
+    let mut pos: usize = {
         // a little trick: pre-calculate the starting point for finding the location
-        let val = (base & PUT_MASK).trailing_zeros() as u16;
+        let val = (base & mask).trailing_zeros() as usize;
 
-        // if bit 15 (or above) is 0, then we won't find a location in this bucket, skip the
-        // remainder logic/loop.
-        if val > 14 {
+        // if the lock bit of the last slot (or above) is 0, then we won't find a location in this
+        // bucket, skip the remainder logic/loop.
+        if val > 2 * width - 2 {
             return Err(());
         }
 
         if val % 2 == 1 {
             base >>= val + 1;
-            (val + 1) / 2
+            val.div_ceil(2)
         } else {
             base >>= val;
             val / 2
@@ -97,15 +137,26 @@ pub(crate) fn enter(src: u16, get: bool) -> Result<u16, ()> {
     Err(())
 }
 
-/// Assuming we have 8 elements per slot. A wrapper over the out-state
+/// Same as `enter`, but locate the highest position in the bucket that can fulfil the request.
+pub(crate) fn enter_last(src: u64, width: usize, get: bool) -> Result<usize, ()> {
+    // the state of the position we're looking for: no lock bit, and the element bit set for `get`
+    let target = if get { 0b01 } else { 0b00 };
+
+    (0..width)
+        .rev()
+        .find(|pos| (src >> (2 * pos)) & 0b11 == target)
+        .ok_or(())
+}
+
+/// A wrapper over the out-state
 #[inline]
-pub(crate) fn exit(src: u16, pos: u16) -> Result<u16, ()> {
+pub(crate) fn exit(src: u64, pos: usize) -> Result<u64, ()> {
     out_state(src, 2 * pos)
 }
 
 /// `2 * pos` -> `padded_pos` is where the enter bit locates for slice position `pos`
 #[inline(always)]
-fn in_state(origin: u16, pad_pos: u16) -> Result<u16, ()> {
+fn in_state(origin: u64, pad_pos: usize) -> Result<u64, ()> {
     // the intended state after mark the enter bit
     let next = origin | (0b10 << pad_pos);
 
@@ -121,7 +172,7 @@ fn in_state(origin: u16, pad_pos: u16) -> Result<u16, ()> {
 
 /// `2 * pos` -> `padded_pos` is where the enter bit locates for slice position `pos`
 #[inline(always)]
-fn out_state(origin: u16, pad_pos: u16) -> Result<u16, ()> {
+fn out_state(origin: u64, pad_pos: usize) -> Result<u64, ()> {
     // only update if the position is marked, otherwise it will be deadlocked
     if (origin & (0b10 << pad_pos)) == 0 {
         return Err(());
@@ -138,30 +189,48 @@ mod utils_test {
     #[test]
     fn access_pass() {
         let test1 = 0b0101010001010100;
-        assert_eq!(enter(test1, false), Ok(0));
-        assert_eq!(enter(test1, true), Ok(1));
+        assert_eq!(enter(test1, 8, false), Ok(0));
+        assert_eq!(enter(test1, 8, true), Ok(1));
 
         let test2 = 0b0101010001010101;
-        assert_eq!(enter(test2, false), Ok(4));
-        assert_eq!(enter(test2, true), Ok(0));
+        assert_eq!(enter(test2, 8, false), Ok(4));
+        assert_eq!(enter(test2, 8, true), Ok(0));
 
         let test3 = 0b0101010001010111;
-        assert_eq!(enter(test3, false), Ok(4));
-        assert_eq!(enter(test3, true), Ok(1));
+        assert_eq!(enter(test3, 8, false), Ok(4));
+        assert_eq!(enter(test3, 8, true), Ok(1));
 
         let test4 = 0b0101010001011011;
-        assert_eq!(enter(test4, false), Ok(4));
-        assert_eq!(enter(test4, true), Ok(2));
+        assert_eq!(enter(test4, 8, false), Ok(4));
+        assert_eq!(enter(test4, 8, true), Ok(2));
+    }
+
+    #[test]
+    fn access_wide() {
+        // the first 8 slots are full, and only the last slot of the 16 is holding an element
+        let test1 = 0x4000_5555;
+        assert_eq!(enter(test1, 16, false), Ok(8));
+        assert_eq!(enter(test1, 16, true), Ok(0));
+        assert_eq!(enter(test1, 8, false), Err(()));
+        assert_eq!(enter_last(test1, 16, true), Ok(15));
+        assert_eq!(check_len(test1), 9);
+
+        // all 32 slots are full, and the last one is locked
+        let test2 = 0x5555_5555_5555_5555 | (0b10 << 62);
+        assert_eq!(enter(test2, 32, false), Err(()));
+        assert_eq!(enter(test2, 32, true), Ok(0));
+        assert_eq!(enter_last(test2, 32, true), Ok(30));
+        assert_eq!(check_len(test2), 32);
     }
 
     #[test]
     fn access_deny() {
         let test1 = 0b0010000000000000;
-        assert_eq!(enter(test1, false), Ok(0));
-        assert_eq!(enter(test1, true), Err(()));
+        assert_eq!(enter(test1, 8, false), Ok(0));
+        assert_eq!(enter(test1, 8, true), Err(()));
 
         let test2 = 0b0111010101010111;
-        assert_eq!(enter(test2, false), Err(()));
-        assert_eq!(enter(test2, true), Ok(1));
+        assert_eq!(enter(test2, 8, false), Err(()));
+        assert_eq!(enter(test2, 8, true), Ok(1));
     }
 }
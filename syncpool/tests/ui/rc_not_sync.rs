@@ -0,0 +1,14 @@
+extern crate syncpool;
+
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread;
+use syncpool::prelude::*;
+
+fn main() {
+    let pool: Arc<SyncPool<Rc<u8>>> = Arc::new(SyncPool::new());
+
+    thread::spawn(move || {
+        let _ = pool.len();
+    });
+}